@@ -0,0 +1,69 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// build metadata for `aurora version`; anything that can't be found (no git in a tarball build,
+// say) is left empty
+fn main() {
+    let commit = git(&["rev-parse", "HEAD"]).unwrap_or_default();
+    let dirty = !commit.is_empty() && git(&["status", "--porcelain", "--untracked-files=no"]).is_some_and(|s| !s.is_empty());
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .unwrap_or_default();
+
+    // the --version line, e.g. "0.1.0 (1a2b3c4-dirty 2026-10-14)"
+    let date = build_date();
+    let short = match commit.get(..7) {
+        Some(short) if dirty => format!("{}-dirty ", short),
+        Some(short) => format!("{} ", short),
+        None => String::new(),
+    };
+    println!("cargo:rustc-env=AURORA_VERSION={} ({}{})", env::var("CARGO_PKG_VERSION").unwrap_or_default(), short, date);
+    println!("cargo:rustc-env=AURORA_COMMIT={}", commit);
+    println!("cargo:rustc-env=AURORA_DIRTY={}", dirty);
+    println!("cargo:rustc-env=AURORA_BUILD_DATE={}", date);
+    println!("cargo:rustc-env=AURORA_RUSTC={}", rustc);
+    println!("cargo:rustc-env=AURORA_TARGET={}", env::var("TARGET").unwrap_or_default());
+
+    // a missing path would make cargo rerun this on every build
+    for path in [".git/HEAD", ".git/index", ".git/refs"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// YYYY-MM-DD, from SOURCE_DATE_EPOCH for reproducible builds
+fn build_date() -> String {
+    let secs = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0));
+
+    // civil-from-days, the same as utils::format_timestamp
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
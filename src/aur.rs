@@ -0,0 +1,463 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+use serde::Deserialize;
+use crate::config::Config;
+use tracing::{debug, trace};
+use crate::runner::Run;
+use crate::state;
+use crate::utils;
+
+const RPC_URL: &str = "https://aur.archlinux.org/rpc/v5";
+// the AUR rejects longer request URIs
+const MAX_URI_LENGTH: usize = 4400;
+// courtesy pause between requests, and the backoff steps for 429 and 5xx answers
+const REQUEST_INTERVAL: Duration = Duration::from_millis(200);
+const RETRY_DELAYS: [u64; 3] = [1, 2, 4];
+
+static REFRESH: OnceLock<bool> = OnceLock::new();
+static WRITES: AtomicU64 = AtomicU64::new(0);
+static LAST_REQUEST: Mutex<Option<Instant>> = Mutex::new(None);
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct AurPackage {
+    pub name: String,
+    pub version: String,
+    // the repository split packages share; cgit knows packages by this name
+    #[serde(default)]
+    pub package_base: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default, rename = "URL")]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub maintainer: Option<String>,
+    #[serde(default)]
+    pub out_of_date: Option<u64>,
+    #[serde(default)]
+    pub num_votes: u64,
+    #[serde(default)]
+    pub popularity: f64,
+    #[serde(default)]
+    pub depends: Vec<String>,
+    #[serde(default)]
+    pub make_depends: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    results: Vec<AurPackage>,
+}
+
+// keyed by name; packages missing from the AUR are left out. the names are split over
+// as many requests as the AUR's URI length limit needs
+pub fn info(names: &[String]) -> Result<BTreeMap<String, AurPackage>, String> {
+    let ttl = Config::load().rpc.info_ttl;
+    let mut found = BTreeMap::new();
+    for url in info_urls(RPC_URL, names) {
+        found.extend(request(&url, ttl)?.into_iter().map(|p| (p.name.clone(), p)));
+    }
+    Ok(found)
+}
+
+fn info_urls(rpc_url: &str, names: &[String]) -> Vec<String> {
+    let base = format!("{}/info?", rpc_url);
+    let mut urls: Vec<String> = Vec::new();
+    for name in names {
+        let arg = format!("arg[]={}", url_encode(name));
+        match urls.last_mut() {
+            Some(url) if url.len() + arg.len() < MAX_URI_LENGTH => {
+                url.push('&');
+                url.push_str(&arg);
+            }
+            _ => urls.push(format!("{}{}", base, arg)),
+        }
+    }
+    urls
+}
+
+// most popular first; `by` is an RPC search field such as "name-desc" or "maintainer"
+pub fn search(query: &str, by: &str) -> Result<Vec<AurPackage>, String> {
+    let url = format!("{}/search/{}?by={}", RPC_URL, url_encode(query), by);
+    let mut results = request(&url, Config::load().rpc.search_ttl)?;
+    results.sort_by(|a, b| b.popularity.total_cmp(&a.popularity).then_with(|| a.name.cmp(&b.name)));
+    Ok(results)
+}
+
+// --refresh skips the cache, but still stores the new answers in it
+pub fn set_refresh(refresh: bool) {
+    let _ = REFRESH.set(refresh);
+}
+
+fn request(url: &str, ttl: u64) -> Result<Vec<AurPackage>, String> {
+    let cached = cache_file(url);
+    if !REFRESH.get().copied().unwrap_or(false) && is_fresh(&cached, ttl) {
+        if let Ok(results) = fs::read(&cached).map_err(|e| e.to_string()).and_then(|body| parse(&body)) {
+            trace!("cached answer for {} in {}", url, cached.display());
+            return Ok(results);
+        }
+    }
+
+    debug!("asking the AUR: {}", url);
+    let body = curl(url)?;
+    let results = parse(&body)?;
+    store(&cached, &body);
+    Ok(results)
+}
+
+fn parse(body: &[u8]) -> Result<Vec<AurPackage>, String> {
+    let response: Response = serde_json::from_slice(body)
+        .map_err(|e| format!("unexpected response from the AUR: {}", e))?;
+    if response.kind == "error" {
+        return Err(response.error.unwrap_or_else(|| "the AUR returned an error".to_string()));
+    }
+    Ok(response.results)
+}
+
+pub fn cache_dir() -> PathBuf {
+    state::state_dir().join("rpc-cache")
+}
+
+// one file per request url, named by its FNV-1a hash
+fn cache_file(url: &str) -> PathBuf {
+    let hash = url
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3));
+    cache_dir().join(format!("{:016x}.json", hash))
+}
+
+fn is_fresh(path: &Path, ttl: u64) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < Duration::from_secs(ttl))
+}
+
+// written to a private file first so concurrent lookups never see half a response
+fn store(path: &Path, body: &[u8]) {
+    let dir = cache_dir();
+    if fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+    let tmp = dir.join(format!(".{}.{}", std::process::id(), WRITES.fetch_add(1, Ordering::Relaxed)));
+    if fs::write(&tmp, body).is_ok() && fs::rename(&tmp, path).is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+}
+
+fn curl(url: &str) -> Result<Vec<u8>, String> {
+    match get(url)? {
+        (200..=299, body) => Ok(body),
+        (code, _) => Err(format!("the AUR answered with HTTP {}", code)),
+    }
+}
+
+// the status code and body, after retrying rate limits and server errors
+fn get(url: &str) -> Result<(u32, Vec<u8>), String> {
+    if !utils::command_exists("curl") {
+        return Err("curl is needed to query the AUR".to_string());
+    }
+
+    let mut attempt = 0;
+    loop {
+        wait_for_turn();
+        let output = Command::new("curl")
+            .arg("-sSL")
+            .arg("--max-time")
+            .arg("15")
+            .arg("-w")
+            .arg("\n%{http_code}")
+            .arg(url)
+            .output_always()
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+
+        // -w puts the status code on a line of its own after the body
+        let mut body = output.stdout;
+        let split = body.iter().rposition(|&b| b == b'\n').unwrap_or(0);
+        let code: u32 = String::from_utf8_lossy(&body[split..]).trim().parse().unwrap_or(0);
+        body.truncate(split);
+
+        match code {
+            429 | 500..=599 if attempt < RETRY_DELAYS.len() => {
+                std::thread::sleep(Duration::from_secs(RETRY_DELAYS[attempt]));
+                attempt += 1;
+            }
+            _ => return Ok((code, body)),
+        }
+    }
+}
+
+fn wait_for_turn() {
+    let mut last = LAST_REQUEST.lock().unwrap();
+    if let Some(elapsed) = last.map(|at| at.elapsed()) {
+        if elapsed < REQUEST_INTERVAL {
+            std::thread::sleep(REQUEST_INTERVAL - elapsed);
+        }
+    }
+    *last = Some(Instant::now());
+}
+
+// a file from a package's AUR repository, through cgit so nothing is cloned; None when cgit has
+// no such file or package
+pub fn repo_file(package: &str, file: &str) -> Result<Option<Vec<u8>>, String> {
+    let url = format!("https://aur.archlinux.org/cgit/aur.git/plain/{}?h={}", file, url_encode(package));
+    debug!("fetching {}", url);
+    match get(&url)? {
+        (200..=299, body) => Ok(Some(body)),
+        (404, _) => Ok(None),
+        (code, _) => Err(format!("the AUR answered with HTTP {}", code)),
+    }
+}
+
+pub struct Comment {
+    pub pinned: bool,
+    // "someone commented on 2024-01-01 12:00 (UTC)"
+    pub header: String,
+    pub body: String,
+}
+
+// the RPC has no comments, so they are scraped from the package page
+pub fn comments(package: &str) -> Result<Vec<Comment>, String> {
+    let page = curl(&format!("https://aur.archlinux.org/packages/{}", url_encode(package)))?;
+    Ok(parse_comments(&String::from_utf8_lossy(&page)))
+}
+
+// tolerant of markup changes: anything that doesn't look like a comment is skipped
+fn parse_comments(html: &str) -> Vec<Comment> {
+    const HEADER: &str = "class=\"comment-header\"";
+    let pinned_section = html.find("Pinned Comments");
+    let latest_section = html.find("Latest Comments");
+    let starts: Vec<usize> = html.match_indices(HEADER).map(|(i, _)| i).collect();
+
+    let mut comments = Vec::new();
+    for (n, &start) in starts.iter().enumerate() {
+        let end = starts.get(n + 1).copied().unwrap_or(html.len());
+        // the last comment of a section runs into the next heading or the comment form
+        let chunk = &html[start..end];
+        let chunk = &chunk[..chunk.find("<h3").or_else(|| chunk.find("<form")).unwrap_or(chunk.len())];
+
+        let header = match chunk.find('>').zip(chunk.find("</h4>")) {
+            Some((open, close)) if open < close => collapse_whitespace(&strip_tags(&chunk[open + 1..close])),
+            _ => continue,
+        };
+        let body = match chunk.find("article-content").and_then(|i| chunk[i..].find('>').map(|j| i + j + 1)) {
+            Some(body_start) => strip_tags(&chunk[body_start..]),
+            None => continue,
+        };
+        let body: Vec<String> = body.lines().map(collapse_whitespace).filter(|l| !l.is_empty()).collect();
+
+        let pinned = match (pinned_section, latest_section) {
+            (Some(pinned), Some(latest)) => start > pinned && start < latest,
+            (Some(pinned), None) => start > pinned,
+            _ => false,
+        };
+        if !header.is_empty() && !body.is_empty() {
+            comments.push(Comment { pinned, header, body: body.join("\n") });
+        }
+    }
+    comments
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    let mut tag = String::new();
+    for c in html.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                tag.clear();
+            }
+            '>' if in_tag => {
+                in_tag = false;
+                let name = tag.trim_start_matches('/').split_whitespace().next().unwrap_or("").to_ascii_lowercase();
+                if matches!(name.as_str(), "p" | "br" | "br/" | "li" | "pre" | "div") {
+                    text.push('\n');
+                }
+            }
+            _ if in_tag => tag.push(c),
+            // line breaks in the markup are not line breaks in the comment
+            '\n' => text.push(' '),
+            _ => text.push(c),
+        }
+    }
+    decode_entities(&text)
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) if end <= 10 => end,
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" | "#39" | "#x27" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|n| n.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn url_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+    use serde_json::json;
+    use super::*;
+
+    fn listen() -> (String, TcpListener) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        (format!("http://{}", listener.local_addr().unwrap()), listener)
+    }
+
+    // answers each connection with the next status, a 200 with the packages the request asked
+    // for; the request targets, once all are answered
+    fn serve(listener: TcpListener, statuses: Vec<u32>) -> JoinHandle<Vec<String>> {
+        thread::spawn(move || {
+            let mut targets = Vec::new();
+            for status in statuses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 8192];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buffer).unwrap() {
+                        0 => break,
+                        n => request.extend_from_slice(&buffer[..n]),
+                    }
+                }
+                let target = String::from_utf8_lossy(&request).split(' ').nth(1).unwrap_or_default().to_string();
+                let body = if status == 200 { results(&target) } else { "busy".to_string() };
+                let response = format!("HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, body.len(), body);
+                stream.write_all(response.as_bytes()).unwrap();
+                targets.push(target);
+            }
+            targets
+        })
+    }
+
+    fn results(target: &str) -> String {
+        let results: Vec<serde_json::Value> = target
+            .split(['?', '&'])
+            .filter_map(|arg| arg.strip_prefix("arg[]="))
+            .map(|name| json!({ "Name": name, "Version": "1.0-1" }))
+            .collect();
+        json!({ "type": "multiinfo", "resultcount": results.len(), "results": results }).to_string()
+    }
+
+    fn names(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("some-longer-package-name-{}", i)).collect()
+    }
+
+    #[test]
+    fn info_urls_stay_under_the_limit() {
+        assert_eq!(info_urls(RPC_URL, &names(1)), [format!("{}/info?arg[]=some-longer-package-name-0", RPC_URL)]);
+
+        let names = names(1000);
+        let urls = info_urls(RPC_URL, &names);
+        assert!(urls.len() > 1);
+        assert!(urls.iter().all(|url| url.len() < MAX_URI_LENGTH && url.starts_with(&format!("{}/info?arg[]=", RPC_URL))));
+        let asked: Vec<String> = urls.iter().flat_map(|url| url.split('&').map(|arg| arg.rsplit('=').next().unwrap().to_string())).collect();
+        assert_eq!(asked, names);
+    }
+
+    #[test]
+    fn every_batch_is_asked_for() {
+        if !utils::command_exists("curl") {
+            return;
+        }
+        let names = names(600);
+        let (url, listener) = listen();
+        let urls = info_urls(&url, &names);
+        let server = serve(listener, vec![200; urls.len()]);
+
+        let mut found = Vec::new();
+        for batch in &urls {
+            found.extend(parse(&curl(batch).unwrap()).unwrap().into_iter().map(|p| p.name));
+        }
+        assert_eq!(found, names);
+        let targets = server.join().unwrap();
+        assert_eq!(targets.len(), urls.len());
+        assert!(targets.len() > 1 && targets.iter().all(|target| target.len() + url.len() < MAX_URI_LENGTH));
+    }
+
+    #[test]
+    fn rate_limits_and_server_errors_are_retried() {
+        if !utils::command_exists("curl") {
+            return;
+        }
+        let (url, listener) = listen();
+        let server = serve(listener, vec![503, 429, 200]);
+        let (code, body) = get(&format!("{}/info?arg[]=foo", url)).unwrap();
+        assert_eq!(code, 200);
+        assert_eq!(parse(&body).unwrap()[0].name, "foo");
+        assert_eq!(server.join().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn client_errors_are_not_retried() {
+        if !utils::command_exists("curl") {
+            return;
+        }
+        let (url, listener) = listen();
+        let server = serve(listener, vec![404]);
+        assert_eq!(curl(&format!("{}/info?arg[]=foo", url)), Err("the AUR answered with HTTP 404".to_string()));
+        assert_eq!(server.join().unwrap(), ["/info?arg[]=foo"]);
+    }
+}
@@ -0,0 +1,32 @@
+// what `build.rs` found out about the build
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const COMMIT: &str = env!("AURORA_COMMIT");
+const DIRTY: &str = env!("AURORA_DIRTY");
+const BUILD_DATE: &str = env!("AURORA_BUILD_DATE");
+const RUSTC: &str = env!("AURORA_RUSTC");
+const TARGET: &str = env!("AURORA_TARGET");
+
+pub fn long() -> String {
+    let unknown = |value: &'static str| if value.is_empty() { "unknown" } else { value };
+    let dirty = if DIRTY == "true" { " (with uncommitted changes)" } else { "" };
+    [
+        format!("aurora {}", VERSION),
+        format!("commit: {}{}", unknown(COMMIT), dirty),
+        format!("built: {}", BUILD_DATE),
+        format!("rustc: {}", unknown(RUSTC)),
+        format!("target: {}", unknown(TARGET)),
+    ]
+    .join("\n")
+}
+
+pub fn json() -> serde_json::Value {
+    let known = |value: &'static str| (!value.is_empty()).then_some(value);
+    serde_json::json!({
+        "version": VERSION,
+        "commit": known(COMMIT),
+        "dirty": DIRTY == "true",
+        "build_date": BUILD_DATE,
+        "rustc": known(RUSTC),
+        "target": known(TARGET),
+    })
+}
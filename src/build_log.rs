@@ -0,0 +1,188 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::paths;
+use crate::utils;
+
+// the logs build commands append to while a package builds, see Run::run_logged
+static CURRENT: Mutex<Option<Log>> = Mutex::new(None);
+
+struct Log {
+    // stripped of escape sequences, one timestamped and tagged line per output line
+    clean: File,
+    // exactly what the commands printed
+    raw: File,
+    phase: String,
+    // what stdout and stderr printed since their last newline
+    partial: [Vec<u8>; 2],
+}
+
+#[derive(Clone, Copy)]
+pub enum Stream {
+    Stdout = 0,
+    Stderr = 1,
+}
+
+pub fn path(package: &str) -> PathBuf {
+    paths::logs_dir().join(format!("{}.log", package))
+}
+
+pub fn raw_path(package: &str) -> PathBuf {
+    paths::logs_dir().join(format!("{}.raw.log", package))
+}
+
+// replaces the package's previous logs; without log files the build just isn't captured
+pub fn start(package: &str) -> PathBuf {
+    let path = path(package);
+    let files = fs::create_dir_all(paths::logs_dir())
+        .and_then(|_| Ok((File::create(&path)?, File::create(raw_path(package))?)))
+        .ok();
+    *CURRENT.lock().unwrap() = files.map(|(mut clean, raw)| {
+        let _ = writeln!(clean, "aurora build log for {}, started {}", package, utils::format_timestamp(utils::unix_now()));
+        Log { clean, raw, phase: "build".to_string(), partial: [vec![], vec![]] }
+    });
+    path
+}
+
+// appends to the logs of the build that just finished, for a step that runs after it
+pub fn resume(package: &str, phase: &str) {
+    let open = |path: PathBuf| OpenOptions::new().append(true).open(path);
+    let files = open(path(package)).and_then(|clean| Ok((clean, open(raw_path(package))?))).ok();
+    *CURRENT.lock().unwrap() = files.map(|(clean, raw)| Log { clean, raw, phase: phase.to_string(), partial: [vec![], vec![]] });
+}
+
+// the tag on the lines that follow, e.g. "pre_build" or "test"
+pub fn set_phase(phase: &str) {
+    if let Some(log) = CURRENT.lock().unwrap().as_mut() {
+        log.phase = phase.to_string();
+    }
+}
+
+pub fn active() -> bool {
+    CURRENT.lock().unwrap().is_some()
+}
+
+pub fn append(stream: Stream, bytes: &[u8]) {
+    let mut current = CURRENT.lock().unwrap();
+    let Some(log) = current.as_mut() else {
+        return;
+    };
+    let _ = log.raw.write_all(bytes);
+    let partial = &mut log.partial[stream as usize];
+    partial.extend_from_slice(bytes);
+    while let Some(end) = partial.iter().position(|&b| b == b'\n') {
+        let line: Vec<u8> = partial.drain(..=end).collect();
+        let _ = writeln!(log.clean, "{} [{}] {}", clock(), log.phase, clean_line(&line[..end]));
+    }
+}
+
+pub fn finish() {
+    let mut current = CURRENT.lock().unwrap();
+    if let Some(log) = current.as_mut() {
+        for partial in &mut log.partial {
+            if !partial.is_empty() {
+                let line = std::mem::take(partial);
+                let _ = writeln!(log.clean, "{} [{}] {}", clock(), log.phase, clean_line(&line));
+            }
+        }
+    }
+    *current = None;
+}
+
+pub fn read(package: &str) -> String {
+    fs::read(path(package)).map(|bytes| String::from_utf8_lossy(&bytes).into_owned()).unwrap_or_default()
+}
+
+// "14:03:27", in UTC like the header
+fn clock() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0) % 86400;
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs % 3600 / 60, secs % 60)
+}
+
+// what a terminal would end up showing: a progress bar redrawn with \r leaves its last state
+fn clean_line(line: &[u8]) -> String {
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim_end_matches('\r');
+    let shown = line.rsplit('\r').next().unwrap_or(line);
+    strip_ansi(shown)
+}
+
+// drops CSI sequences (colors, cursor movement), OSC sequences (titles, hyperlinks) and other
+// escapes, and any control characters but tabs
+pub fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            if !c.is_control() || c == '\t' {
+                out.push(c);
+            }
+            continue;
+        }
+        match chars.next() {
+            // parameters and intermediates up to a final byte in @..~
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // ends with BEL or ESC \
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // charset selection carries one more character
+            Some('(' | ')') => {
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csi_sequences_are_dropped() {
+        assert_eq!(strip_ansi("\x1b[1;31merror\x1b[0m: failed"), "error: failed");
+        assert_eq!(strip_ansi("\x1b[2K\x1b[1Gdone"), "done");
+        assert_eq!(strip_ansi("\x1b[38;5;208morange\x1b[m"), "orange");
+        assert_eq!(strip_ansi("\x1b[?25lhidden cursor\x1b[?25h"), "hidden cursor");
+    }
+
+    #[test]
+    fn osc_sequences_are_dropped() {
+        assert_eq!(strip_ansi("\x1b]0;title\x07text"), "text");
+        assert_eq!(strip_ansi("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\"), "link");
+    }
+
+    #[test]
+    fn other_escapes_and_controls_are_dropped() {
+        assert_eq!(strip_ansi("\x1b(Bplain\x1b=x"), "plainx");
+        assert_eq!(strip_ansi("a\tb\x08c\x07"), "a\tbc");
+        // an escape cut off at the end of the output
+        assert_eq!(strip_ansi("text\x1b[1;3"), "text");
+    }
+
+    #[test]
+    fn a_redrawn_line_keeps_its_last_state() {
+        assert_eq!(clean_line(b"  10%\r  50%\r\x1b[32m 100%\x1b[0m"), " 100%");
+        // crlf line endings
+        assert_eq!(clean_line(b"compiling foo\r"), "compiling foo");
+        assert_eq!(clean_line(b"plain"), "plain");
+    }
+}
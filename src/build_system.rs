@@ -0,0 +1,961 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::Table;
+use tracing::debug;
+use crate::utils;
+
+pub const MAKEFILES: [&str; 3] = ["Makefile", "makefile", "GNUMakefile"];
+
+const C_COMPILER: &str = "cc|gcc|clang";
+const CXX_COMPILER: &str = "c++|g++|clang++";
+
+// compilers and generators are only checked on unix; on windows they come from the VS environment
+fn with_native(mut tools: Vec<&'static str>, native: &[&'static str]) -> Vec<&'static str> {
+    if !cfg!(windows) {
+        tools.extend_from_slice(native);
+    }
+    tools
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BuildSystem {
+    Zig,
+    Make,
+    Autotools,
+    Cargo,
+    CMake,
+    Meson,
+    Ninja,
+    Nimble,
+    Stack,
+    Go,
+    Python,
+    Node,
+    Just,
+    SCons,
+    Bazel,
+    Gradle,
+    Maven,
+    Waf,
+    Premake,
+    Crystal,
+    Dub,
+    Swift,
+    Cabal,
+    Dune,
+    Gleam,
+    Rebar3,
+    Mix,
+    QMake,
+    XMake,
+}
+
+impl BuildSystem {
+    // default detection priority, highest first
+    pub const ALL: [BuildSystem; 29] = [
+        BuildSystem::Zig,
+        BuildSystem::Make,
+        BuildSystem::Just,
+        BuildSystem::Autotools,
+        BuildSystem::Cargo,
+        BuildSystem::CMake,
+        BuildSystem::Meson,
+        BuildSystem::Ninja,
+        BuildSystem::Nimble,
+        BuildSystem::Stack,
+        BuildSystem::Cabal,
+        BuildSystem::Go,
+        BuildSystem::Python,
+        BuildSystem::Node,
+        BuildSystem::SCons,
+        BuildSystem::Bazel,
+        BuildSystem::Gradle,
+        BuildSystem::Maven,
+        BuildSystem::Waf,
+        BuildSystem::Premake,
+        BuildSystem::Crystal,
+        BuildSystem::Dub,
+        BuildSystem::Swift,
+        BuildSystem::Dune,
+        BuildSystem::Gleam,
+        BuildSystem::Rebar3,
+        BuildSystem::Mix,
+        BuildSystem::QMake,
+        BuildSystem::XMake,
+    ];
+
+    pub fn from_name(name: &str) -> Option<BuildSystem> {
+        BuildSystem::ALL.into_iter().find(|b| b.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BuildSystem::Zig => "zig",
+            BuildSystem::Make => "make",
+            BuildSystem::Autotools => "autotools",
+            BuildSystem::Cargo => "cargo",
+            BuildSystem::CMake => "cmake",
+            BuildSystem::Meson => "meson",
+            BuildSystem::Ninja => "ninja",
+            BuildSystem::Nimble => "nimble",
+            BuildSystem::Stack => "stack",
+            BuildSystem::Go => "go",
+            BuildSystem::Python => "python",
+            BuildSystem::Node => "node",
+            BuildSystem::Just => "just",
+            BuildSystem::SCons => "scons",
+            BuildSystem::Bazel => "bazel",
+            BuildSystem::Gradle => "gradle",
+            BuildSystem::Maven => "maven",
+            BuildSystem::Waf => "waf",
+            BuildSystem::Premake => "premake",
+            BuildSystem::Crystal => "crystal",
+            BuildSystem::Dub => "dub",
+            BuildSystem::Swift => "swift",
+            BuildSystem::Cabal => "cabal",
+            BuildSystem::Dune => "dune",
+            BuildSystem::Gleam => "gleam",
+            BuildSystem::Rebar3 => "rebar3",
+            BuildSystem::Mix => "mix",
+            BuildSystem::QMake => "qmake",
+            BuildSystem::XMake => "xmake",
+        }
+    }
+
+    pub fn display_name(self) -> &'static str {
+        match self {
+            BuildSystem::Zig => "Zig",
+            BuildSystem::Make => "Make",
+            BuildSystem::Autotools => "Autotools",
+            BuildSystem::Cargo => "Cargo",
+            BuildSystem::CMake => "CMake",
+            BuildSystem::Meson => "Meson",
+            BuildSystem::Ninja => "Ninja",
+            BuildSystem::Nimble => "Nimble",
+            BuildSystem::Stack => "Stack",
+            BuildSystem::Go => "Go",
+            BuildSystem::Python => "Python",
+            BuildSystem::Node => "Node.js",
+            BuildSystem::Just => "Just",
+            BuildSystem::SCons => "SCons",
+            BuildSystem::Bazel => "Bazel",
+            BuildSystem::Gradle => "Gradle",
+            BuildSystem::Maven => "Maven",
+            BuildSystem::Waf => "Waf",
+            BuildSystem::Premake => "Premake",
+            BuildSystem::Crystal => "Crystal",
+            BuildSystem::Dub => "Dub",
+            BuildSystem::Swift => "SwiftPM",
+            BuildSystem::Cabal => "Cabal",
+            BuildSystem::Dune => "Dune",
+            BuildSystem::Gleam => "Gleam",
+            BuildSystem::Rebar3 => "rebar3",
+            BuildSystem::Mix => "Mix",
+            BuildSystem::QMake => "qmake",
+            BuildSystem::XMake => "xmake",
+        }
+    }
+
+    pub fn is_present(self, build_dir: &Path) -> bool {
+        match self {
+            // a Makefile left behind by an earlier qmake run belongs to the .pro file
+            BuildSystem::Make => find_makefile(build_dir)
+                .map(|f| !(is_qmake_generated(&f) && BuildSystem::QMake.is_present(build_dir)))
+                .unwrap_or(false),
+            BuildSystem::Waf => {
+                build_dir.join("wscript").is_file()
+                    || utils::is_executable(&build_dir.join("waf"))
+            }
+            // a package.json without a bin entry is a library, not something to install
+            BuildSystem::Node => !node_bins(build_dir).is_empty(),
+            // only mix projects that define an escript produce something installable
+            BuildSystem::Mix => fs::read_to_string(build_dir.join("mix.exs"))
+                .map(|c| c.contains("escript"))
+                .unwrap_or(false),
+            _ => self.build_file(build_dir).map(|f| f.exists()).unwrap_or(false),
+        }
+    }
+
+    // roughly what the build step runs, for `aurora shell`; flags and paths are left out
+    pub fn build_commands(self) -> &'static str {
+        match self {
+            BuildSystem::Zig => "zig build",
+            BuildSystem::Make => "make",
+            BuildSystem::Autotools => "./configure && make",
+            BuildSystem::Cargo => "cargo build --release",
+            BuildSystem::CMake => "cmake -B build && cmake --build build",
+            BuildSystem::Meson => "meson setup build && ninja -C build",
+            BuildSystem::Ninja => "ninja",
+            BuildSystem::Nimble => "nimble build",
+            BuildSystem::Stack => "stack install",
+            BuildSystem::Go => "go build",
+            BuildSystem::Python => "pipx install . (or python3 -m pip install --user .)",
+            BuildSystem::Node => "npm install && npm pack && npm install -g",
+            BuildSystem::Just => "just",
+            BuildSystem::SCons => "scons",
+            BuildSystem::Bazel => "bazel build",
+            BuildSystem::Gradle => "gradle installDist",
+            BuildSystem::Maven => "mvn package -DskipTests",
+            BuildSystem::Waf => "waf configure && waf build",
+            BuildSystem::Premake => "premake5 gmake2 && make",
+            BuildSystem::Crystal => "shards build --release",
+            BuildSystem::Dub => "dub build",
+            BuildSystem::Swift => "swift build -c release",
+            BuildSystem::Cabal => "cabal install",
+            BuildSystem::Dune => "dune build",
+            BuildSystem::Gleam => "gleam export erlang-shipment",
+            BuildSystem::Rebar3 => "rebar3 escriptize",
+            BuildSystem::Mix => "mix escript.build",
+            BuildSystem::QMake => "qmake && make",
+            BuildSystem::XMake => "xmake f && xmake",
+        }
+    }
+
+    // build systems whose build step also installs the result
+    pub fn installs_itself(self) -> bool {
+        matches!(self, BuildSystem::Python | BuildSystem::Node)
+    }
+
+    pub fn required_tools(self, build_dir: &Path) -> Vec<&'static str> {
+        match self {
+            BuildSystem::Zig => vec!["zig"],
+            BuildSystem::Make => vec!["make"],
+            BuildSystem::Autotools => with_native(vec!["make"], &[C_COMPILER]),
+            BuildSystem::Cargo => vec!["cargo"],
+            BuildSystem::CMake => with_native(vec!["cmake"], &["make|ninja", C_COMPILER]),
+            BuildSystem::Meson => with_native(vec!["meson", "ninja"], &[C_COMPILER]),
+            BuildSystem::Ninja => vec!["ninja"],
+            BuildSystem::Nimble => vec!["nimble"],
+            BuildSystem::Stack => vec!["stack"],
+            BuildSystem::Go => vec!["go"],
+            // pipx or pip is chosen at build time
+            BuildSystem::Python => vec![],
+            BuildSystem::Node => vec!["npm"],
+            BuildSystem::Just => vec!["just"],
+            BuildSystem::SCons => vec!["scons"],
+            BuildSystem::Bazel => vec!["bazel"],
+            BuildSystem::Gradle if build_dir.join("gradlew").exists() => vec![],
+            BuildSystem::Gradle => vec!["gradle"],
+            BuildSystem::Maven if build_dir.join("mvnw").exists() => vec![],
+            BuildSystem::Maven => vec!["mvn"],
+            BuildSystem::Waf if build_dir.join("waf").is_file() => vec!["python3"],
+            BuildSystem::Waf => vec!["python3", "waf"],
+            BuildSystem::Premake => with_native(vec!["premake5", "make"], &[C_COMPILER]),
+            BuildSystem::Crystal => vec!["shards", "crystal"],
+            BuildSystem::Dub => vec!["dub", "dmd|ldc2|gdc"],
+            BuildSystem::Swift => vec!["swift"],
+            BuildSystem::Cabal => vec!["cabal", "ghc"],
+            BuildSystem::Dune => vec!["dune", "ocaml"],
+            BuildSystem::Gleam => vec!["gleam", "erl"],
+            BuildSystem::Rebar3 => vec!["rebar3", "escript"],
+            BuildSystem::Mix => vec!["mix", "escript"],
+            BuildSystem::QMake => with_native(vec!["qmake6|qmake|qmake-qt5", "make"], &[CXX_COMPILER]),
+            BuildSystem::XMake => vec!["xmake"],
+        }
+    }
+
+    pub fn needs_java(self) -> bool {
+        matches!(self, BuildSystem::Gradle | BuildSystem::Maven)
+    }
+
+    pub fn build_file(self, build_dir: &Path) -> Option<PathBuf> {
+        match self {
+            BuildSystem::Zig => Some(build_dir.join("build.zig")),
+            BuildSystem::Make => find_makefile(build_dir),
+            BuildSystem::Autotools => Some(build_dir.join("configure")),
+            BuildSystem::Cargo => Some(build_dir.join("Cargo.toml")),
+            BuildSystem::CMake => Some(build_dir.join("CMakeLists.txt")),
+            BuildSystem::Meson => Some(build_dir.join("meson.build")),
+            BuildSystem::Ninja => Some(build_dir.join("build.ninja")),
+            BuildSystem::Nimble => find_file_with_extension(build_dir, "nimble"),
+            BuildSystem::Stack => Some(build_dir.join("stack.yaml")),
+            BuildSystem::Go => Some(build_dir.join("go.mod")),
+            BuildSystem::Python => ["pyproject.toml", "setup.py"]
+                .iter()
+                .map(|f| build_dir.join(f))
+                .find(|p| p.exists()),
+            BuildSystem::Node => Some(build_dir.join("package.json")),
+            BuildSystem::Just => ["justfile", "Justfile", ".justfile"]
+                .iter()
+                .map(|f| build_dir.join(f))
+                .find(|p| p.exists()),
+            BuildSystem::SCons => ["SConstruct", "Sconstruct", "sconstruct", "SConscript"]
+                .iter()
+                .map(|f| build_dir.join(f))
+                .find(|p| p.exists()),
+            // only workspace markers at the root count, a vendored BUILD file does not
+            BuildSystem::Bazel => ["MODULE.bazel", "WORKSPACE.bazel", "WORKSPACE"]
+                .iter()
+                .map(|f| build_dir.join(f))
+                .find(|p| p.is_file()),
+            BuildSystem::Gradle => ["build.gradle.kts", "build.gradle"]
+                .iter()
+                .map(|f| build_dir.join(f))
+                .find(|p| p.exists()),
+            BuildSystem::Maven => Some(build_dir.join("pom.xml")),
+            BuildSystem::Waf => Some(build_dir.join("wscript")),
+            BuildSystem::Premake => Some(build_dir.join("premake5.lua")),
+            BuildSystem::Crystal => Some(build_dir.join("shard.yml")),
+            BuildSystem::Swift => Some(build_dir.join("Package.swift")),
+            BuildSystem::Cabal => find_file_with_extension(build_dir, "cabal"),
+            BuildSystem::Dune => Some(build_dir.join("dune-project")),
+            BuildSystem::Gleam => Some(build_dir.join("gleam.toml")),
+            BuildSystem::Rebar3 => Some(build_dir.join("rebar.config")),
+            BuildSystem::Mix => Some(build_dir.join("mix.exs")),
+            BuildSystem::QMake => find_file_with_extension(build_dir, "pro"),
+            BuildSystem::XMake => Some(build_dir.join("xmake.lua")),
+            BuildSystem::Dub => ["dub.json", "dub.sdl"]
+                .iter()
+                .map(|f| build_dir.join(f))
+                .find(|p| p.exists()),
+        }
+    }
+}
+
+pub fn detect(build_dir: &Path, priority: &[BuildSystem]) -> Option<BuildSystem> {
+    let detected = priority
+        .iter()
+        .copied()
+        .chain(BuildSystem::ALL)
+        .find(|b| b.is_present(build_dir));
+    if tracing::enabled!(tracing::Level::DEBUG) {
+        let present: Vec<&str> = BuildSystem::ALL.iter().filter(|b| b.is_present(build_dir)).map(|b| b.name()).collect();
+        debug!("build files found for: {}; picked {}", present.join(", "), detected.map_or("nothing", |b| b.name()));
+    }
+    detected
+}
+
+pub fn find_makefile(build_dir: &Path) -> Option<PathBuf> {
+    MAKEFILES.iter().map(|f| build_dir.join(f)).find(|p| p.exists())
+}
+
+fn is_qmake_generated(makefile: &Path) -> bool {
+    fs::read_to_string(makefile)
+        .map(|c| c.lines().take(10).any(|l| l.starts_with('#') && l.contains("Generated by qmake")))
+        .unwrap_or(false)
+}
+
+pub fn find_file_with_extension(dir: &Path, extension: &str) -> Option<PathBuf> {
+    let mut matches: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().map(|e| e == extension).unwrap_or(false))
+        .collect();
+    matches.sort();
+    matches.into_iter().next()
+}
+
+pub fn go_module_name(build_dir: &Path) -> Option<String> {
+    let content = fs::read_to_string(build_dir.join("go.mod")).ok()?;
+    let module = content
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("module "))?
+        .trim()
+        .trim_matches('"');
+
+    let mut segments = module.rsplit('/');
+    let last = segments.next()?;
+    // major version suffixes like example.com/tool/v2 name the binary "tool"
+    let is_major_version = last.len() > 1
+        && last.starts_with('v')
+        && last[1..].chars().all(|c| c.is_ascii_digit());
+    if is_major_version {
+        segments.next().map(|s| s.to_string())
+    } else {
+        Some(last.to_string())
+    }
+}
+
+pub fn go_targets(build_dir: &Path, fallback: &str) -> Vec<(String, String)> {
+    let cmd_dir = build_dir.join("cmd");
+    let mut targets: Vec<(String, String)> = match fs::read_dir(&cmd_dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_dir() && find_file_with_extension(p, "go").is_some())
+            .filter_map(|p| {
+                let name = p.file_name()?.to_string_lossy().into_owned();
+                Some((name.clone(), format!("./cmd/{}", name)))
+            })
+            .collect(),
+        Err(_) => vec![],
+    };
+    targets.sort();
+
+    if targets.is_empty() {
+        let name = go_module_name(build_dir).unwrap_or_else(|| fallback.to_string());
+        targets.push((name, ".".to_string()));
+    }
+    targets
+}
+
+pub fn python_scripts(build_dir: &Path) -> Vec<String> {
+    let content = match fs::read_to_string(build_dir.join("pyproject.toml")) {
+        Ok(c) => c,
+        Err(_) => return vec![],
+    };
+    let value: Table = match content.parse() {
+        Ok(v) => v,
+        Err(_) => return vec![],
+    };
+
+    let project = value.get("project").and_then(|p| p.as_table());
+    let poetry = value
+        .get("tool")
+        .and_then(|t| t.get("poetry"))
+        .and_then(|p| p.as_table());
+
+    let mut scripts: Vec<String> = [
+        project.and_then(|p| p.get("scripts")),
+        project.and_then(|p| p.get("gui-scripts")),
+        poetry.and_then(|p| p.get("scripts")),
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(|s| s.as_table())
+    .flat_map(|t| t.keys().cloned())
+    .collect();
+    scripts.sort();
+    scripts.dedup();
+    scripts
+}
+
+pub fn node_bins(build_dir: &Path) -> Vec<String> {
+    let content = match fs::read_to_string(build_dir.join("package.json")) {
+        Ok(c) => c,
+        Err(_) => return vec![],
+    };
+    let json: serde_json::Value = match serde_json::from_str(&content) {
+        Ok(v) => v,
+        Err(_) => return vec![],
+    };
+
+    match &json["bin"] {
+        serde_json::Value::String(_) => json["name"]
+            .as_str()
+            .map(|name| name.rsplit('/').next().unwrap_or(name).to_string())
+            .into_iter()
+            .collect(),
+        serde_json::Value::Object(bins) => {
+            let mut names: Vec<String> = bins.keys().cloned().collect();
+            names.sort();
+            names
+        }
+        _ => vec![],
+    }
+}
+
+pub fn shard_targets(build_dir: &Path) -> Vec<String> {
+    let content = match fs::read_to_string(build_dir.join("shard.yml")) {
+        Ok(c) => c,
+        Err(_) => return vec![],
+    };
+    let yaml: serde_yaml::Value = match serde_yaml::from_str(&content) {
+        Ok(v) => v,
+        Err(_) => return vec![],
+    };
+
+    yaml.get("targets")
+        .and_then(|t| t.as_mapping())
+        .map(|targets| {
+            targets
+                .keys()
+                .filter_map(|k| k.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// path of the dub output relative to the project root, e.g. "bin/tool"
+pub fn dub_target(build_dir: &Path) -> Option<String> {
+    let (name, target_name, target_path) = if let Ok(content) = fs::read_to_string(build_dir.join("dub.json")) {
+        let json: serde_json::Value = serde_json::from_str(&content).ok()?;
+        let field = |key: &str| json[key].as_str().map(|s| s.to_string());
+        (field("name"), field("targetName"), field("targetPath"))
+    } else {
+        let content = fs::read_to_string(build_dir.join("dub.sdl")).ok()?;
+        let field = |key: &str| {
+            content.lines().find_map(|line| {
+                let rest = line.trim().strip_prefix(key)?;
+                rest.starts_with(char::is_whitespace)
+                    .then(|| rest.trim().trim_matches('"').to_string())
+            })
+        };
+        (field("name"), field("targetName"), field("targetPath"))
+    };
+
+    let binary = target_name.or(name)?;
+    Some(match target_path {
+        Some(dir) => format!("{}/{}", dir.trim_end_matches('/'), binary),
+        None => binary,
+    })
+}
+
+pub struct CMakePreset {
+    pub name: String,
+    pub binary_dir: Option<PathBuf>,
+    pub build_preset: Option<String>,
+}
+
+// visible configure presets from CMakePresets.json, with the build preset that uses each
+pub fn cmake_presets(build_dir: &Path) -> Vec<CMakePreset> {
+    let content = match fs::read_to_string(build_dir.join("CMakePresets.json")) {
+        Ok(c) => c,
+        Err(_) => return vec![],
+    };
+    let json: serde_json::Value = match serde_json::from_str(&content) {
+        Ok(v) => v,
+        Err(_) => return vec![],
+    };
+
+    let configure = json["configurePresets"].as_array().cloned().unwrap_or_default();
+    let build = json["buildPresets"].as_array().cloned().unwrap_or_default();
+    let by_name = |name: &str| configure.iter().find(|p| p["name"].as_str() == Some(name));
+
+    // binaryDir is often only set on a hidden base preset
+    let binary_dir = |preset: &serde_json::Value| {
+        let mut current = preset;
+        for _ in 0..8 {
+            if let Some(dir) = current["binaryDir"].as_str() {
+                return Some(dir.to_string());
+            }
+            let parent = match &current["inherits"] {
+                serde_json::Value::String(s) => s.as_str(),
+                serde_json::Value::Array(a) => a.first()?.as_str()?,
+                _ => return None,
+            };
+            current = by_name(parent)?;
+        }
+        None
+    };
+
+    let source_dir = build_dir.to_string_lossy();
+    let source_dir_name = build_dir.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    configure
+        .iter()
+        .filter(|p| !p["hidden"].as_bool().unwrap_or(false))
+        .filter_map(|p| {
+            let name = p["name"].as_str()?.to_string();
+            let binary_dir = binary_dir(p).map(|dir| {
+                let dir = dir
+                    .replace("${sourceDir}", &source_dir)
+                    .replace("${sourceDirName}", &source_dir_name)
+                    .replace("${presetName}", &name);
+                build_dir.join(dir)
+            });
+            let build_preset = build
+                .iter()
+                .filter(|b| !b["hidden"].as_bool().unwrap_or(false))
+                .find(|b| b["configurePreset"].as_str() == Some(name.as_str()))
+                .and_then(|b| b["name"].as_str().map(|s| s.to_string()));
+            Some(CMakePreset { name, binary_dir, build_preset })
+        })
+        .collect()
+}
+
+// executables a project declares up front, for the review output
+pub fn declared_executables(build_system: BuildSystem, build_dir: &Path) -> Vec<String> {
+    match build_system {
+        BuildSystem::Cabal => build_system
+            .build_file(build_dir)
+            .map(|f| cabal_executables(&f))
+            .unwrap_or_default(),
+        BuildSystem::Dune => dune_executables(build_dir),
+        _ => vec![],
+    }
+}
+
+pub fn cabal_executables(cabal_file: &Path) -> Vec<String> {
+    let content = match fs::read_to_string(cabal_file) {
+        Ok(c) => c,
+        Err(_) => return vec![],
+    };
+
+    // stanza headers start at column 0 and are case-insensitive
+    content
+        .lines()
+        .filter(|line| !line.starts_with(char::is_whitespace))
+        .filter_map(|line| {
+            let (keyword, name) = line.split_once(char::is_whitespace)?;
+            keyword
+                .eq_ignore_ascii_case("executable")
+                .then(|| name.trim().to_string())
+        })
+        .collect()
+}
+
+pub fn dune_executables(build_dir: &Path) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut pending = vec![(build_dir.to_path_buf(), 0)];
+
+    while let Some((dir, depth)) = pending.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.is_dir() && depth < 3 && !entry.file_name().to_string_lossy().starts_with(['.', '_']) {
+                pending.push((path, depth + 1));
+            } else if entry.file_name() == "dune" {
+                if let Ok(content) = fs::read_to_string(&path) {
+                    names.extend(dune_public_names(&content));
+                }
+            }
+        }
+    }
+
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn dune_public_names(content: &str) -> Vec<String> {
+    // only executables with a public name get installed by `dune install`
+    ["(public_name ", "(public_names "]
+        .iter()
+        .flat_map(|key| content.match_indices(key).map(move |(i, _)| &content[i + key.len()..]))
+        .filter_map(|rest| rest.split(')').next())
+        .flat_map(|names| names.split_whitespace().map(|n| n.to_string()))
+        .collect()
+}
+
+pub fn gleam_name(build_dir: &Path) -> Option<String> {
+    let content = fs::read_to_string(build_dir.join("gleam.toml")).ok()?;
+    let value: Table = content.parse().ok()?;
+    value.get("name")?.as_str().map(|s| s.to_string())
+}
+
+pub fn qmake_target(pro_file: &Path) -> Option<String> {
+    let content = fs::read_to_string(pro_file).ok()?;
+    content.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        if key.trim() != "TARGET" {
+            return None;
+        }
+        let value = value.trim().trim_matches('"');
+        // `TARGET = $$qtLibraryTarget(...)` and friends can't be resolved here
+        if value.is_empty() || value.contains("$$") {
+            None
+        } else {
+            Some(value.to_string())
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use super::*;
+
+    // a scratch project holding `files`, which may be in subdirectories
+    fn project(files: &[(&str, &str)]) -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, content) in files {
+            let path = dir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    fn detected(files: &[(&str, &str)]) -> Option<BuildSystem> {
+        detect(project(files).path(), &[])
+    }
+
+    #[test]
+    fn nimble_files_are_found_by_extension() {
+        let dir = project(&[("foo.nimble", "bin = @[\"foo\"]\n"), ("src/foo.nim", "echo 1\n")]);
+        assert_eq!(detect(dir.path(), &[]), Some(BuildSystem::Nimble));
+        assert_eq!(BuildSystem::Nimble.build_file(dir.path()), Some(dir.path().join("foo.nimble")));
+    }
+
+    #[test]
+    fn nimble_needs_a_nimble_file() {
+        assert_eq!(detected(&[("src/foo.nim", "echo 1\n")]), None);
+        // the literal name the old glob check looked for doesn't count as one
+        let dir = project(&[]);
+        fs::create_dir(dir.path().join("pkg.nimble")).unwrap();
+        assert_eq!(detect(dir.path(), &[]), None);
+    }
+
+    #[test]
+    fn the_first_nimble_file_by_name_is_used() {
+        let dir = project(&[("b.nimble", ""), ("a.nimble", "")]);
+        assert_eq!(BuildSystem::Nimble.build_file(dir.path()), Some(dir.path().join("a.nimble")));
+    }
+
+    const HELLO_GO: &str = "package main\n\nimport \"fmt\"\n\nfunc main() {\n\tfmt.Println(\"hello\")\n}\n";
+
+    #[test]
+    fn go_modules_are_detected() {
+        let dir = project(&[("go.mod", "module github.com/someone/hello\n\ngo 1.21\n"), ("main.go", HELLO_GO)]);
+        assert_eq!(detect(dir.path(), &[]), Some(BuildSystem::Go));
+        assert_eq!(BuildSystem::Go.build_file(dir.path()), Some(dir.path().join("go.mod")));
+        assert_eq!(go_module_name(dir.path()).as_deref(), Some("hello"));
+        assert_eq!(go_targets(dir.path(), "hello-git"), [("hello".to_string(), ".".to_string())]);
+        assert_eq!(detected(&[("main.go", HELLO_GO)]), None);
+    }
+
+    #[test]
+    fn go_binaries_are_named_after_the_module() {
+        let name = |go_mod: &str| go_module_name(project(&[("go.mod", go_mod)]).path());
+        assert_eq!(name("module example.com/tool/v2\n").as_deref(), Some("tool"));
+        assert_eq!(name("module \"example.com/quoted\"\n").as_deref(), Some("quoted"));
+        assert_eq!(name("// comment\nmodule hello\n").as_deref(), Some("hello"));
+        assert_eq!(name("module example.com/v\n").as_deref(), Some("v"));
+        assert_eq!(name("go 1.21\n"), None);
+    }
+
+    #[test]
+    fn go_cmd_directories_are_separate_binaries() {
+        let dir = project(&[
+            ("go.mod", "module example.com/tools\n"),
+            ("cmd/b/main.go", HELLO_GO),
+            ("cmd/a/main.go", HELLO_GO),
+            ("cmd/docs/README.md", ""),
+        ]);
+        assert_eq!(
+            go_targets(dir.path(), "tools"),
+            [("a".to_string(), "./cmd/a".to_string()), ("b".to_string(), "./cmd/b".to_string())]
+        );
+        // without a module name the package name stands in
+        let dir = project(&[("go.mod", "go 1.21\n"), ("main.go", HELLO_GO)]);
+        assert_eq!(go_targets(dir.path(), "hello-git"), [("hello-git".to_string(), ".".to_string())]);
+    }
+
+    #[test]
+    fn zig_wins_over_a_makefile() {
+        let dir = project(&[("build.zig", "const std = @import(\"std\");\n"), ("Makefile", "all:\n\tzig build\n")]);
+        assert_eq!(detect(dir.path(), &[]), Some(BuildSystem::Zig));
+        assert_eq!(BuildSystem::Zig.build_file(dir.path()), Some(dir.path().join("build.zig")));
+        // unless the config prefers make
+        assert_eq!(detect(dir.path(), &[BuildSystem::Make]), Some(BuildSystem::Make));
+        assert_eq!(detected(&[("build.zig.zon", ".{}\n")]), None);
+    }
+
+    #[test]
+    fn node_packages_need_a_bin_entry() {
+        let dir = project(&[("package.json", r#"{ "name": "@someone/tool", "bin": "cli.js" }"#)]);
+        assert_eq!(detect(dir.path(), &[]), Some(BuildSystem::Node));
+        assert_eq!(node_bins(dir.path()), ["tool"]);
+        assert_eq!(BuildSystem::Node.build_file(dir.path()), Some(dir.path().join("package.json")));
+
+        // a library
+        assert_eq!(detected(&[("package.json", r#"{ "name": "lib", "main": "index.js" }"#)]), None);
+        assert_eq!(detected(&[("package.json", "{ not json")]), None);
+        assert_eq!(detected(&[("package.json", r#"{ "name": "lib" }"#), ("Makefile", "all:\n")]), Some(BuildSystem::Make));
+    }
+
+    #[test]
+    fn node_bin_maps_name_every_command() {
+        let dir = project(&[("package.json", r#"{ "name": "tools", "bin": { "zeta": "z.js", "alpha": "a.js" } }"#)]);
+        assert_eq!(node_bins(dir.path()), ["alpha", "zeta"]);
+        let dir = project(&[("package.json", r#"{ "bin": "cli.js" }"#)]);
+        assert!(node_bins(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn justfiles_are_detected_under_each_name() {
+        for name in ["justfile", "Justfile", ".justfile"] {
+            let dir = project(&[(name, "build:\n\tcc -o foo foo.c\n")]);
+            assert_eq!(detect(dir.path(), &[]), Some(BuildSystem::Just), "{}", name);
+            assert_eq!(BuildSystem::Just.build_file(dir.path()), Some(dir.path().join(name)));
+        }
+    }
+
+    #[test]
+    fn a_makefile_wins_over_a_justfile() {
+        let files = [("justfile", "build:\n\tmake\n"), ("Makefile", "all:\n")];
+        assert_eq!(detected(&files), Some(BuildSystem::Make));
+        assert_eq!(detect(project(&files).path(), &[BuildSystem::Just]), Some(BuildSystem::Just));
+        assert_eq!(detected(&[("justfile", ""), ("Cargo.toml", "[package]\n")]), Some(BuildSystem::Just));
+    }
+
+    #[test]
+    fn scons_projects_are_detected_by_either_file() {
+        for name in ["SConstruct", "Sconstruct", "sconstruct", "SConscript"] {
+            let dir = project(&[(name, "Program('foo.c')\n")]);
+            assert_eq!(detect(dir.path(), &[]), Some(BuildSystem::SCons), "{}", name);
+            assert_eq!(BuildSystem::SCons.build_file(dir.path()), Some(dir.path().join(name)));
+        }
+        // SConstruct is the one to review when both are there
+        let dir = project(&[("SConscript", ""), ("SConstruct", "")]);
+        assert_eq!(BuildSystem::SCons.build_file(dir.path()), Some(dir.path().join("SConstruct")));
+        assert_eq!(BuildSystem::SCons.required_tools(dir.path()), ["scons"]);
+        assert_eq!(BuildSystem::SCons.display_name(), "SCons");
+    }
+
+    #[test]
+    fn dub_projects_are_detected_from_json_and_sdl() {
+        let dir = project(&[("dub.json", r#"{ "name": "tool" }"#)]);
+        assert_eq!(detect(dir.path(), &[]), Some(BuildSystem::Dub));
+        assert_eq!(dub_target(dir.path()).as_deref(), Some("tool"));
+
+        let dir = project(&[("dub.sdl", "name \"tool\"\ndescription \"a tool\"\n")]);
+        assert_eq!(detect(dir.path(), &[]), Some(BuildSystem::Dub));
+        assert_eq!(BuildSystem::Dub.build_file(dir.path()), Some(dir.path().join("dub.sdl")));
+        assert_eq!(dub_target(dir.path()).as_deref(), Some("tool"));
+    }
+
+    #[test]
+    fn dub_targets_follow_target_name_and_path() {
+        let json = project(&[("dub.json", r#"{ "name": "tool", "targetName": "tl", "targetPath": "bin/" }"#)]);
+        assert_eq!(dub_target(json.path()).as_deref(), Some("bin/tl"));
+        let sdl = project(&[("dub.sdl", "name \"tool\"\ntargetPath \"out\"\nnamespace \"x\"\n")]);
+        assert_eq!(dub_target(sdl.path()).as_deref(), Some("out/tool"));
+        assert_eq!(dub_target(project(&[("dub.json", "{}")]).path()), None);
+        assert_eq!(dub_target(project(&[]).path()), None);
+    }
+
+    #[test]
+    fn swift_packages_are_detected() {
+        let dir = project(&[("Package.swift", "// swift-tools-version:5.9\nimport PackageDescription\n"), ("Sources/tool/main.swift", "print(1)\n")]);
+        assert_eq!(detect(dir.path(), &[]), Some(BuildSystem::Swift));
+        assert_eq!(BuildSystem::Swift.build_file(dir.path()), Some(dir.path().join("Package.swift")));
+        assert_eq!(detected(&[("Sources/tool/main.swift", "print(1)\n")]), None);
+        // a Makefile that wraps swift build wins
+        assert_eq!(detected(&[("Package.swift", ""), ("Makefile", "all:\n\tswift build\n")]), Some(BuildSystem::Make));
+    }
+
+    #[test]
+    fn beam_projects_are_detected() {
+        let dir = project(&[("gleam.toml", "name = \"tool\"\nversion = \"1.0.0\"\n")]);
+        assert_eq!(detect(dir.path(), &[]), Some(BuildSystem::Gleam));
+        assert_eq!(gleam_name(dir.path()).as_deref(), Some("tool"));
+        assert_eq!(detected(&[("rebar.config", "{escript_main_app, tool}.\n")]), Some(BuildSystem::Rebar3));
+        let escript = "defmodule Tool.MixProject do\n  def project, do: [app: :tool, escript: [main_module: Tool]]\nend\n";
+        assert_eq!(detected(&[("mix.exs", escript)]), Some(BuildSystem::Mix));
+        assert_eq!(gleam_name(project(&[("gleam.toml", "version = \"1.0.0\"\n")]).path()), None);
+    }
+
+    #[test]
+    fn mix_projects_without_an_escript_are_not_installable() {
+        let library = "defmodule Lib.MixProject do\n  def project, do: [app: :lib]\nend\n";
+        assert_eq!(detected(&[("mix.exs", library)]), None);
+        // rebar3 comes before mix in projects that carry both
+        assert_eq!(detected(&[("mix.exs", "escript: []"), ("rebar.config", "")]), Some(BuildSystem::Rebar3));
+    }
+
+    #[test]
+    fn qmake_owns_the_makefile_it_generated() {
+        let generated = "#############################################################################\n# Makefile for building: tool\n# Generated by qmake (3.1) (Qt 6.6.1)\n";
+        assert_eq!(detected(&[("tool.pro", "TARGET = tool\n"), ("Makefile", generated)]), Some(BuildSystem::QMake));
+        assert_eq!(detected(&[("tool.pro", "TARGET = tool\n"), ("Makefile", "all:\n\tqmake && make\n")]), Some(BuildSystem::Make));
+        assert_eq!(detected(&[("Makefile", generated)]), Some(BuildSystem::Make));
+        assert_eq!(detected(&[("tool.pro", "")]), Some(BuildSystem::QMake));
+    }
+
+    #[test]
+    fn qmake_targets_come_from_the_pro_file() {
+        let target = |pro: &str| {
+            let dir = project(&[("tool.pro", pro)]);
+            qmake_target(&dir.path().join("tool.pro"))
+        };
+        assert_eq!(target("QT += widgets\nTARGET = viewer\nSOURCES += main.cpp\n").as_deref(), Some("viewer"));
+        assert_eq!(target("TARGET=\"quoted\"\n").as_deref(), Some("quoted"));
+        assert_eq!(target("TARGET = $$qtLibraryTarget(viewer)\n"), None);
+        assert_eq!(target("TARGET +=\nSOURCES += main.cpp\n"), None);
+        assert_eq!(target("SOURCES += main.cpp\n"), None);
+    }
+
+    #[test]
+    fn xmake_projects_are_detected() {
+        let dir = project(&[("xmake.lua", "target(\"tool\")\n    set_kind(\"binary\")\n")]);
+        assert_eq!(detect(dir.path(), &[]), Some(BuildSystem::XMake));
+        assert_eq!(BuildSystem::XMake.build_file(dir.path()), Some(dir.path().join("xmake.lua")));
+        assert_eq!(BuildSystem::XMake.required_tools(dir.path()), ["xmake"]);
+        // the generic build systems come first
+        assert_eq!(detected(&[("xmake.lua", ""), ("CMakeLists.txt", "")]), Some(BuildSystem::CMake));
+        assert_eq!(detected(&[("xmake.lua", ""), ("tool.pro", "")]), Some(BuildSystem::QMake));
+    }
+
+    #[test]
+    fn bazel_is_only_detected_by_root_workspace_markers() {
+        for marker in ["MODULE.bazel", "WORKSPACE.bazel", "WORKSPACE"] {
+            assert_eq!(detected(&[(marker, "")]), Some(BuildSystem::Bazel), "{}", marker);
+        }
+        // a vendored BUILD file, or a workspace of a vendored dependency, doesn't make a bazel project
+        assert_eq!(detected(&[("third_party/zlib/BUILD", "cc_library(name = \"zlib\")\n")]), None);
+        assert_eq!(detected(&[("third_party/abseil/WORKSPACE", ""), ("BUILD.bazel", "")]), None);
+        assert_eq!(detected(&[("third_party/zlib/BUILD", ""), ("Makefile", "all:\n")]), Some(BuildSystem::Make));
+        // a directory named like a marker isn't one
+        assert_eq!(detected(&[("WORKSPACE/notes", "")]), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn waf_is_detected_by_wscript_or_an_executable_waf() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = project(&[("wscript", "def build(bld): pass\n")]);
+        assert_eq!(detect(dir.path(), &[]), Some(BuildSystem::Waf));
+        assert_eq!(BuildSystem::Waf.required_tools(dir.path()), ["python3", "waf"]);
+
+        let dir = project(&[("waf", "#!/usr/bin/env python3\n")]);
+        assert_eq!(detect(dir.path(), &[]), None);
+        fs::set_permissions(dir.path().join("waf"), fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(detect(dir.path(), &[]), Some(BuildSystem::Waf));
+        assert_eq!(BuildSystem::Waf.required_tools(dir.path()), ["python3"]);
+    }
+
+    #[test]
+    fn cabal_executables_come_from_the_stanzas() {
+        let cabal = "cabal-version: 2.4\nname: tool\n\nlibrary\n  exposed-modules: Tool\n\nExecutable tool\n  main-is: Main.hs\n\nexecutable tool-helper\n  main-is: Helper.hs\n\ntest-suite spec\n  type: exitcode-stdio-1.0\n  -- executable commented\n";
+        let dir = project(&[("tool.cabal", cabal)]);
+        assert_eq!(detect(dir.path(), &[]), Some(BuildSystem::Cabal));
+        assert_eq!(declared_executables(BuildSystem::Cabal, dir.path()), ["tool", "tool-helper"]);
+        // stack builds cabal packages too, and comes first
+        assert_eq!(detected(&[("tool.cabal", cabal), ("stack.yaml", "resolver: lts-22.0\n")]), Some(BuildSystem::Stack));
+    }
+
+    #[test]
+    fn dune_executables_are_the_public_names() {
+        let dir = project(&[
+            ("dune-project", "(lang dune 3.0)\n"),
+            ("bin/dune", "(executable\n (public_name tool)\n (name main))\n"),
+            ("tools/dune", "(executables\n (public_names tool-a tool-b)\n (names a b))\n"),
+            ("test/dune", "(executable (name private_test))\n"),
+            ("_build/default/bin/dune", "(executable (public_name stale))\n"),
+        ]);
+        assert_eq!(detect(dir.path(), &[]), Some(BuildSystem::Dune));
+        assert_eq!(declared_executables(BuildSystem::Dune, dir.path()), ["tool", "tool-a", "tool-b"]);
+        assert_eq!(detected(&[("bin/dune", "(executable (public_name tool))\n")]), None);
+    }
+
+    const CMAKE_PRESETS: &str = r#"{
+        "version": 3,
+        "configurePresets": [
+            {"name": "base", "hidden": true, "binaryDir": "${sourceDir}/out/${presetName}"},
+            {"name": "default", "inherits": "base"},
+            {"name": "release", "inherits": ["base"], "cacheVariables": {"CMAKE_BUILD_TYPE": "Release"}},
+            {"name": "own-dir", "binaryDir": "build-${sourceDirName}"},
+            {"name": "no-dir"}
+        ],
+        "buildPresets": [
+            {"name": "build-release", "configurePreset": "release"},
+            {"name": "hidden-build", "hidden": true, "configurePreset": "default"}
+        ]
+    }"#;
+
+    #[test]
+    fn cmake_presets_resolve_inherited_binary_dirs() {
+        let dir = project(&[("CMakeLists.txt", ""), ("CMakePresets.json", CMAKE_PRESETS)]);
+        let presets = cmake_presets(dir.path());
+        let names: Vec<&str> = presets.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["default", "release", "own-dir", "no-dir"]);
+
+        let source = dir.path().to_string_lossy();
+        assert_eq!(presets[0].binary_dir, Some(PathBuf::from(format!("{}/out/default", source))));
+        assert_eq!(presets[1].binary_dir, Some(PathBuf::from(format!("{}/out/release", source))));
+        let name = dir.path().file_name().unwrap().to_string_lossy();
+        assert_eq!(presets[2].binary_dir, Some(dir.path().join(format!("build-{}", name))));
+        assert_eq!(presets[3].binary_dir, None);
+
+        assert_eq!(presets[1].build_preset.as_deref(), Some("build-release"));
+        assert_eq!(presets[0].build_preset, None);
+        assert!(cmake_presets(project(&[("CMakeLists.txt", "")]).path()).is_empty());
+        assert!(cmake_presets(project(&[("CMakePresets.json", "{")]).path()).is_empty());
+    }
+}
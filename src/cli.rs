@@ -1,18 +1,155 @@
+use std::path::PathBuf;
 use clap::{Parser, Subcommand, ArgAction};
 
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[command(version = env!("AURORA_VERSION"), about, long_about = None)]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+
+    // ask the AUR again instead of using cached answers
+    #[arg(long, global = true)]
+    pub refresh: bool,
+
+    // -v for debug output and the commands being run, -vv for trace output
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     Install(InstallArgs),
+    Build(InstallArgs),
+    // a shell in the build directory with the build environment, before building as usual
+    Shell(InstallArgs),
+    Fetch {
+        #[arg(required = true)]
+        packages: Vec<String>,
+    },
+    List {
+        // dependencies that nothing tracked needs any more
+        #[arg(long)]
+        orphans: bool,
+    },
+    Search(SearchArgs),
+    Info {
+        package: String,
+    },
+    Deps(DepsArgs),
+    // reads a file from the package's AUR repository without cloning it
+    Preview {
+        package: String,
+        #[arg(long, default_value = "PKGBUILD")]
+        file: String,
+    },
+    Comments {
+        package: String,
+
+        #[arg(short = 'n', long, default_value_t = 5)]
+        count: usize,
+    },
+    Why {
+        package: String,
+    },
+    // what aurora installed and downgraded, newest first
+    History {
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+        #[arg(long)]
+        package: Option<String>,
+        #[arg(long)]
+        json: bool,
+    },
+    // reverts the last install or downgrade in the history
+    Undo {
+        // revert without asking first
+        #[arg(short, long)]
+        yes: bool,
+    },
+    // what changed upstream since the installed build
+    Diff {
+        package: String,
+    },
+    // pages the log of the package's last build
+    Logs {
+        package: String,
+        // the output with its escape sequences, as the terminal showed it
+        #[arg(long)]
+        raw: bool,
+    },
+    // checks installed files against the hashes recorded at install time
+    Verify {
+        packages: Vec<String>,
+        #[arg(long)]
+        json: bool,
+    },
+    // rebuilds packages whose binaries can't find a shared library any more
+    CheckRebuild,
+    // compares every foreign package pacman knows with its AUR version; exits with 100 when there
+    // are updates
+    CheckUpdates {
+        // also check -git packages against their upstream branch
+        #[arg(long)]
+        devel: bool,
+        #[arg(long)]
+        json: bool,
+    },
+    Downgrade {
+        package: String,
+    },
+    Pin {
+        package: String,
+    },
+    Unpin {
+        package: String,
+    },
+    Check {
+        path: Option<PathBuf>,
+    },
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    Init(InitArgs),
+    Clean {
+        #[arg(long)]
+        rpc_cache: bool,
+        // forget which reviews were accepted, so every package is reviewed again
+        #[arg(long)]
+        trust: bool,
+    },
+    SelfUpdate {
+        // only report whether a newer release exists
+        #[arg(long)]
+        check: bool,
+    },
+    // the --version details: commit, build date, compiler
+    Version {
+        #[arg(long)]
+        json: bool,
+    },
+    // for packagers: writes the man pages
+    #[command(hide = true)]
+    GenerateMan {
+        #[arg(long, default_value = ".")]
+        out_dir: PathBuf,
+    },
+    // `aurora <query>` searches and offers to install the results
+    #[command(external_subcommand)]
+    External(Vec<String>),
 }
 
-#[derive(Parser)]
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    Show,
+    // packages answered with "always" at the review prompt
+    Trusted {
+        #[arg(long, value_name = "PACKAGE", action = ArgAction::Append)]
+        remove: Vec<String>,
+    },
+}
+
+#[derive(Parser, Clone)]
 pub struct InstallArgs {
     #[arg(required = true)]
     pub packages: Vec<String>,
@@ -20,6 +157,240 @@ pub struct InstallArgs {
     #[arg(short, long, action = ArgAction::Append)]
     pub flags: Vec<String>,
 
+    #[arg(long = "pkg-flag", value_name = "PACKAGE:FLAG", action = ArgAction::Append)]
+    pub pkg_flags: Vec<String>,
+
+    // both --skip-review and --no-confirm
+    #[arg(short, long)]
+    pub yes: bool,
+
+    // don't show the PKGBUILD or build files; other questions are still asked
+    #[arg(long)]
+    pub skip_review: bool,
+
+    // answer yes to everything but the review, and pass --noconfirm to makepkg and pacman
+    #[arg(long)]
+    pub no_confirm: bool,
+
+    // unanswered prompts take their default after this many seconds; the review still waits
+    #[arg(long, value_name = "SECS")]
+    pub prompt_timeout: Option<u64>,
+
+    #[arg(long)]
+    pub force: bool,
+
+    #[arg(long)]
+    pub system: bool,
+
+    #[arg(long)]
+    pub allow_root: bool,
+
+    #[arg(long)]
+    pub prefix: Option<PathBuf>,
+
+    #[arg(long, conflicts_with_all = ["system", "prefix"])]
+    pub dest: Option<PathBuf>,
+
+    #[arg(long = "target", value_name = "TRIPLE")]
+    pub target_triple: Option<String>,
+
+    #[arg(long, default_value = "release", value_parser = ["release", "debug"])]
+    pub profile: String,
+
+    #[arg(long)]
+    pub preset: Option<String>,
+
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_env, action = ArgAction::Append)]
+    pub env: Vec<(String, String)>,
+
+    #[arg(long)]
+    pub no_registry: bool,
+
+    // pinned comments plus this many recent ones
+    #[arg(long, value_name = "N", num_args = 0..=1, require_equals = true, default_missing_value = "3")]
+    pub comments: Option<usize>,
+
+    #[arg(long)]
+    pub skip_preflight: bool,
+
+    // don't check or install the system packages the manifest lists
+    #[arg(long)]
+    pub skip_deps: bool,
+
+    // runs pacman -Syu before the first build on arch
+    #[arg(long)]
+    pub sysupgrade: bool,
+
+    // skip the PKGBUILD when the .SRCINFO summary shows nothing to look at
+    #[arg(long)]
+    pub summary_only: bool,
+
+    // review even when nothing changed since the last accepted review
+    #[arg(long)]
+    pub review_always: bool,
+
+    #[arg(long)]
+    pub ignorearch: bool,
+
+    // builds see only PATH, HOME, LANG, TERM, clean_env_passthrough and the recipe's env
+    #[arg(long)]
+    pub clean_env: bool,
+
+    // builds run at this niceness, 19 without a value
+    #[arg(long, value_name = "N", num_args = 0..=1, require_equals = true, default_missing_value = "19")]
+    pub nice: Option<i32>,
+
+    // builds only get the disk when nothing else wants it, like ionice -c3
+    #[arg(long)]
+    pub ionice: bool,
+
+    // each build waits while the 1-minute load average is above this
+    #[arg(long, value_name = "LOAD")]
+    pub max_load: Option<f64>,
+
+    // runs the build commands in bubblewrap: only the build directory and tool caches are writable,
+    // ~/.ssh and ~/.gnupg are hidden
+    #[arg(long)]
+    pub sandbox: bool,
+
+    // --sandbox without network access once the sources are fetched
+    #[arg(long)]
+    pub sandbox_no_net: bool,
+
+    // runs the project's tests after the build; failing tests stop the install
+    #[arg(long)]
+    pub with_tests: bool,
+
+    // downloads from this domain are allowed on this run, on top of allowed_domains
+    #[arg(long, value_name = "DOMAIN", action = ArgAction::Append)]
+    pub allow_domain: Vec<String>,
+
+    // installs the packages as dependencies of this one, see `aurora why`
+    #[arg(long, value_name = "PACKAGE")]
+    pub required_by: Option<String>,
+
+    #[arg(long)]
+    pub dry_run: bool,
+
+    #[arg(long)]
+    pub offline: bool,
+
+    #[arg(long)]
+    pub from_build: bool,
+
+    // log events as JSON lines on stderr; with --dry-run, the plan as JSON as well
+    #[arg(long)]
+    pub json: bool,
+
+    // set by `aurora build`
+    #[arg(skip)]
+    pub build_only: bool,
+
+    // set by `aurora shell`
+    #[arg(skip)]
+    pub shell: bool,
+}
+
+impl InstallArgs {
+    pub fn skips_review(&self) -> bool {
+        self.yes || self.skip_review
+    }
+
+    pub fn answers_yes(&self) -> bool {
+        self.yes || self.no_confirm
+    }
+
+    pub fn sandboxed(&self) -> bool {
+        self.sandbox || self.sandbox_no_net
+    }
+}
+
+fn parse_env(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{}'", s)),
+    }
+}
+
+#[derive(Parser, Default)]
+pub struct SearchArgs {
+    #[arg(required_unless_present = "maintainer")]
+    pub query: Vec<String>,
+
+    #[arg(long)]
+    pub maintainer: Option<String>,
+
+    #[arg(long)]
+    pub installed_only: bool,
+
+    #[arg(long)]
+    pub out_of_date: bool,
+
+    #[arg(long, value_name = "N")]
+    pub min_votes: Option<u64>,
+
+    #[arg(short, long)]
+    pub interactive: bool,
+
+    #[arg(long)]
+    pub fzf: bool,
+}
+
+#[derive(Parser)]
+pub struct DepsArgs {
+    pub package: String,
+
     #[arg(short, long)]
     pub yes: bool,
+
+    #[arg(long)]
+    pub depth: Option<usize>,
+
+    #[arg(long, conflicts_with = "json")]
+    pub flat: bool,
+
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Parser)]
+pub struct InitArgs {
+    #[arg(long, default_value = "json", value_parser = ["json", "toml"])]
+    pub format: String,
+
+    #[arg(long)]
+    pub force: bool,
+
+    #[arg(long)]
+    pub non_interactive: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn install(flags: &[&str]) -> InstallArgs {
+        let args = ["install", "foo"].iter().chain(flags);
+        InstallArgs::try_parse_from(args).unwrap_or_else(|e| panic!("{:?}: {}", flags, e))
+    }
+
+    #[test]
+    fn review_and_confirmation_flags() {
+        // flags, skips the review, answers yes
+        let matrix: [(&[&str], bool, bool); 8] = [
+            (&[], false, false),
+            (&["--skip-review"], true, false),
+            (&["--no-confirm"], false, true),
+            (&["--skip-review", "--no-confirm"], true, true),
+            (&["--yes"], true, true),
+            (&["-y"], true, true),
+            (&["--yes", "--skip-review"], true, true),
+            (&["-y", "--no-confirm"], true, true),
+        ];
+        for (flags, skips_review, answers_yes) in matrix {
+            let args = install(flags);
+            assert_eq!(args.skips_review(), skips_review, "skips_review with {:?}", flags);
+            assert_eq!(args.answers_yes(), answers_yes, "answers_yes with {:?}", flags);
+        }
+    }
 }
@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+use ansi_term::Colour::{Green, Red, Yellow};
+use crate::manifest::{self, AuroraManifest};
+
+pub fn check(path: Option<PathBuf>) {
+    let path = match path.or_else(|| manifest::find(&PathBuf::from("."))) {
+        Some(path) => path,
+        None => {
+            eprintln!("{} No aurora.toml, aurora.yaml or aurora.json found", Red.paint("Error:"));
+            std::process::exit(1);
+        }
+    };
+
+    let manifest = match AuroraManifest::load(&path) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eprintln!("{} {}: {}", Red.paint("Error:"), path.display(), e);
+            std::process::exit(1);
+        }
+    };
+
+    for key in manifest.unknown_fields() {
+        println!("{} Unknown field '{}'", Yellow.paint("~> Warning:"), key);
+    }
+
+    match manifest.build_system {
+        Some(build_system) => println!("~> Build system: {}", build_system.display_name()),
+        None => println!("~> Build system: detected"),
+    }
+    if let Some(schema) = manifest.aurora.schema {
+        println!("~> Schema: {}", schema);
+    }
+    if let Some(min_version) = &manifest.aurora.min_version {
+        println!("~> Requires aurora {} or newer", min_version);
+    }
+    println!("{} {} is valid", Green.paint("~>"), path.display());
+}
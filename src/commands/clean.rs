@@ -0,0 +1,38 @@
+use std::fs;
+use std::path::Path;
+use ansi_term::Colour::{Green, Red};
+use crate::aur;
+use crate::paths;
+use crate::trust;
+
+// removes the build cache and cached AUR answers; `rpc_cache_only` keeps the builds, and
+// `trust_only` only forgets the accepted reviews
+pub fn clean(rpc_cache_only: bool, trust_only: bool) {
+    if trust_only {
+        let file = trust::trust_file();
+        if !file.exists() {
+            println!("~> No accepted reviews to forget");
+            return;
+        }
+        match fs::remove_file(&file) {
+            Ok(()) => println!("{} accepted reviews ({})", Green.paint("~> Forgot the"), file.display()),
+            Err(e) => eprintln!("{} Could not remove {}: {}", Red.paint("Error:"), file.display(), e),
+        }
+        return;
+    }
+    if !rpc_cache_only {
+        remove("build cache", &paths::temp_dir().join("builds"));
+    }
+    remove("AUR response cache", &aur::cache_dir());
+}
+
+fn remove(what: &str, dir: &Path) {
+    if !dir.exists() {
+        println!("~> No {} to clean", what);
+        return;
+    }
+    match fs::remove_dir_all(dir) {
+        Ok(()) => println!("{} {} ({})", Green.paint("~> Removed the"), what, dir.display()),
+        Err(e) => eprintln!("{} Could not remove {}: {}", Red.paint("Error:"), dir.display(), e),
+    }
+}
@@ -0,0 +1,39 @@
+use ansi_term::Colour::{Red, Yellow};
+use crate::aur;
+
+pub fn comments(package: &str, count: usize) {
+    if !show(package, count) {
+        std::process::exit(1);
+    }
+}
+
+// pinned comments and the `count` most recent ones; failures are only a warning since the
+// page is scraped and may change under us
+pub fn show(package: &str, count: usize) -> bool {
+    let comments = match aur::comments(package) {
+        Ok(comments) => comments,
+        Err(e) => {
+            println!("{} Could not fetch AUR comments for {}: {}", Yellow.paint("~> Warning:"), package, e);
+            return false;
+        }
+    };
+
+    let pinned = comments.iter().filter(|c| c.pinned);
+    let latest = comments.iter().filter(|c| !c.pinned).take(count);
+    let mut shown = false;
+    for comment in pinned.chain(latest) {
+        if comment.pinned {
+            println!("{} {}", Red.bold().paint("~> Pinned:"), comment.header);
+        } else {
+            println!("{} {}", Yellow.bold().paint("~>"), comment.header);
+        }
+        for line in comment.body.lines() {
+            println!("   {}", line);
+        }
+        shown = true;
+    }
+    if !shown {
+        println!("~> No comments on {}", package);
+    }
+    true
+}
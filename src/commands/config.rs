@@ -0,0 +1,71 @@
+use ansi_term::Colour::Green;
+use crate::cli::ConfigAction;
+use crate::config::{self, Config};
+use crate::state::State;
+use crate::trust;
+
+pub fn config(action: ConfigAction) {
+    match action {
+        ConfigAction::Show => show(),
+        ConfigAction::Trusted { remove } => trusted(&remove),
+    }
+}
+
+fn trusted(remove: &[String]) {
+    for package in remove {
+        if trust::set_always(package, false) {
+            println!("{} {} is reviewed again like any other package", Green.paint("~>"), package);
+        } else {
+            println!("~> {} was not always trusted", package);
+        }
+    }
+    if !remove.is_empty() {
+        return;
+    }
+
+    let packages = trust::always_trusted();
+    if packages.is_empty() {
+        println!("~> No package is always trusted; answer \"a\" at a review prompt to add one");
+        return;
+    }
+    println!("~> Always trusted while their build files are unchanged:");
+    for package in packages {
+        println!("   {}", package);
+    }
+}
+
+fn show() {
+    let path = config::config_dir().join("config.toml");
+    let config = Config::load();
+
+    if path.exists() {
+        println!("~> Config file: {}", path.display());
+    } else {
+        println!("~> Config file: {} (not present, using defaults)", path.display());
+    }
+    println!("   dest: {}", config.dest_dir().display());
+    if let Some(registry) = &config.registry {
+        println!("   registry: {}", registry);
+    }
+    if !config.build_priority.is_empty() {
+        println!("   build_priority: {}", config.build_priority.join(", "));
+    }
+    for (key, value) in &config.env {
+        println!("   env: {}={}", key, value);
+    }
+    println!("~> Local recipes: {}", config::config_dir().join("recipes").display());
+
+    let state = State::load();
+    if state.packages.is_empty() {
+        return;
+    }
+
+    println!("~> Recipe used for the last install:");
+    for (name, record) in &state.packages {
+        println!(
+            "   {}: {}",
+            Green.bold().paint(name),
+            record.recipe.as_deref().unwrap_or("detected")
+        );
+    }
+}
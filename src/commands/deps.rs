@@ -0,0 +1,394 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, IsTerminal};
+use ansi_term::Colour::{Green, Red, Yellow};
+use crate::aur;
+use crate::cli::DepsArgs;
+use crate::config;
+use crate::distro;
+use crate::utils;
+use crate::version;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Status {
+    Installed,
+    Repo,
+    Aur,
+}
+
+impl Status {
+    fn name(self) -> &'static str {
+        match self {
+            Status::Installed => "installed",
+            Status::Repo => "repo",
+            Status::Aur => "aur",
+        }
+    }
+
+    fn tag(self) -> String {
+        let tag = format!("[{}]", self.name());
+        match self {
+            Status::Installed => Green.paint(tag).to_string(),
+            Status::Repo => tag,
+            Status::Aur => Yellow.paint(tag).to_string(),
+        }
+    }
+}
+
+struct Node {
+    status: Status,
+    // the package picked for a virtual dependency
+    provider: Option<String>,
+    version: Option<String>,
+    depends: Vec<String>,
+    make_depends: Vec<String>,
+}
+
+// what to do when several packages provide a dependency and none was picked before
+#[derive(Clone, Copy, PartialEq)]
+enum Pick {
+    Ask,
+    // --yes: the newest repo package, or the most popular AUR one
+    Best,
+    // nobody to ask, e.g. --json
+    Fail,
+}
+
+pub fn deps(args: &DepsArgs) {
+    // prompts would end up in the JSON
+    let pick = if args.yes {
+        Pick::Best
+    } else if args.json || !io::stdin().is_terminal() {
+        Pick::Fail
+    } else {
+        Pick::Ask
+    };
+    let graph = match resolve(&args.package, args.depth, pick) {
+        Ok(graph) => graph,
+        Err(e) => {
+            eprintln!("{} {}", Red.paint("Error:"), e);
+            std::process::exit(1);
+        }
+    };
+
+    if args.json {
+        let packages: serde_json::Map<String, serde_json::Value> = graph
+            .iter()
+            .map(|(name, node)| {
+                let node = serde_json::json!({
+                    "status": node.status.name(),
+                    "provider": node.provider,
+                    "version": node.version,
+                    "depends": node.depends,
+                    "makedepends": node.make_depends,
+                });
+                (name.clone(), node)
+            })
+            .collect();
+        let json = serde_json::json!({ "root": args.package, "packages": packages });
+        println!("{}", serde_json::to_string_pretty(&json).expect("Failed to serialize dependency graph"));
+    } else if args.flat {
+        for (name, node) in graph.iter().filter(|(name, _)| **name != args.package) {
+            println!("{}{} {}", name, provided_by(node), node.status.tag());
+        }
+    } else {
+        print_tree(&graph, &args.package, false, 0, args.depth, &mut vec![], &mut BTreeSet::new());
+    }
+}
+
+// walks the graph a level at a time so each level is one batched lookup; only AUR
+// packages are expanded, pacman knows the dependencies of everything else
+fn resolve(root: &str, max_depth: Option<usize>, pick: Pick) -> Result<BTreeMap<String, Node>, String> {
+    let mut graph: BTreeMap<String, Node> = BTreeMap::new();
+    // version constraints on each package, and who first pulled it in
+    let mut wanted: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut parents: BTreeMap<String, String> = BTreeMap::new();
+    let mut level = vec![root.to_string()];
+    let mut depth = 0;
+
+    while !level.is_empty() {
+        // an installed version that breaks a constraint needs replacing, so it doesn't count;
+        // packages only installed as a provider are left to `pacman -T`
+        let versions = distro::installed_versions(&level);
+        let specs: Vec<String> = level
+            .iter()
+            .filter(|name| !versions.contains_key(*name))
+            .flat_map(|name| wanted.get(name).cloned().unwrap_or_else(|| vec![name.clone()]))
+            .collect();
+        let provided = distro::installed(&specs);
+        let installed: BTreeSet<String> = level
+            .iter()
+            .filter(|name| match versions.get(*name) {
+                Some(version) => constraints_hold(wanted.get(*name), version),
+                None => wanted
+                    .get(*name)
+                    .map_or(provided.contains(*name), |specs| specs.iter().all(|spec| provided.contains(spec))),
+            })
+            .cloned()
+            .collect();
+        let query: Vec<String> = level
+            .iter()
+            .filter(|name| *name == root || !installed.contains(*name))
+            .cloned()
+            .collect();
+        let mut found = aur::info(&query)?;
+
+        if depth == 0 && !found.contains_key(root) {
+            return Err(format!("{} was not found in the AUR", root));
+        }
+
+        // names that are neither installed nor a real package anywhere are virtual
+        let unknown: Vec<String> = query.iter().filter(|name| !found.contains_key(*name)).cloned().collect();
+        let mut providers = BTreeMap::new();
+        let in_repos = distro::in_repos(&unknown);
+        if utils::command_exists("pacman") {
+            for name in unknown.iter().filter(|name| !in_repos.contains_key(*name)) {
+                if let Some(provider) = choose_provider(name, pick)? {
+                    providers.insert(name.clone(), provider);
+                }
+            }
+        }
+        let aur_providers: Vec<String> = providers
+            .values()
+            .filter(|(_, from_aur)| *from_aur)
+            .map(|(provider, _)| provider.clone())
+            .collect();
+        let from_aur = aur::info(&aur_providers)?;
+        for (name, (provider, _)) in &providers {
+            if let Some(package) = from_aur.get(provider) {
+                found.insert(name.clone(), package.clone());
+            }
+        }
+
+        let mut next = Vec::new();
+        for name in &level {
+            let status = if installed.contains(name) {
+                Status::Installed
+            } else if found.contains_key(name) {
+                Status::Aur
+            } else {
+                Status::Repo
+            };
+
+            let node = match found.get(name).filter(|_| status == Status::Aur || name == root) {
+                Some(package) => Node {
+                    status,
+                    provider: providers.get(name).map(|(p, _)| p.clone()),
+                    version: Some(package.version.clone()),
+                    depends: package.depends.iter().map(|d| version::parse_dependency(d).0.to_string()).collect(),
+                    make_depends: package.make_depends.iter().map(|d| version::parse_dependency(d).0.to_string()).collect(),
+                },
+                None => Node {
+                    status,
+                    provider: providers.get(name).map(|(p, _)| p.clone()),
+                    version: match status {
+                        Status::Installed => versions.get(name).cloned(),
+                        _ => in_repos.get(name).cloned(),
+                    },
+                    depends: vec![],
+                    make_depends: vec![],
+                },
+            };
+
+            let specs = found.get(name).map(|p| p.depends.iter().chain(&p.make_depends));
+            for spec in specs.into_iter().flatten() {
+                let (dependency, constraint) = version::parse_dependency(spec);
+                if constraint.is_some() {
+                    wanted.entry(dependency.to_string()).or_default().push(spec.clone());
+                }
+                if dependency != root {
+                    parents.entry(dependency.to_string()).or_insert_with(|| name.clone());
+                }
+            }
+
+            if max_depth.is_none_or(|max| depth < max) {
+                for dependency in node.depends.iter().chain(&node.make_depends) {
+                    if !graph.contains_key(dependency) && !level.contains(dependency) && !next.contains(dependency) {
+                        next.push(dependency.clone());
+                    }
+                }
+            }
+            graph.insert(name.clone(), node);
+        }
+
+        level = next;
+        depth += 1;
+    }
+
+    // constraints found after a package was resolved are only checked here
+    for (name, specs) in &wanted {
+        let version = match graph.get(name).and_then(|node| node.version.as_deref()) {
+            Some(version) if graph[name].status != Status::Installed => version,
+            _ => continue,
+        };
+        if let Some(spec) = specs.iter().find(|spec| !constraint_holds(spec, version)) {
+            let mut chain = vec![spec.clone()];
+            let mut current = name;
+            while let Some(parent) = parents.get(current) {
+                chain.insert(0, parent.clone());
+                current = parent;
+            }
+            return Err(format!(
+                "{}: no version satisfies it, the {} has {} {}",
+                chain.join(" -> "),
+                if graph[name].status == Status::Aur { "AUR" } else { "repository" },
+                name,
+                version
+            ));
+        }
+    }
+    Ok(graph)
+}
+
+fn constraint_holds(spec: &str, version: &str) -> bool {
+    match version::parse_dependency(spec).1 {
+        Some((op, wanted)) => version::satisfies(version, op, wanted),
+        None => true,
+    }
+}
+
+fn constraints_hold(specs: Option<&Vec<String>>, version: &str) -> bool {
+    specs.into_iter().flatten().all(|spec| constraint_holds(spec, version))
+}
+
+struct Candidate {
+    name: String,
+    from_aur: bool,
+    version: String,
+    popularity: Option<f64>,
+}
+
+// the provider for a virtual dependency and whether it comes from the AUR: the one remembered
+// from an earlier run, the only candidate, or the user's (or --yes's) pick
+fn choose_provider(dependency: &str, pick: Pick) -> Result<Option<(String, bool)>, String> {
+    let repo = distro::repo_providers(dependency);
+    let repo_versions = distro::in_repos(&repo);
+    let mut candidates: Vec<Candidate> = repo
+        .into_iter()
+        .map(|name| Candidate {
+            version: repo_versions.get(&name).cloned().unwrap_or_default(),
+            name,
+            from_aur: false,
+            popularity: None,
+        })
+        .collect();
+    candidates.extend(aur::search(dependency, "provides")?.into_iter().map(|p| Candidate {
+        name: p.name,
+        from_aur: true,
+        version: p.version,
+        popularity: Some(p.popularity),
+    }));
+
+    if let Some(remembered) = config::providers().get(dependency) {
+        if let Some(candidate) = candidates.iter().find(|c| c.name == *remembered) {
+            return Ok(Some((candidate.name.clone(), candidate.from_aur)));
+        }
+    }
+    if candidates.len() <= 1 {
+        return Ok(candidates.into_iter().next().map(|c| (c.name, c.from_aur)));
+    }
+
+    let picked = match pick {
+        Pick::Best => best(&candidates),
+        Pick::Fail => {
+            let names: Vec<&str> = candidates.iter().map(|c| c.name.as_str()).collect();
+            return Err(format!(
+                "{} is provided by {}; pick one with --yes or by running aurora deps in a terminal",
+                dependency,
+                names.join(", ")
+            ));
+        }
+        Pick::Ask => {
+            println!("~> {} is provided by several packages:", dependency);
+            for (n, candidate) in candidates.iter().enumerate() {
+                let popularity = candidate.popularity.map(|p| format!(" ({:.2})", p)).unwrap_or_default();
+                let status = if candidate.from_aur { Status::Aur } else { Status::Repo };
+                println!(
+                    "   {} {} {}{} {}",
+                    Yellow.paint((n + 1).to_string()),
+                    candidate.name,
+                    candidate.version,
+                    popularity,
+                    status.tag()
+                );
+            }
+            let default = best(&candidates);
+            let input = utils::prompt(&format!("~> Which one? [{}]", default + 1));
+            match input.parse::<usize>() {
+                Ok(n) if (1..=candidates.len()).contains(&n) => n - 1,
+                _ if input.is_empty() => default,
+                _ => return Err(format!("'{}' is not one of the providers", input)),
+            }
+        }
+    };
+    let candidate = &candidates[picked];
+    if pick == Pick::Ask {
+        config::remember_provider(dependency, &candidate.name);
+    }
+    Ok(Some((candidate.name.clone(), candidate.from_aur)))
+}
+
+// the repo package with the highest version, or else the most popular AUR package
+fn best(candidates: &[Candidate]) -> usize {
+    let repo = candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| !c.from_aur)
+        // reversed so ties go to the first candidate
+        .rev()
+        .max_by(|(_, a), (_, b)| version::vercmp(&a.version, &b.version));
+    let aur = || {
+        candidates
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by(|(_, a), (_, b)| a.popularity.unwrap_or(0.0).total_cmp(&b.popularity.unwrap_or(0.0)))
+    };
+    repo.or_else(aur).map_or(0, |(n, _)| n)
+}
+
+fn provided_by(node: &Node) -> String {
+    node.provider.as_deref().map(|p| format!(" -> {}", p)).unwrap_or_default()
+}
+
+fn print_tree(
+    graph: &BTreeMap<String, Node>,
+    name: &str,
+    make: bool,
+    depth: usize,
+    max_depth: Option<usize>,
+    ancestors: &mut Vec<String>,
+    expanded: &mut BTreeSet<String>,
+) {
+    let indent = "  ".repeat(depth);
+    let kind = if make { " (make)" } else { "" };
+    let node = match graph.get(name) {
+        Some(node) => node,
+        None => return,
+    };
+    let version = node.version.as_deref().map(|v| format!(" {}", v)).unwrap_or_default();
+    let line = format!("{}{}{}{} {}{}", indent, name, provided_by(node), version, node.status.tag(), kind);
+
+    if ancestors.iter().any(|a| a == name) {
+        println!("{} {}", line, Red.paint("[cycle]"));
+        return;
+    }
+    let has_children = !node.depends.is_empty() || !node.make_depends.is_empty();
+    if has_children && expanded.contains(name) {
+        println!("{} (see above)", line);
+        return;
+    }
+    println!("{}", line);
+
+    if max_depth.is_some_and(|max| depth >= max) {
+        return;
+    }
+    expanded.insert(name.to_string());
+    ancestors.push(name.to_string());
+    for dependency in &node.depends {
+        print_tree(graph, dependency, false, depth + 1, max_depth, ancestors, expanded);
+    }
+    for dependency in &node.make_depends {
+        print_tree(graph, dependency, true, depth + 1, max_depth, ancestors, expanded);
+    }
+    ancestors.pop();
+}
@@ -0,0 +1,165 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use ansi_term::Colour::{Green, Red, Yellow};
+use crate::commands::install;
+use crate::distro;
+use crate::paths;
+use crate::pkgbuild;
+use crate::runner::Run;
+use crate::state::State;
+use crate::utils;
+
+// how far a shallow clone is deepened, in turn, before fetching all of it
+const DEEPEN_STEPS: [u32; 3] = [50, 500, 5000];
+
+// what changed upstream since the installed build: the commits of a package aurora built from its
+// repository, the PKGBUILD of one makepkg built
+pub fn diff(package: &str) {
+    if let Some(commit) = State::load().packages.get(package).and_then(|record| record.commit.clone()) {
+        let dir = fetch(package);
+        if !show_commits(&dir, &commit, "origin/HEAD", true) {
+            println!("{} {} is at the latest commit", Green.paint("~>"), package);
+        }
+        return;
+    }
+    let Some(version) = distro::foreign().remove(package) else {
+        eprintln!("{} {} is not installed, or was installed before aurora recorded commits", Red.paint("Error:"), package);
+        std::process::exit(1);
+    };
+
+    let dir = fetch(package);
+    let Some(current) = output(&dir, &["show", "origin/HEAD:PKGBUILD"]) else {
+        eprintln!("{} The AUR repository for {} has no PKGBUILD", Red.paint("Error:"), package);
+        std::process::exit(1);
+    };
+    let available = output(&dir, &["show", "origin/HEAD:.SRCINFO"]).map(|info| pkgbuild::parse_srcinfo(&info).full_version());
+    match available {
+        Some(available) if available != version => println!("~> {} {} -> {}", package, Red.paint(&version), Green.paint(available)),
+        _ => println!("~> {} {}", package, version),
+    }
+    let fetched = paths::temp_dir().join("diff").join(package).join("PKGBUILD");
+    let written = fetched.parent().is_some_and(|parent| fs::create_dir_all(parent).is_ok()) && fs::write(&fetched, current).is_ok();
+    if !written || !show_pkgbuild_changes(package, &fetched) {
+        eprintln!(
+            "{} aurora has no copy of the PKGBUILD {} {} was built from; it keeps one of the builds it installs",
+            Red.paint("Error:"),
+            package,
+            version
+        );
+        std::process::exit(1);
+    }
+}
+
+// brings the cached clone up to date without touching its checkout, or clones it
+fn fetch(package: &str) -> PathBuf {
+    let dir = paths::builds_dir().join(package);
+    if dir.join(".git").is_dir() {
+        println!("\x1b[1m~> Fetching {}\x1b[0m", package);
+        if !git(&dir, &["fetch", "-q", "origin"]) {
+            println!("{} Could not fetch {}, comparing with what was fetched before", Yellow.paint("~> Warning:"), package);
+        }
+        return dir;
+    }
+    println!("\x1b[1m~> Cloning repository: {}\x1b[0m", package);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).expect("Failed to clean previous build");
+    }
+    if !utils::git_clone(&install::aur_url(package), &dir) {
+        eprintln!("{} Could not clone {}", Red.paint("Error:"), package);
+        std::process::exit(1);
+    }
+    dir
+}
+
+// `git log --oneline` and a diffstat from the installed commit to `target`, deepening a shallow
+// clone until it has the installed commit when `deepen`; false when there is nothing new
+pub fn show_commits(dir: &Path, installed: &str, target: &str, deepen: bool) -> bool {
+    let Some(head) = output(dir, &["rev-parse", target]).map(|head| head.trim().to_string()) else {
+        return false;
+    };
+    if head.starts_with(installed) || installed.starts_with(&head) {
+        return false;
+    }
+
+    let range = format!("{}..{}", installed, target);
+    if !reach(dir, installed, target, deepen) {
+        let count = output(dir, &["rev-list", "--count", target]).unwrap_or_default();
+        match output(dir, &["rev-parse", "--is-shallow-repository"]).is_some_and(|shallow| shallow.trim() == "true") {
+            true => println!("~> The clone is too shallow to reach {}; commits since, at least: {}", utils::short_commit(installed), count.trim()),
+            false => println!(
+                "{} {} is no longer in the upstream history, it was probably rewritten; commits now: {}",
+                Yellow.paint("~> Warning:"),
+                utils::short_commit(installed),
+                count.trim()
+            ),
+        }
+        return true;
+    }
+
+    let count = output(dir, &["rev-list", "--count", &range]).unwrap_or_default();
+    println!("~> Commits since {} ({}):", utils::short_commit(installed), count.trim());
+    let _ = Command::new("git").arg("-C").arg(dir).args(["--no-pager", "log", "--oneline", "--no-decorate", &range]).run_always();
+    let _ = Command::new("git").arg("-C").arg(dir).args(["--no-pager", "diff", "--stat", installed, target]).run_always();
+    true
+}
+
+// whether the installed commit is an ancestor of `target` once the clone is deep enough
+fn reach(dir: &Path, installed: &str, target: &str, deepen: bool) -> bool {
+    let ancestor = || git(dir, &["merge-base", "--is-ancestor", installed, target]);
+    for depth in DEEPEN_STEPS {
+        if ancestor() {
+            return true;
+        }
+        if !deepen {
+            return false;
+        }
+        let shallow = output(dir, &["rev-parse", "--is-shallow-repository"]).is_some_and(|shallow| shallow.trim() == "true");
+        if !shallow || !git(dir, &["fetch", "-q", &format!("--deepen={}", depth), "origin"]) {
+            return false;
+        }
+    }
+    ancestor() || (git(dir, &["fetch", "-q", "--unshallow", "origin"]) && ancestor())
+}
+
+// the diff from the PKGBUILD the installed build came from to `pkgbuild`; false when aurora has
+// no copy of the former
+pub fn show_pkgbuild_changes(package: &str, pkgbuild: &Path) -> bool {
+    let kept = paths::pkgbuild_copy(package);
+    if !kept.is_file() {
+        return false;
+    }
+    let same = fs::read(&kept).ok() == fs::read(pkgbuild).ok();
+    if same {
+        println!("~> The PKGBUILD is the same as the one the installed {} was built from", package);
+        return true;
+    }
+    println!("~> PKGBUILD changes since the installed build:");
+    let _ = Command::new("git").args(["--no-pager", "diff", "--no-index"]).arg(&kept).arg(pkgbuild).run_always();
+    true
+}
+
+// keeps the PKGBUILD a package was just installed from, for the next diff
+pub fn keep_pkgbuild(package: &str, build_dir: &Path) {
+    let kept = paths::pkgbuild_copy(package);
+    let copied = kept.parent().is_some_and(|parent| fs::create_dir_all(parent).is_ok()) && fs::copy(build_dir.join("PKGBUILD"), &kept).is_ok();
+    if !copied {
+        println!("{} Could not keep a copy of the PKGBUILD of {}", Yellow.paint("~> Warning:"), package);
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> bool {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .run_always()
+        .is_ok_and(|s| s.success())
+}
+
+fn output(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).stderr(Stdio::null()).output_always().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
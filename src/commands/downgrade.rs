@@ -0,0 +1,149 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use ansi_term::Colour::{Green, Red, Yellow};
+use crate::distro;
+use crate::journal::{self, Change};
+use crate::paths;
+use crate::runner::Run;
+use crate::state::{self, InstallRecord, State};
+use crate::utils;
+
+enum Version {
+    // a package makepkg built, installed with pacman -U
+    Package(PathBuf),
+    // files saved from an earlier install
+    Saved(PathBuf, Box<InstallRecord>),
+}
+
+pub fn downgrade(package: &str) {
+    let mut versions: Vec<Version> = cached_packages(package).into_iter().map(Version::Package).collect();
+    versions.extend(state::saved_versions(package).into_iter().rev().map(|(dir, record)| Version::Saved(dir, Box::new(record))));
+
+    if versions.is_empty() {
+        eprintln!(
+            "{} No earlier versions of {} are kept; aurora keeps the last few once it has installed it again",
+            Red.paint("Error:"),
+            package
+        );
+        std::process::exit(1);
+    }
+
+    println!("~> Earlier versions of {}:", package);
+    for (n, version) in versions.iter().enumerate() {
+        let label = match version {
+            Version::Package(file) => file.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            Version::Saved(_, record) => format!(
+                "{} build{}, installed {}",
+                record.build_system,
+                record.commit.as_deref().map(|c| format!(" of {}", utils::short_commit(c))).unwrap_or_default(),
+                utils::format_timestamp(record.installed_at)
+            ),
+        };
+        println!("   {} {}", Yellow.paint((n + 1).to_string()), label);
+    }
+    let input = utils::prompt("~> Which one? [1]");
+    let picked = match input.parse::<usize>() {
+        Ok(n) if (1..=versions.len()).contains(&n) => n - 1,
+        _ if input.is_empty() => 0,
+        _ => {
+            eprintln!("{} '{}' is not one of the versions", Red.paint("Error:"), input);
+            std::process::exit(1);
+        }
+    };
+
+    let before = journal::versions().remove(package);
+    let done = match &versions[picked] {
+        Version::Package(file) => install_package(file),
+        Version::Saved(dir, record) => restore(package, dir, record, true),
+    };
+    let after = if done { journal::versions().remove(package) } else { before.clone() };
+    journal::record("downgrade", vec![Change::new(package, before, after, if done { "restored" } else { "failed" })]);
+    if !done {
+        std::process::exit(1);
+    }
+}
+
+// newest first
+pub fn cached_packages(package: &str) -> Vec<PathBuf> {
+    if !utils::command_exists("pacman") {
+        return vec![];
+    }
+    let mut cached: Vec<(std::time::SystemTime, PathBuf)> = fs::read_dir(paths::package_cache_dir(package))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().contains(".pkg.tar"))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+    cached.sort();
+    cached.into_iter().rev().map(|(_, path)| path).collect()
+}
+
+pub fn install_package(file: &Path) -> bool {
+    let installed = distro::privileged_pacman().arg("-U").arg(file).run().is_ok_and(|s| s.success());
+    if installed {
+        println!("{} {}", Green.paint("~> Installed"), file.display());
+    } else {
+        eprintln!("{}", Red.paint("pacman -U failed"));
+    }
+    installed
+}
+
+// puts the saved files back; with `pin`, also pins the package at the commit they were built from
+pub fn restore(package: &str, dir: &Path, saved: &InstallRecord, pin: bool) -> bool {
+    let mut state = State::load();
+    if let Some(current) = state.packages.get(package) {
+        state::save_version(package, current);
+    }
+
+    for (index, file) in saved.files.iter().enumerate() {
+        let copy = dir.join("files").join(index.to_string());
+        if !copy.is_file() {
+            continue;
+        }
+        if !restore_file(&copy, file) {
+            eprintln!("{} Could not restore {}", Red.paint("Error:"), file.display());
+            return false;
+        }
+    }
+
+    let current = state.packages.get(package);
+    let mut record = saved.clone();
+    record.installed_at = utils::unix_now();
+    if pin {
+        record.pinned = saved.commit.clone();
+    }
+    record.explicit = current.is_none_or(|r| r.explicit);
+    record.required_by = current.map(|r| r.required_by.clone()).unwrap_or_default();
+    state.packages.insert(package.to_string(), record);
+    state.save();
+
+    println!("{} {}", Green.paint("~> Restored"), package);
+    if !pin {
+        return true;
+    }
+    match &saved.commit {
+        Some(commit) => println!(
+            "~> Pinned at {} so installs keep this version; `aurora unpin {}` follows the latest commit again",
+            utils::short_commit(commit),
+            package
+        ),
+        None => println!("{} No commit was recorded for this version, so it could not be pinned", Yellow.paint("~> Warning:")),
+    }
+    true
+}
+
+fn restore_file(copy: &Path, path: &Path) -> bool {
+    let copied = path.parent().is_none_or(|parent| fs::create_dir_all(parent).is_ok()) && fs::copy(copy, path).is_ok();
+    if copied || utils::is_root() {
+        return copied;
+    }
+    // installed with --system
+    Command::new(utils::get_privilege_command())
+        .arg("cp")
+        .arg(copy)
+        .arg(path)
+        .run()
+        .is_ok_and(|s| s.success())
+}
@@ -0,0 +1,62 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+use ansi_term::Colour::{Green, Red, Yellow};
+use crate::commands::install;
+use crate::paths;
+use crate::runner::Run;
+use crate::utils;
+
+// clones into the build cache so a later `install --offline` can build without a network
+pub fn fetch(packages: &[String]) {
+    let builds = paths::builds_dir();
+    let mut failed = false;
+
+    for package in packages {
+        let dir = builds.join(package);
+        let fetched = if dir.join(".git").is_dir() {
+            println!("\x1b[1m~> Updating {}\x1b[0m", package);
+            fast_forward(&dir)
+        } else {
+            println!("\x1b[1m~> Cloning repository: {}\x1b[0m", package);
+            if dir.exists() {
+                std::fs::remove_dir_all(&dir).expect("Failed to clean previous build");
+            }
+            utils::git_clone(&install::aur_url(package), &dir)
+        };
+
+        if fetched {
+            println!("{} {}", Green.paint("~> Fetched into"), dir.display());
+        } else {
+            eprintln!("{} Could not fetch {}", Red.paint("Error:"), package);
+            failed = true;
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+    println!("~> Build with: aurora install --offline {}", packages.join(" "));
+}
+
+// a clone left behind by an earlier build may have local changes, which are thrown away
+fn fast_forward(dir: &Path) -> bool {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .stdout(Stdio::null())
+            .run_always()
+            .map(|s| s.success())
+            .unwrap_or(false)
+    };
+
+    if !git(&["fetch", "--depth=1", "-q", "origin"]) {
+        return false;
+    }
+    if !git(&["reset", "--hard", "-q", "FETCH_HEAD"]) || !git(&["clean", "-fdxq"]) {
+        println!("{} Could not reset {}", Yellow.paint("~> Warning:"), dir.display());
+        return false;
+    }
+    true
+}
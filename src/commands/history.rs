@@ -0,0 +1,39 @@
+use ansi_term::Colour::{Green, Red, Yellow};
+use crate::journal::{self, Entry};
+use crate::utils;
+
+// newest first; with --package only the entries that touched it, and only its line in them
+pub fn history(limit: Option<usize>, package: Option<&str>, json: bool) {
+    let mut entries: Vec<Entry> = journal::read().into_iter().rev().collect();
+    if let Some(package) = package {
+        entries.retain_mut(|entry| {
+            entry.packages.retain(|change| change.name == package);
+            !entry.packages.is_empty()
+        });
+    }
+    entries.truncate(limit.unwrap_or(entries.len()));
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entries).expect("Failed to serialize history"));
+        return;
+    }
+    if entries.is_empty() {
+        println!("~> Nothing in the history yet");
+        return;
+    }
+    for entry in &entries {
+        println!("{}  {}  {}", utils::format_timestamp(entry.at), Yellow.paint(&entry.action), entry.command.join(" "));
+        for change in &entry.packages {
+            let versions = match (&change.before, &change.after) {
+                (Some(before), Some(after)) if before != after => format!("{} -> {}", before, after),
+                (Some(version), _) | (None, Some(version)) => version.clone(),
+                (None, None) => "-".to_string(),
+            };
+            let outcome = match change.outcome.as_str() {
+                "failed" => Red.paint(&change.outcome),
+                _ => Green.paint(&change.outcome),
+            };
+            println!("   {} {} {}", change.name, versions, outcome);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::env;
+use std::fs;
+use ansi_term::Colour::{Green, Red};
+use serde::Serialize;
+use crate::build_system::{self, BuildSystem};
+use crate::cli::InitArgs;
+use crate::config::Config;
+use crate::manifest::{self, AuroraManifest};
+use crate::utils;
+
+// field order here is the order they are written in
+#[derive(Serialize)]
+struct NewManifest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    build_system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    binary: Option<String>,
+    flags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    install_cmd: Option<String>,
+    aurora: Schema,
+}
+
+#[derive(Serialize)]
+struct Schema {
+    schema: u32,
+}
+
+pub fn init(args: &InitArgs) {
+    let dir = env::current_dir().expect("Failed to read current directory");
+
+    let existing = manifest::find(&dir);
+    if let Some(existing) = &existing {
+        if !args.force {
+            eprintln!(
+                "{} {} already exists, use --force to replace it",
+                Red.paint("Error:"),
+                existing.file_name().unwrap().to_string_lossy()
+            );
+            std::process::exit(1);
+        }
+    }
+
+    let config = Config::load();
+    let detected = build_system::detect(&dir, &config.build_priority());
+    let default_binary = dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut manifest = NewManifest {
+        build_system: detected.map(|b| b.name().to_string()),
+        binary: None,
+        flags: vec![],
+        install_cmd: None,
+        aurora: Schema { schema: manifest::SCHEMA_VERSION },
+    };
+
+    if !args.non_interactive {
+        let current = detected.map(|b| b.name()).unwrap_or("none");
+        let answer = utils::prompt(&format!("~> Build system [{}]:", current));
+        if !answer.is_empty() {
+            if BuildSystem::from_name(&answer).is_none() {
+                eprintln!("{} Unknown build system '{}'", Red.paint("Error:"), answer);
+                std::process::exit(1);
+            }
+            manifest.build_system = Some(answer);
+        }
+
+        let answer = utils::prompt(&format!("~> Binary name [{}]:", default_binary));
+        if !answer.is_empty() && answer != default_binary {
+            manifest.binary = Some(answer);
+        }
+
+        let answer = utils::prompt("~> Extra build flags (space separated) []:");
+        manifest.flags = answer.split_whitespace().map(|f| f.to_string()).collect();
+
+        let answer = utils::prompt("~> Install command, empty to let aurora copy the binary []:");
+        if !answer.is_empty() {
+            manifest.install_cmd = Some(answer);
+        }
+    }
+
+    let (file_name, content) = if args.format == "toml" {
+        ("aurora.toml", toml::to_string(&manifest).expect("Failed to serialize manifest"))
+    } else {
+        let json = serde_json::to_string_pretty(&manifest).expect("Failed to serialize manifest");
+        ("aurora.json", format!("{}\n", json))
+    };
+
+    // whatever we write has to load again
+    let parsed = if args.format == "toml" {
+        AuroraManifest::from_toml(&content)
+    } else {
+        AuroraManifest::from_json(&content)
+    };
+    if let Err(e) = parsed {
+        eprintln!("{} Generated manifest does not validate: {}", Red.paint("Error:"), e);
+        std::process::exit(1);
+    }
+
+    let path = dir.join(file_name);
+    fs::write(&path, content).expect("Failed to write manifest");
+    // a replaced manifest in another format would otherwise shadow or be shadowed by the new one
+    if let Some(existing) = existing.filter(|e| *e != path) {
+        fs::remove_file(&existing).expect("Failed to remove old manifest");
+    }
+    println!("{} Wrote {}", Green.paint("~>"), path.display());
+}
@@ -1,432 +1,3299 @@
+use std::collections::BTreeMap;
 use std::fs;
 use std::env;
+use std::io::{self, IsTerminal};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use std::io::{self, Write};
-use std::time::Instant;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use ansi_term::Colour::{Green, Red, Yellow};
-use toml::{Value, Table};
+use toml::Table;
+use crate::aur;
+use crate::build_log;
+use crate::cli::InstallArgs;
+use crate::commands::comments;
+use crate::commands::diff;
+use crate::build_system::{self, BuildSystem, CMakePreset};
+use crate::config::Config;
+use tracing::{debug, info};
+use crate::distro;
+use crate::hints;
+use crate::journal::{self, Change};
+use crate::log;
+use crate::manifest::{self, AuroraManifest};
+use crate::notify;
+use crate::paths;
+use crate::pkgbuild;
+use crate::registry;
+use crate::runner::{self, Run};
+use crate::sandbox;
+use crate::sha256;
+use crate::sources;
+use crate::state::{self, Conflict, InstallRecord, State};
+use crate::stats;
+use crate::sudo;
+use crate::trust;
+use crate::units;
 use crate::utils;
 
-pub fn install(packages: &[String], flags: &[String], yes: bool) {
-    for package in packages {
-        install_single(package, flags, yes);
+pub fn install(args: &InstallArgs) {
+    let use_makepkg = cfg!(target_os = "linux") && Path::new("/etc/arch-release").exists();
+
+    if cfg!(windows) && args.system {
+        eprintln!("{} --system is not supported on Windows", Red.paint("Error:"));
+        std::process::exit(1);
+    }
+
+    let packages = match package_flags(args) {
+        Ok(packages) => packages,
+        Err(e) => {
+            eprintln!("{} {}", Red.paint("Error:"), e);
+            std::process::exit(1);
+        }
+    };
+
+    let config = Config::load();
+    utils::set_prompt_timeout(args.prompt_timeout.or(config.prompt_timeout));
+    if args.clean_env || config.clean_env {
+        runner::set_clean_env(config.clean_env_passthrough);
+    }
+    let nice = args.nice.or(config.nice);
+    if nice.is_some() || args.ionice || config.ionice {
+        runner::set_priority(runner::Priority { nice, idle_io: args.ionice || config.ionice });
+    }
+    let max_load = args.max_load.or(config.max_load).filter(|_| !args.dry_run);
+    if let Some(max_load) = max_load {
+        debug!("each build waits while the load average is over {}", max_load);
+    }
+    if args.sandboxed() && !args.dry_run {
+        if let Some(reason) = sandbox::unavailable() {
+            eprintln!("{} {}", Red.paint("Error:"), reason);
+            std::process::exit(1);
+        }
+    }
+    if utils::is_root() {
+        check_root(args, use_makepkg);
+    }
+
+    if use_makepkg && !check_sync(args) {
+        std::process::exit(1);
+    } else if !use_makepkg && args.sysupgrade {
+        println!("{} --sysupgrade only applies on arch", Yellow.paint("~> Warning:"));
+    }
+
+    // for the history; dry runs and builds change nothing
+    let before = (!args.dry_run && !args.build_only).then(journal::versions);
+    let mut results = Vec::new();
+    let mut built = Vec::new();
+    for (index, (package, flags)) in packages.iter().enumerate() {
+        let batch = Batch { index: index + 1, total: packages.len() };
+        if let Some(max_load) = max_load {
+            wait_for_load(max_load, package);
+        }
+        let start = Instant::now();
+        let waiting = built.len();
+        // every event of the package's run carries which one of the batch it is
+        let span = tracing::info_span!("package", package = package.as_str(), index = batch.index, total = batch.total);
+        let success = span.in_scope(|| install_single(package, flags, args, use_makepkg, batch, &mut built));
+        // packages waiting for the batch's pacman -U are notified once it has run
+        if !args.dry_run && built.len() == waiting {
+            notify::finished(package, success, start.elapsed());
+        }
+        results.push((batch, package, success));
+    }
+
+    if !built.is_empty() {
+        let installed = install_built(&built, args);
+        for waiting in &built {
+            if !args.dry_run {
+                notify::finished(&waiting.package, installed, waiting.start.elapsed());
+            }
+            results[waiting.batch.index - 1].2 &= installed;
+        }
+    }
+
+    sudo::stop();
+
+    for (batch, package, success) in &results {
+        info!(package, index = batch.index, total = batch.total, success, "finished");
+    }
+    if results.len() > 1 && !args.json {
+        println!("~> Summary:");
+        for (batch, package, success) in &results {
+            let outcome = match success {
+                true if args.dry_run => Green.paint("planned"),
+                true if args.build_only => Green.paint("built"),
+                true => Green.paint("installed"),
+                false => Red.paint("failed"),
+            };
+            println!("   {}{} {}", batch.prefix(), package, outcome);
+        }
+    }
+    if let Some(before) = before {
+        let after = journal::versions();
+        let changes = results
+            .iter()
+            .map(|(_, package, success)| {
+                let (before, after) = (before.get(*package).cloned(), after.get(*package).cloned());
+                let outcome = match (success, &before) {
+                    (false, _) => "failed",
+                    (true, None) => "installed",
+                    (true, Some(_)) if before == after => "reinstalled",
+                    (true, Some(_)) => "updated",
+                };
+                Change::new(package, before, after, outcome)
+            })
+            .collect();
+        journal::record("install", changes);
+    }
+
+    // an answer nobody gave shouldn't read like one that was given
+    let timed_out = utils::timed_out_prompts();
+    if !timed_out.is_empty() && !args.json {
+        println!("{} Nobody answered these, so they took their default:", Yellow.paint("~>"));
+        for question in timed_out {
+            println!("   {}", question);
+        }
+    }
+}
+
+// holds off starting the next build while the machine is busy with something else
+fn wait_for_load(max_load: f64, package: &str) {
+    let mut announced = false;
+    while let Some(load) = utils::load_average().filter(|load| *load > max_load) {
+        if !announced {
+            println!("~> The load average is {:.2}, over max_load {}; waiting before building {}", load, max_load, package);
+            announced = true;
+        }
+        thread::sleep(LOAD_CHECK_INTERVAL);
+    }
+}
+
+// where a package sits in a multi-package install
+#[derive(Clone, Copy)]
+struct Batch {
+    index: usize,
+    total: usize,
+}
+
+impl Batch {
+    // "[2/5] ", or nothing for a single package
+    fn prefix(self) -> String {
+        if self.total > 1 {
+            format!("[{}/{}] ", self.index, self.total)
+        } else {
+            String::new()
+        }
+    }
+}
+
+// splits `foo=-DFOO=1,-GNinja` and `--pkg-flag foo:-DFOO=1` into flags for each package; they
+// come after the global --flags so they can override them
+fn package_flags(args: &InstallArgs) -> Result<Vec<(String, Vec<String>)>, String> {
+    let mut packages: Vec<(String, Vec<String>)> = args
+        .packages
+        .iter()
+        .map(|spec| match spec.split_once('=') {
+            Some((name, flags)) => (
+                name.to_string(),
+                args.flags.iter().cloned().chain(flags.split(',').filter(|f| !f.is_empty()).map(|f| f.to_string())).collect(),
+            ),
+            None => (spec.clone(), args.flags.clone()),
+        })
+        .collect();
+
+    for pkg_flag in &args.pkg_flags {
+        let (name, flag) = pkg_flag
+            .split_once(':')
+            .ok_or_else(|| format!("--pkg-flag expects <package>:<flag>, got '{}'", pkg_flag))?;
+        match packages.iter_mut().find(|(package, _)| package == name) {
+            Some((_, flags)) => flags.push(flag.to_string()),
+            None => return Err(format!("--pkg-flag refers to '{}', which is not being installed", name)),
+        }
+    }
+    Ok(packages)
+}
+
+fn check_root(args: &InstallArgs, use_makepkg: bool) {
+    if let Ok(user) = env::var("SUDO_USER") {
+        if user != "root" {
+            println!(
+                "{} aurora was started through sudo; building as root leaves root-owned files in the build cache",
+                Yellow.paint("~> Warning:")
+            );
+
+            let input = if args.answers_yes() {
+                String::new()
+            } else {
+                utils::prompt(&format!("~> Build as '{}' and only elevate for the install step? [Y/n]", user))
+            };
+
+            if !input.eq_ignore_ascii_case("n") {
+                let status = Command::new("sudo")
+                    .arg("-u")
+                    .arg(&user)
+                    .arg("--")
+                    .arg(env::current_exe().expect("Failed to locate aurora executable"))
+                    .args(reexec_args(use_makepkg))
+                    .run_always()
+                    .expect("Failed to drop privileges");
+                std::process::exit(status.code().unwrap_or(1));
+            }
+        }
+    }
+
+    if use_makepkg {
+        eprintln!("{} makepkg cannot be run as root. Run aurora as a regular user.", Red.paint("Error:"));
+        std::process::exit(1);
+    }
+
+    if !args.allow_root {
+        eprintln!(
+            "{} Refusing to build as root. Run aurora as a regular user (with --system to install into /usr/local/bin), or pass --allow-root.",
+            Red.paint("Error:")
+        );
+        std::process::exit(1);
+    }
+
+    println!("{} Building as root because --allow-root was given", Yellow.paint("~> Warning:"));
+}
+
+// how old the sync databases may get before a build warns about them
+const STALE_SYNC: Duration = Duration::from_secs(24 * 60 * 60);
+
+// AUR packages link against whatever the system has right now, and makedepends pulled in without
+// a full sync are a partial upgrade
+fn check_sync(args: &InstallArgs) -> bool {
+    if args.sysupgrade {
+        println!("~> Upgrading the system before building");
+        let mut pacman = distro::privileged_pacman();
+        pacman.arg("-Syu");
+        if args.answers_yes() {
+            pacman.arg("--noconfirm");
+        }
+        if !pacman.run().is_ok_and(|s| s.success()) {
+            eprintln!("{} pacman -Syu failed, so nothing was built", Red.paint("Error:"));
+            return false;
+        }
+        sudo::keep_alive();
+        return true;
+    }
+    if args.offline || Config::load().ignore_partial_upgrade {
+        return true;
+    }
+
+    let pending = distro::pending_updates();
+    if !pending.is_empty() {
+        let noun = if pending.len() == 1 { "update is" } else { "updates are" };
+        println!(
+            "{} {} repository {} pending; packages built now may break after the next pacman -Syu",
+            Yellow.paint("~> Warning:"),
+            pending.len(),
+            noun
+        );
+    } else if let Some(age) = distro::sync_age().filter(|age| *age > STALE_SYNC) {
+        println!(
+            "{} The sync databases were last refreshed {} ago; packages built now may link against outdated libraries",
+            Yellow.paint("~> Warning:"),
+            utils::format_duration(age)
+        );
+    } else {
+        return true;
+    }
+    println!("~> Pass --sysupgrade to run pacman -Syu first, or set ignore_partial_upgrade = true to silence this");
+    true
+}
+
+fn reexec_args(use_makepkg: bool) -> Vec<String> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    args.retain(|a| a != "--allow-root");
+    if !use_makepkg && !args.iter().any(|a| a == "--system") {
+        args.push("--system".to_string());
+    }
+    args
+}
+
+fn install_single(package: &str, flags: &[String], args: &InstallArgs, use_makepkg: bool, batch: Batch, built: &mut Vec<Built>) -> bool {
+    let success = if use_makepkg {
+        install_with_makepkg(package, flags, args, batch, built)
+    } else {
+        install_with_build_system(package, flags, args, batch)
+    };
+    // a build that failed inside its sandbox returns without leaving it
+    sandbox::leave();
+    success
+}
+
+const LOAD_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+// the last few packages makepkg built are copied aside for `aurora downgrade`
+const KEPT_PACKAGES: usize = 3;
+
+fn cache_packages(package: &str, built: &[PathBuf]) {
+    let cache = paths::package_cache_dir(package);
+    if fs::create_dir_all(&cache).is_err() {
+        return;
+    }
+    let mut copied = 0;
+    for file in built.iter().flat_map(|f| [f.clone(), signature(f)]).filter(|f| f.is_file()) {
+        if let Some(name) = file.file_name() {
+            copied += fs::copy(&file, cache.join(name)).is_ok() as usize;
+        }
+    }
+
+    let mut cached: Vec<(SystemTime, PathBuf)> = fs::read_dir(&cache)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+    cached.sort();
+    let excess = cached.len().saturating_sub(KEPT_PACKAGES * copied.max(1));
+    for (_, old) in cached.into_iter().take(excess) {
+        let _ = fs::remove_file(old);
+    }
+}
+
+fn signature(package: &Path) -> PathBuf {
+    let mut name = package.as_os_str().to_os_string();
+    name.push(".sig");
+    PathBuf::from(name)
+}
+
+// with sign_packages, the key has to be usable before hours go into a build; signing something
+// small also gets gpg-agent to ask for the passphrase now rather than at the end
+fn check_signing_key(key: Option<&str>) -> bool {
+    if !utils::command_exists("gpg") {
+        eprintln!("{} sign_packages is set, but gpg is not installed", Red.paint("Error:"));
+        return false;
+    }
+    let listed = Command::new("gpg")
+        .arg("--list-secret-keys")
+        .args(key)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .run_always()
+        .is_ok_and(|s| s.success());
+    if !listed {
+        match key {
+            Some(key) => eprintln!("{} gpg has no secret key for {}", Red.paint("Error:"), key),
+            None => eprintln!("{} gpg has no secret key to sign packages with", Red.paint("Error:")),
+        }
+        return false;
+    }
+
+    let probe = paths::temp_dir().join("sign-check");
+    let probe_signature = signature(&probe);
+    let _ = fs::create_dir_all(paths::temp_dir());
+    let mut gpg = Command::new("gpg");
+    gpg.args(["--yes", "--detach-sign", "--output"]).arg(&probe_signature);
+    if let Some(key) = key {
+        gpg.arg("--local-user").arg(key);
+    }
+    let signed = fs::write(&probe, "aurora").is_ok()
+        && gpg.arg(&probe).stdout(Stdio::null()).run_always().is_ok_and(|s| s.success());
+    let _ = fs::remove_file(&probe);
+    let _ = fs::remove_file(&probe_signature);
+    if !signed {
+        eprintln!("{} gpg could not sign with {}; check that gpg-agent is running", Red.paint("Error:"), key.unwrap_or("your default key"));
+    }
+    signed
+}
+
+// the parts of .SRCINFO worth a look before the PKGBUILD itself; returns how many of them are
+// warnings
+fn print_summary(package: &str, info: &pkgbuild::SrcInfo) -> usize {
+    let mut warnings = 0;
+    println!("~> {} {}", Green.bold().paint(package), info.full_version());
+    if let Some(url) = &info.url {
+        println!("   upstream: {}", url);
+    }
+
+    let hosts = sources::from_srcinfo(info);
+    let shipped = info.sources.len() - hosts.values().map(|host| host.count).sum::<usize>();
+    if hosts.is_empty() {
+        println!("   sources: {}", if shipped > 0 { "only files in the AUR repository" } else { "none" });
+    } else {
+        if shipped > 0 {
+            println!("   sources: {} in the AUR repository, downloads from", shipped);
+        } else {
+            println!("   sources: downloads from");
+        }
+        sources::print(&hosts);
+        warnings += hosts.values().filter(|host| host.suspicious()).count();
+    }
+
+    let mut kinds = Vec::new();
+    for (kind, sums) in &info.checksums {
+        let skipped = sums.iter().filter(|s| *s == "SKIP").count();
+        let kind = kind.trim_end_matches("sums");
+        if skipped > 0 {
+            warnings += 1;
+            kinds.push(format!("{} {}", kind, Yellow.paint(format!("({} SKIP)", skipped))));
+        } else {
+            kinds.push(kind.to_string());
+        }
+    }
+    if kinds.is_empty() && !info.sources.is_empty() {
+        warnings += 1;
+        kinds.push(Yellow.paint("none").to_string());
+    }
+    if !kinds.is_empty() {
+        println!("   checksums: {}", kinds.join(", "));
+    }
+
+    println!("   depends: {}, makedepends: {}", info.depends.len(), info.make_depends.len());
+    if let Some(install) = &info.install {
+        // install scriptlets run as root when pacman installs the package
+        warnings += 1;
+        println!("   {} {}", Yellow.paint("install script:"), install);
+    }
+    warnings
+}
+
+// None when the build is cancelled, otherwise whether the package is now always trusted; an
+// empty answer (or no terminal) proceeds, but only an answer typed at a terminal counts as a review
+fn confirm_review(package: &str, base: &str, fingerprint: &str) -> Option<bool> {
+    let input = utils::prompt_untimed("~> Proceed with build? [Y/n/a = always for this package]");
+    if input.eq_ignore_ascii_case("n") {
+        println!("{}", Yellow.paint("Build cancelled by user"));
+        return None;
+    }
+    if !io::stdin().is_terminal() {
+        return Some(false);
+    }
+    trust::remember(base, fingerprint);
+    if !input.eq_ignore_ascii_case("a") {
+        return Some(false);
+    }
+    trust::set_always(package, true);
+    println!(
+        "~> {} will build without review while its build files stay the same; `aurora config trusted --remove {}` undoes this",
+        package, package
+    );
+    Some(true)
+}
+
+// .install files run as root when pacman installs the package, so they are reviewed with it
+fn install_scripts(build_dir: &Path) -> Vec<PathBuf> {
+    let mut scripts: Vec<PathBuf> = fs::read_dir(build_dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    scripts.retain(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "install"));
+    scripts.sort();
+    scripts
+}
+
+// refuses hosts outside allowed_domains; run before the review so --yes can't skip it
+fn allowed(package: &str, hosts: &BTreeMap<String, sources::Host>, config: &Config, args: &InstallArgs) -> bool {
+    let unknown = sources::unknown(hosts, &config.allowed_domains, &args.allow_domain);
+    if unknown.is_empty() {
+        return true;
+    }
+    eprintln!(
+        "{} {} fetches from {}, which is not in allowed_domains; pass --allow-domain {} to build anyway",
+        Red.paint("Error:"),
+        package,
+        unknown.join(", "),
+        unknown.join(" --allow-domain ")
+    );
+    false
+}
+
+// left in the build directory once the sources are downloaded and extracted, so a failed
+// build can be retried without downloading them again
+const SOURCES_MARKER: &str = ".aurora-sources";
+
+// packages makepkg built, waiting for the single pacman -U that installs the whole batch
+struct Built {
+    package: String,
+    files: Vec<PathBuf>,
+    batch: Batch,
+    start: Instant,
+}
+
+fn install_with_makepkg(package: &str, flags: &[String], args: &InstallArgs, batch: Batch, built: &mut Vec<Built>) -> bool {
+    let start = Instant::now();
+    let config = Config::load();
+    let sign = config.signs_packages();
+    if sign && !args.dry_run && !check_signing_key(config.gpg_key.as_deref()) {
+        return false;
+    }
+    let builds = paths::builds_dir();
+    let clone_url = aur_url(package);
+
+    let cached = builds.join(package);
+    let resume = !args.dry_run && !args.offline && cached.join(SOURCES_MARKER).is_file();
+    let build_dir = if resume || args.from_build {
+        if resume {
+            println!("~> {}Resuming {}: sources were downloaded by an earlier run", batch.prefix(), package);
+            info!("resuming");
+        }
+        cached
+    } else {
+        println!("\x1b[1m~> {}Cloning AUR repository: {}\x1b[0m", batch.prefix(), package);
+        info!(url = clone_url.as_str(), "cloning");
+        match clone_package(&clone_url, &cached, args) {
+            Some(dir) => dir,
+            None => return false,
+        }
+    };
+
+    if !build_dir.join("PKGBUILD").is_file() {
+        eprintln!("{} The AUR repository for {} has no PKGBUILD", Red.paint("Error:"), package);
+        return false;
+    }
+
+    if args.build_only {
+        println!("\x1b[1m~> Building {} from AUR\x1b[0m", package);
+    } else {
+        println!("\x1b[1m~> Building and installing {} from AUR\x1b[0m", package);
+    }
+
+    let arches = pkgbuild::arches(&build_dir);
+    let host = utils::host_arch();
+    if !arches.is_empty() && !arches.iter().any(|a| a == "any" || *a == host) {
+        let message = format!("{} is built for {}, but this machine is {}", package, arches.join(", "), host);
+        if args.ignorearch {
+            println!("{} {}; building anyway because of --ignorearch", Yellow.paint("~> Warning:"), message);
+        } else {
+            eprintln!("{} {}. Pass --ignorearch to try anyway.", Red.paint("Error:"), message);
+            return false;
+        }
+    }
+
+    let hosts = pkgbuild::srcinfo(&build_dir).map(|info| sources::from_srcinfo(&info)).unwrap_or_default();
+    if !allowed(package, &hosts, &config, args) {
+        return false;
+    }
+
+    if let Some(count) = args.comments {
+        comments::show(package, count);
+    }
+
+    let mut unattended = false;
+    if !args.skips_review() && !args.dry_run && !args.from_build {
+        let pkgbuild_path = build_dir.join("PKGBUILD");
+        let mut reviewed = vec![pkgbuild_path.clone()];
+        reviewed.extend(install_scripts(&build_dir));
+        let fingerprint = trust::fingerprint(&reviewed.iter().map(|p| p.as_path()).collect::<Vec<_>>(), &[]);
+        let base = pkgbuild::package_base(&build_dir, package);
+        match trust::reviewed_at(&base, &fingerprint).filter(|_| !args.review_always) {
+            Some(at) => {
+                println!("~> PKGBUILD unchanged since last review on {}", utils::format_timestamp(at));
+                unattended = trust::always(package);
+            }
+            None => {
+                let warnings = pkgbuild::srcinfo(&build_dir).map(|info| print_summary(package, &info));
+                // an upgrade shows what changed since the installed build instead of all of it
+                let upgrade = distro::foreign().contains_key(package);
+                if args.summary_only && warnings == Some(0) {
+                    println!("~> Nothing stands out, skipping the PKGBUILD because of --summary-only");
+                } else {
+                    if !upgrade || !diff::show_pkgbuild_changes(package, &pkgbuild_path) {
+                        println!("~> PKGBUILD contents:");
+                        utils::page_file(&pkgbuild_path);
+                    }
+                    for script in &reviewed[1..] {
+                        println!("~> Install script: {}", script.file_name().unwrap().to_string_lossy());
+                        utils::page_file(script);
+                    }
+                }
+
+                match confirm_review(package, &base, &fingerprint) {
+                    Some(always) => unattended = always,
+                    None => return false,
+                }
+            }
+        }
+    }
+    let unattended_args;
+    let args = match unattended {
+        true => {
+            println!("~> {} is always trusted, building as with --no-confirm", package);
+            unattended_args = InstallArgs { no_confirm: true, ..args.clone() };
+            &unattended_args
+        }
+        false => args,
+    };
+
+    // makepkg puts both in the package's .PKGINFO and .BUILDINFO
+    let mut makepkg_env = BTreeMap::new();
+    if let Some(packager) = &config.packager {
+        makepkg_env.insert("PACKAGER".to_string(), packager.clone());
+    }
+    if let Some(time) = utils::git_commit_time(&build_dir).filter(|_| config.reproducible) {
+        makepkg_env.insert("SOURCE_DATE_EPOCH".to_string(), time.to_string());
+    }
+    if !makepkg_env.is_empty() {
+        let vars: Vec<String> = makepkg_env.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        println!("~> Build environment: {}", vars.join(" "));
+    }
+
+    // makepkg runs check() unless makepkg.conf turns it off; --check runs it regardless
+    let with_tests = args.with_tests || config.with_tests.iter().any(|p| p == package);
+    let makepkg = |phase: &[&str]| {
+        let mut makepkg = Command::new("makepkg");
+        makepkg.args(phase).envs(&makepkg_env);
+        if args.answers_yes() {
+            makepkg.arg("--noconfirm");
+        }
+        if args.ignorearch {
+            makepkg.arg("--ignorearch");
+        }
+        // everything but fetching the sources builds
+        let building = !phase.iter().any(|p| *p == "-o" || *p == "--verifysource");
+        if with_tests && building {
+            makepkg.arg("--check");
+        }
+        if sign && building {
+            makepkg.arg("--sign");
+            if let Some(key) = &config.gpg_key {
+                makepkg.arg("--key").arg(key);
+            }
+        }
+        makepkg.args(flags).current_dir(&build_dir);
+        let mut makepkg = match building {
+            true => sandbox::wrap(&makepkg).unwrap_or(makepkg),
+            false => makepkg,
+        };
+        runner::sanitize(&mut makepkg);
+        runner::deprioritize(&mut makepkg);
+        makepkg.run().expect("makepkg command failed")
+    };
+
+    // makepkg -i would ask for a password whenever the build happens to finish, so the install is
+    // a step of its own and sudo asks right away
+    if !args.build_only {
+        sudo::authenticate();
+    }
+
+    // in a sandbox, prepare() runs with the build; only the download and dependencies happen
+    // outside of it, so there is nothing extracted to resume from
+    let sandboxed = args.sandboxed();
+    let mut phase: &[&str] = &["-e", "-s"];
+    if !resume && !args.from_build {
+        println!("~> Downloading and verifying sources");
+        let fetch: &[&str] = if sandboxed { &["-s", "--verifysource"] } else { &["-s", "-o"] };
+        if !makepkg(fetch).success() {
+            eprintln!("{}", Red.paint("Downloading sources failed"));
+            return false;
+        }
+        if sandboxed {
+            phase = &["-s"];
+        } else if !args.dry_run {
+            let _ = fs::write(build_dir.join(SOURCES_MARKER), "");
+        }
+    }
+
+    if args.shell && !enter_shell(package, &build_dir, &BTreeMap::new(), &format!("makepkg -e -s {}", flags.join(" "))) {
+        return false;
+    }
+    println!("~> {}Building {}{}", batch.prefix(), package, stats::banner_note(stats::estimate(package, "makepkg")));
+    info!(build_system = "makepkg", "building");
+    let build_start = Instant::now();
+    if sandboxed {
+        sandbox::enter(sandbox::Options::new(&build_dir, &config.sandbox_writable, args.sandbox_no_net));
+    }
+    while !makepkg(phase).success() {
+        if !retry_failed(package, "Build failed", &build_dir, &BTreeMap::new(), args) {
+            if !sandboxed {
+                println!("~> Running aurora again resumes the build without downloading the sources");
+            }
+            return false;
+        }
+        phase = &["-e", "-s"];
+    }
+    sandbox::leave();
+
+    // every split package, wherever PKGDEST puts them
+    let files: Vec<PathBuf> = match Command::new("makepkg").arg("--packagelist").current_dir(&build_dir).output_always() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(PathBuf::from)
+            .filter(|path| args.dry_run || path.is_file())
+            .collect(),
+        Err(_) => vec![],
+    };
+    if !args.dry_run {
+        let _ = fs::remove_file(build_dir.join(SOURCES_MARKER));
+        stats::record(package, "makepkg", build_start.elapsed());
+        if let Some(unsigned) = files.iter().find(|p| sign && !signature(p).is_file()) {
+            eprintln!("{} makepkg did not sign {}", Red.paint("Error:"), unsigned.display());
+            return false;
+        }
+        cache_packages(package, &files);
+    }
+
+    if args.build_only {
+        if args.dry_run {
+            print_plan(args, package, batch, &clone_url, &build_dir, "makepkg", None);
+            return true;
+        }
+        println!("~> Packages:");
+        for package in &files {
+            println!("   {}", package.display());
+        }
+        println!("{} in {}s", Green.paint("~> BUILD FINISHED"), start.elapsed().as_secs());
+        return true;
+    }
+
+    if files.is_empty() && !args.dry_run {
+        eprintln!("{} makepkg built {} but aurora could not find the packages", Red.paint("Error:"), package);
+        return false;
+    }
+    if args.dry_run {
+        print_plan(args, package, batch, &clone_url, &build_dir, "makepkg", None);
+    } else if batch.index < batch.total {
+        println!("~> {}Built {}; it is installed together with the rest of the batch", batch.prefix(), package);
+        info!("built, waiting for the batch");
+    }
+    built.push(Built { package: package.to_string(), files, batch, start });
+    true
+}
+
+// one pacman -U for every package the batch built, so there's a single prompt and a single run
+// of the hooks, and packages that depend on each other go in together
+fn install_built(built: &[Built], args: &InstallArgs) -> bool {
+    let files: Vec<&PathBuf> = built.iter().flat_map(|b| &b.files).collect();
+    let names: Vec<String> = files.iter().filter_map(|p| p.file_name()).map(|n| n.to_string_lossy().into_owned()).collect();
+    println!("~> Installing {} with pacman -U", names.join(", "));
+    let mut pacman = distro::privileged_pacman();
+    pacman.arg("-U");
+    if args.answers_yes() {
+        pacman.arg("--noconfirm");
+    }
+    if !pacman.args(&files).run().is_ok_and(|s| s.success()) {
+        let packages: Vec<&str> = built.iter().map(|b| b.package.as_str()).collect();
+        eprintln!(
+            "{} pacman -U failed, so nothing from this batch was installed ({})",
+            Red.paint("Error:"),
+            packages.join(", ")
+        );
+        for waiting in built {
+            println!("~> The packages for {} are kept in {}", waiting.package, paths::package_cache_dir(&waiting.package).display());
+        }
+        return false;
+    }
+
+    // pacman -U marks everything explicit; dependencies are marked afterwards
+    if args.required_by.is_some() {
+        let packages: Vec<String> = files.iter().filter_map(|p| package_name(p)).collect();
+        let marked = distro::privileged_pacman().args(["-D", "--asdeps"]).args(&packages).run();
+        if !marked.is_ok_and(|s| s.success()) {
+            println!("{} Could not mark {} as dependencies", Yellow.paint("~> Warning:"), packages.join(", "));
+        }
+    }
+
+    if args.dry_run {
+        let commands = runner::take_plan();
+        if args.json {
+            let plan = serde_json::json!({
+                "packages": built.iter().map(|b| &b.package).collect::<Vec<_>>(),
+                "commands": commands,
+            });
+            println!("{}", serde_json::to_string_pretty(&plan).expect("Failed to serialize plan"));
+        } else {
+            println!("~> Then, for the whole batch:");
+            for command in &commands {
+                println!("   run: {}", command.shell_line());
+            }
+        }
+        return true;
+    }
+    for installed in built {
+        diff::keep_pkgbuild(&installed.package, &paths::builds_dir().join(&installed.package));
+    }
+    println!("{} in {}s", Green.paint("~> INSTALL FINISHED"), built[0].start.elapsed().as_secs());
+    true
+}
+
+// "foo-libs-1:2.0-1-x86_64.pkg.tar.zst" is foo-libs; version, release and arch never contain a dash
+fn package_name(file: &Path) -> Option<String> {
+    let name = file.file_name()?.to_string_lossy().into_owned();
+    let stem = name.split(".pkg.tar").next()?;
+    Some(stem.rsplitn(4, '-').nth(3)?.to_string())
+}
+
+fn install_with_build_system(package: &str, flags: &[String], args: &InstallArgs, batch: Batch) -> bool {
+    let start = Instant::now();
+    let builds = paths::builds_dir();
+
+    let config = Config::load();
+    let dest = if args.system {
+        PathBuf::from("/usr/local/bin")
+    } else if let Some(dest) = &args.dest {
+        dest.clone()
+    } else if let Some(prefix) = &args.prefix {
+        prefix.join("bin")
+    } else {
+        config.dest_dir()
+    };
+
+    let prefix = dest.parent().unwrap_or(&dest).to_path_buf();
+
+    let repo = package;
+    let clone_url = aur_url(package);
+
+    println!("\x1b[1m~> {}Cloning repository: {}\x1b[0m", batch.prefix(), package);
+    info!(url = clone_url.as_str(), "cloning");
+    let build_dir = match clone_package(&clone_url, &builds.join(repo), args) {
+        Some(dir) => dir,
+        None => return false,
+    };
+
+    let pinned = State::load().packages.get(package).and_then(|r| r.pinned.clone());
+    let commit = match &pinned {
+        Some(pinned) => match use_pinned_commit(package, pinned, &build_dir, args) {
+            Some(commit) => Some(commit),
+            None => return false,
+        },
+        None => utils::git_head(&build_dir),
+    };
+
+    debug!("build directory {}, commit {}", build_dir.display(), commit.as_deref().unwrap_or("unknown"));
+    println!("\x1b[1m~> Searching for build file\x1b[0m");
+    let mut manifest_path = manifest::find(&build_dir);
+    let mut recipe_sources = Vec::new();
+    if manifest_path.is_some() {
+        recipe_sources.push("repository");
+    } else if !args.no_registry {
+        if let Some(registry) = &config.registry {
+            match registry::fetch(registry, package) {
+                Ok(Some(recipe)) => {
+                    println!("~> Using recipe for {} from {}", package, registry);
+                    manifest_path = Some(recipe);
+                    recipe_sources.push("registry");
+                }
+                Ok(None) => {}
+                Err(e) => println!(
+                    "{} Recipe registry unavailable ({}), detecting the build system instead",
+                    Yellow.paint("~> Warning:"),
+                    e
+                ),
+            }
+        }
+    }
+
+    let local_recipe = manifest::local_recipe(package);
+    if let Some(path) = &local_recipe {
+        println!("~> Using local recipe {}", path.display());
+        recipe_sources.insert(0, "local");
+    }
+
+    let (manifest, local_fields) = match load_manifest(manifest_path.as_deref(), local_recipe.as_deref()) {
+        Some(loaded) => loaded,
+        None => return false,
+    };
+
+    let build_system = if let Some(build_system) = manifest.build_system {
+        build_system
+    } else {
+        match build_system::detect(&build_dir, &config.build_priority()) {
+            Some(build_system) => build_system,
+            None => {
+                if build_dir.join("package.json").exists() {
+                    println!(
+                        "{} package.json declares no \"bin\" entry; this looks like a library",
+                        Yellow.paint("~> Warning:")
+                    );
+                }
+                eprintln!("{}", Red.paint("No build system found"));
+                return false;
+            }
+        }
+    };
+
+    if args.build_only && build_system.installs_itself() {
+        eprintln!(
+            "{} {} projects install as part of their build; use aurora install",
+            Red.paint("Error:"),
+            build_system.display_name()
+        );
+        return false;
+    }
+
+    if !args.skip_preflight && !preflight(build_system, &build_dir, args) {
+        return false;
+    }
+
+    let submodules = sources::submodules(&build_dir);
+    if !allowed(package, &submodules, &config, args) {
+        return false;
+    }
+
+    let debug = args.profile == "debug";
+    let mut final_flags = manifest.flags.clone();
+    final_flags.extend(flags.iter().cloned());
+    debug!("flags from the manifest {:?}, from the command line {:?}", manifest.flags, flags);
+    debug!("installing into {}", dest.display());
+
+    println!("~> Build system: {}", Green.paint(build_system.display_name()));
+    println!("~> Profile: {}", args.profile);
+    if args.target_triple.is_some() && build_system != BuildSystem::Cargo {
+        println!("{} --target only applies to cargo projects, building for the host", Yellow.paint("~> Warning:"));
+    }
+    if let Some(arch) = flags_arch(&final_flags) {
+        let host = utils::host_arch();
+        if utils::normalize_arch(&arch) != utils::normalize_arch(&host) {
+            println!(
+                "{} The flags build for {}, but this machine is {}; the result may not run here",
+                Yellow.paint("~> Warning:"),
+                arch,
+                host
+            );
+        }
+    }
+    if !local_fields.is_empty() {
+        println!("~> From local recipe: {}", Yellow.paint(local_fields.join(", ")));
+    }
+    if !manifest.target.is_empty() {
+        println!("~> Target: {}", manifest.target.join(" "));
+    }
+    let staging = build_dir.join("aurora-staging");
+    let install_cmd = manifest
+        .install_cmd
+        .as_deref()
+        .map(|cmd| expand_install_cmd(cmd, &prefix, &staging, &build_dir));
+    if let Some(install_cmd) = &install_cmd {
+        println!("~> Install command: {}", install_cmd);
+    }
+
+    let build_file = build_system.build_file(&build_dir);
+
+    let cmake_preset = if build_system == BuildSystem::CMake {
+        let requested = args.preset.as_deref().or(manifest.preset.as_deref());
+        match choose_cmake_preset(&build_dir, requested, debug) {
+            Ok(preset) => preset,
+            Err(()) => return false,
+        }
+    } else {
+        None
+    };
+    if let Some(preset) = &cmake_preset {
+        println!("~> CMake preset: {}", preset.name);
+    }
+    let cmake_config = if debug { "Debug" } else { "Release" };
+    let cmake_build_dir = cmake_preset
+        .as_ref()
+        .and_then(|p| p.binary_dir.clone())
+        .unwrap_or_else(|| build_dir.join("build"));
+
+    let declared = build_system::declared_executables(build_system, &build_dir);
+    if !declared.is_empty() {
+        println!("~> Executables: {}", declared.join(", "));
+    }
+
+    // repo-provided commands are always listed, even when the review is skipped
+    let scripts: Vec<(&str, &String)> = manifest
+        .pre_build
+        .iter()
+        .map(|script| ("pre_build", script))
+        .chain(manifest.post_install.iter().map(|script| ("post_install", script)))
+        .collect();
+    if !scripts.is_empty() {
+        println!("{}", Yellow.bold().paint("~> The manifest runs these commands:"));
+        for (stage, script) in &scripts {
+            println!("   {} {}", Yellow.paint(format!("{}:", stage)), script);
+        }
+    }
+
+    if let Some(count) = args.comments {
+        comments::show(package, count);
+    }
+
+    // rebuilding an installed package starts with what changed upstream since its build
+    let installed = State::load().packages.get(package).and_then(|record| record.commit.clone());
+    if let Some(installed) = installed.filter(|_| commit.is_some() && !args.skips_review() && !args.dry_run && !args.from_build) {
+        diff::show_commits(&build_dir, &installed, "HEAD", !args.offline);
+    }
+
+    // everything the review shows; an unchanged set was accepted before and isn't shown again
+    let mut reviewed: Vec<&Path> = manifest_path.iter().chain(&local_recipe).map(|p| p.as_path()).collect();
+    reviewed.extend(build_file.iter().map(|p| p.as_path()));
+    let script_files: Vec<PathBuf> = scripts
+        .iter()
+        .filter_map(|(_, script)| script.split_whitespace().next())
+        .map(|program| build_dir.join(program))
+        .filter(|file| file.is_file())
+        .collect();
+    reviewed.extend(script_files.iter().map(|p| p.as_path()));
+    let mut listed: Vec<String> = scripts.iter().map(|(stage, script)| format!("{}: {}", stage, script)).collect();
+    listed.extend(submodules.keys().cloned());
+    let fingerprint = trust::fingerprint(&reviewed, &listed);
+    let base = pkgbuild::package_base(&build_dir, package);
+    let trusted = trust::reviewed_at(&base, &fingerprint).filter(|_| !args.review_always);
+    let mut unattended = false;
+    if let Some(at) = trusted.filter(|_| !args.skips_review() && !args.dry_run && !args.from_build) {
+        println!("~> Build files unchanged since last review on {}", utils::format_timestamp(at));
+        unattended = trust::always(package);
+    }
+
+    if !args.skips_review() && !args.dry_run && !args.from_build && trusted.is_none() {
+        let mut review = false;
+        for path in manifest_path.iter().chain(&local_recipe) {
+            println!("~> Manifest: {}", path.display());
+            utils::page_file(path);
+            review = true;
+        }
+        if let Some(file_path) = &build_file {
+            if file_path.exists() {
+                println!("~> Build file: {}", file_path.file_name().unwrap().to_string_lossy());
+                utils::page_file(file_path);
+                review = true;
+            }
+        }
+
+        if !submodules.is_empty() {
+            println!("~> Git submodules download from");
+            sources::print(&submodules);
+            review = true;
+        }
+
+        for (_, script) in &scripts {
+            let program = script.split_whitespace().next().unwrap_or("");
+            let script_file = build_dir.join(program);
+            if !program.is_empty() && script_file.is_file() {
+                println!("~> Script: {}", program);
+                utils::page_file(&script_file);
+            }
+            review = true;
+        }
+
+        if review {
+            match confirm_review(package, &base, &fingerprint) {
+                Some(always) => unattended = always,
+                None => return false,
+            }
+        }
+    }
+    let unattended_args;
+    let args = match unattended {
+        true => {
+            println!("~> {} is always trusted, building as with --no-confirm", package);
+            unattended_args = InstallArgs { no_confirm: true, ..args.clone() };
+            &unattended_args
+        }
+        false => args,
+    };
+
+    if !args.skip_deps && !system_dependencies(&manifest.dependencies, args) {
+        return false;
+    }
+    // the copy into a system directory comes last; asking now lets the timestamp be kept fresh
+    if args.system && !args.build_only && config.keep_sudo_alive {
+        sudo::authenticate();
+    }
+
+    // [build.env] holds defaults the repository may override; [env] is the user's
+    // override of the repository, and the command line wins over everything
+    let mut build_env = config.build_env();
+    build_env.extend(manifest.env.clone());
+    build_env.extend(config.env.clone());
+    build_env.extend(args.env.iter().cloned());
+
+    let compiler_cache = config.compiler_cache();
+    if let Some(cache) = compiler_cache {
+        build_env.extend(compiler_cache_env(cache, build_system, &build_env));
+    }
+    let reproducible = match config.reproducible {
+        true => reproducible_env(build_system, &build_dir, &build_env),
+        false => BTreeMap::new(),
+    };
+    build_env.extend(reproducible.clone());
+
+    println!("~> Building with flags: {:?}", final_flags);
+    if !build_env.is_empty() {
+        let vars: Vec<String> = build_env.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        println!("~> Build environment: {}", vars.join(" "));
+    }
+    if args.shell {
+        let command = format!("{} {}", build_system.build_commands(), final_flags.join(" "));
+        if !enter_shell(package, &build_dir, &build_env, &command) {
+            return false;
+        }
+    }
+    if args.sandboxed() {
+        // pip, pipx and npm install into the home directory as they build
+        if build_system.installs_itself() {
+            eprintln!(
+                "{} {} projects install themselves into your home directory, which --sandbox doesn't allow; build {} without it",
+                Red.paint("Error:"),
+                build_system.display_name(),
+                package
+            );
+            return false;
+        }
+        sandbox::enter(sandbox::Options::new(&build_dir, &config.sandbox_writable, args.sandbox_no_net));
+    }
+    if !args.dry_run {
+        build_log::start(package);
+    }
+    if !args.from_build && !run_scripts("pre_build", &manifest.pre_build, &build_dir, &build_env) {
+        return false;
+    }
+
+    // with --from-build everything in the tree counts as build output
+    let before_build = if args.from_build { BTreeMap::new() } else { utils::snapshot_tree(&build_dir) };
+    let estimate = stats::estimate(package, build_system.name());
+    if !args.from_build {
+        println!("~> {}Building {}{}", batch.prefix(), package, stats::banner_note(estimate));
+        info!(build_system = build_system.name(), "building");
+    }
+    let build_start = Instant::now();
+    // the countdown would garble the build output, so it only runs while that is hidden
+    let mut eta = if args.dry_run || args.from_build || log::verbose() { None } else { stats::Eta::start(estimate) };
+    build_log::set_phase("build");
+    // a failed phase can be retried from the failure menu, without cloning again
+    'build: loop {
+        let status = match build_system {
+            _ if args.from_build => {
+                println!("~> Using the existing build in {}", build_dir.display());
+                runner::success()
+            }
+            BuildSystem::Zig => {
+                Command::new("zig")
+                    .arg("build")
+                    .arg(if debug { "-Doptimize=Debug" } else { "-Doptimize=ReleaseSafe" })
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("Zig command failed")
+            }
+            BuildSystem::Make => {
+                let makefile = build_system::find_makefile(&build_dir)
+                    .unwrap_or_else(|| build_dir.join("Makefile"));
+
+                let mut cmd = Command::new("make");
+                cmd.arg("-f").arg(makefile)
+                    .args(&final_flags)
+                    .args(&manifest.target)
+                    .envs(&build_env)
+                    .current_dir(&build_dir);
+
+                cmd.run_logged().expect("Make command failed")
+            }
+            BuildSystem::Autotools => {
+                let configure_status = Command::new("./configure")
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("Configure command failed");
+            
+                if !configure_status.success() {
+                    drop(eta.take());
+                    if retry_failed(package, "Configure failed", &build_dir, &build_env, args) {
+                        continue 'build;
+                    }
+                    return false;
+                }
+            
+                Command::new("make")
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("Make command failed")
+            }
+            BuildSystem::CMake => {
+                if let Some(preset) = &cmake_preset {
+                    let mut configure = Command::new("cmake");
+                    configure.arg("--preset").arg(&preset.name);
+                    if preset.binary_dir.is_none() {
+                        configure.arg("-B").arg(&cmake_build_dir);
+                    }
+                    let configure_status = configure
+                        .args(&final_flags)
+                        .envs(&build_env)
+                        .current_dir(&build_dir)
+                        .run_logged()
+                        .expect("CMake command failed");
+
+                    if !configure_status.success() {
+                        drop(eta.take());
+                        if retry_failed(package, "CMake configure failed", &build_dir, &build_env, args) {
+                            continue 'build;
+                        }
+                        return false;
+                    }
+
+                    let mut build = Command::new("cmake");
+                    build.arg("--build");
+                    match &preset.build_preset {
+                        Some(build_preset) => build.arg("--preset").arg(build_preset),
+                        None => build.arg(&cmake_build_dir).arg("--config").arg(cmake_config),
+                    };
+                    build
+                        .envs(&build_env)
+                        .current_dir(&build_dir)
+                        .run_logged()
+                        .expect("CMake build failed")
+                } else {
+                    fs::create_dir_all(&cmake_build_dir).expect("Failed to create build dir");
+
+                    let mut cmake_cmd = Command::new("cmake");
+                    cmake_cmd
+                        .arg(format!("-DCMAKE_BUILD_TYPE={}", cmake_config))
+                        .args(&final_flags)
+                        .arg("..")
+                        .envs(&build_env)
+                        .current_dir(&cmake_build_dir);
+
+                    // without the build type the flags differ, which a reproducible build can't have
+                    let configure_status = match cmake_cmd.run_logged() {
+                        Ok(status) if status.success() || config.reproducible => status,
+                        _ => Command::new("cmake")
+                            .args(&final_flags)
+                            .arg("..")
+                            .envs(&build_env)
+                            .current_dir(&cmake_build_dir)
+                            .run_logged()
+                            .expect("CMake command failed"),
+                    };
+
+                    if !configure_status.success() {
+                        drop(eta.take());
+                        if retry_failed(package, "CMake configure failed", &build_dir, &build_env, args) {
+                            continue 'build;
+                        }
+                        return false;
+                    }
+
+                    Command::new("cmake")
+                        .arg("--build")
+                        .arg(".")
+                        .arg("--config")
+                        .arg(cmake_config)
+                        .envs(&build_env)
+                        .current_dir(&cmake_build_dir)
+                        .run_logged()
+                        .expect("CMake build failed")
+                }
+            }
+            BuildSystem::Cargo => {
+                let mut cargo_cmd = Command::new("cargo");
+                cargo_cmd.arg("build");
+                if !debug {
+                    cargo_cmd.arg("--release");
+                }
+                if let Some(triple) = &args.target_triple {
+                    cargo_cmd.arg("--target").arg(triple);
+                }
+                if !manifest.features.is_empty() {
+                    cargo_cmd.arg("--features").arg(manifest.features.join(","));
+                }
+                if manifest.no_default_features {
+                    cargo_cmd.arg("--no-default-features");
+                }
+                cargo_cmd
+                    .args(&final_flags)
+                    .arg("--manifest-path")
+                    .arg(build_dir.join("Cargo.toml"))
+                    .arg("--target-dir")
+                    .arg(build_dir.join("target"))
+                    .envs(&build_env)
+                    .current_dir(&build_dir);
+
+                cargo_cmd.run_logged().expect("Cargo command failed")
+            }
+            BuildSystem::Meson => {
+                let meson_build_dir = build_dir.join("build");
+                fs::create_dir_all(&meson_build_dir).expect("Failed to create build dir");
+
+                let meson_status = Command::new("meson")
+                    .arg("setup")
+                    .arg("--buildtype")
+                    .arg(&args.profile)
+                    .args(&final_flags)
+                    .arg(&meson_build_dir)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged();
+
+                if meson_status.is_err() || !meson_status.as_ref().unwrap().success() {
+                    Command::new("meson")
+                        .arg(&meson_build_dir)
+                        .envs(&build_env)
+                        .current_dir(&build_dir)
+                        .run_logged()
+                        .expect("Meson setup failed");
+                }
+
+                Command::new("ninja")
+                    .arg("-C")
+                    .arg(&meson_build_dir)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("Ninja build failed")
+            }
+            BuildSystem::Ninja => {
+                Command::new("ninja")
+                    .args(&final_flags)
+                    .args(&manifest.target)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("Ninja build failed")
+            }
+            BuildSystem::Nimble => {
+                Command::new("nimble")
+                    .arg("build")
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("Nimble command failed")
+            }
+            BuildSystem::Stack => {
+                Command::new("stack")
+                    .arg("install")
+                    .args(&final_flags)
+                    .arg("--local-bin-path")
+                    .arg(build_dir.join("bin"))
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    // stack's progress is the only sign it is still working
+                    .run_logged_visible()
+                    .expect("Stack command failed")
+            }
+            BuildSystem::Python => {
+                let mut python_cmd = if utils::command_exists("pipx") {
+                    let mut cmd = Command::new("pipx");
+                    cmd.arg("install").arg("--force");
+                    cmd
+                } else if python_has_pip() {
+                    let mut cmd = Command::new("python3");
+                    cmd.arg("-m").arg("pip").arg("install").arg("--user");
+                    cmd
+                } else {
+                    eprintln!(
+                        "{} Neither pipx nor pip is available. Install pipx (python-pipx on Arch, pipx on Debian/Fedora) and try again.",
+                        Red.paint("Error:")
+                    );
+                    return false;
+                };
+
+                python_cmd
+                    .args(&final_flags)
+                    .arg(&build_dir)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("Python install command failed")
+            }
+            BuildSystem::Node => {
+                let manager = config.node_package_manager.as_deref().unwrap_or("npm");
+                let mut deps_cmd = Command::new(manager);
+                match manager {
+                    "pnpm" => deps_cmd.arg("install").arg("--prod"),
+                    "yarn" => deps_cmd.arg("install").arg("--production"),
+                    _ => deps_cmd.arg("install").arg("--omit=dev"),
+                };
+
+                let deps_status = deps_cmd
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("Node package manager failed");
+
+                if !deps_status.success() {
+                    drop(eta.take());
+                    if retry_failed(package, "Installing dependencies failed", &build_dir, &build_env, args) {
+                        continue 'build;
+                    }
+                    return false;
+                }
+
+                // installing a directory globally only links it, so pack a tarball first; the
+                // tarball's name is read off the output, which run_logged doesn't keep
+                let mut pack = Command::new("npm");
+                pack.arg("pack").envs(&build_env).current_dir(&build_dir);
+                runner::sanitize(&mut pack);
+                runner::deprioritize(&mut pack);
+                let pack = pack.run_output().expect("npm pack failed");
+
+                let tarball = if args.dry_run {
+                    Some(build_dir.join(format!("{}.tgz", package)))
+                } else {
+                    String::from_utf8_lossy(&pack.stdout)
+                        .lines()
+                        .last()
+                        .map(|l| build_dir.join(l.trim()))
+                };
+
+                let tarball = match tarball {
+                    Some(t) if pack.status.success() && (t.is_file() || args.dry_run) => t,
+                    _ => {
+                        drop(eta.take());
+                        if retry_failed(package, "npm pack failed", &build_dir, &build_env, args) {
+                            continue 'build;
+                        }
+                        return false;
+                    }
+                };
+
+                Command::new("npm")
+                    .arg("install")
+                    .arg("-g")
+                    .arg("--prefix")
+                    .arg(paths::home_dir().join(".local"))
+                    .args(&final_flags)
+                    .arg(&tarball)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("npm install failed")
+            }
+            BuildSystem::Just => {
+                let targets = if manifest.target.is_empty() {
+                    vec!["build".to_string()]
+                } else {
+                    manifest.target.clone()
+                };
+                Command::new("just")
+                    .args(&targets)
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("Just command failed")
+            }
+            BuildSystem::SCons => {
+                Command::new("scons")
+                    .arg(format!("-j{}", utils::parallel_jobs()))
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("SCons command failed")
+            }
+            BuildSystem::Bazel => {
+                Command::new("bazel")
+                    .arg("build")
+                    .arg(if debug { "--compilation_mode=dbg" } else { "--compilation_mode=opt" })
+                    .args(&final_flags)
+                    .args(bazel_targets(&manifest))
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("Bazel command failed")
+            }
+            BuildSystem::Gradle => {
+                let mut gradle_cmd = if build_dir.join("gradlew").exists() {
+                    Command::new("./gradlew")
+                } else {
+                    Command::new("gradle")
+                };
+
+                gradle_cmd
+                    .arg("installDist")
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("Gradle command failed")
+            }
+            BuildSystem::Maven => {
+                let mut mvn_cmd = if build_dir.join("mvnw").exists() {
+                    Command::new("./mvnw")
+                } else {
+                    Command::new("mvn")
+                };
+
+                if !log::verbose() {
+                    mvn_cmd.arg("-q");
+                }
+
+                mvn_cmd
+                    .arg("package")
+                    .arg("-DskipTests")
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("Maven command failed")
+            }
+            BuildSystem::Waf => {
+                let waf = waf_command(&build_dir);
+                let configure_status = Command::new(&waf)
+                    .arg("configure")
+                    .arg("--prefix")
+                    .arg(&prefix)
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("waf configure failed");
+
+                if !configure_status.success() {
+                    drop(eta.take());
+                    if retry_failed(package, "Configure failed", &build_dir, &build_env, args) {
+                        continue 'build;
+                    }
+                    return false;
+                }
+
+                Command::new(&waf)
+                    .arg("build")
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("waf build failed")
+            }
+            BuildSystem::Premake => {
+                let generate_status = Command::new("premake5")
+                    .arg("gmake2")
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("premake5 command failed");
+
+                if !generate_status.success() {
+                    drop(eta.take());
+                    if retry_failed(package, "premake5 failed to generate makefiles", &build_dir, &build_env, args) {
+                        continue 'build;
+                    }
+                    return false;
+                }
+
+                let makefile_dir = match find_generated_makefile(&build_dir) {
+                    Some(dir) => dir,
+                    None => {
+                        drop(eta.take());
+                        if retry_failed(package, "premake5 did not generate a Makefile", &build_dir, &build_env, args) {
+                            continue 'build;
+                        }
+                        return false;
+                    }
+                };
+
+                Command::new("make")
+                    .arg(format!("-j{}", utils::parallel_jobs()))
+                    .arg(format!("config={}", args.profile))
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&makefile_dir)
+                    .run_logged()
+                    .expect("Make command failed")
+            }
+            BuildSystem::Crystal => {
+                let mut shards_cmd = Command::new("shards");
+                shards_cmd.arg("build");
+                if !debug {
+                    shards_cmd.arg("--release");
+                }
+                shards_cmd
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("shards command failed")
+            }
+            BuildSystem::Dub => {
+                Command::new("dub")
+                    .arg("build")
+                    .arg("-b")
+                    .arg(&args.profile)
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("dub command failed")
+            }
+            BuildSystem::Swift => {
+                Command::new("swift")
+                    .arg("build")
+                    .arg("-c")
+                    .arg(&args.profile)
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("swift command failed")
+            }
+            BuildSystem::Cabal => {
+                Command::new("cabal")
+                    .arg("install")
+                    .arg("--installdir")
+                    .arg(build_dir.join("aurora-staging"))
+                    .arg("--install-method=copy")
+                    .arg("--overwrite-policy=always")
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("cabal command failed")
+            }
+            BuildSystem::Dune => {
+                Command::new("dune")
+                    .arg("build")
+                    .arg("--profile")
+                    .arg(if debug { "dev" } else { "release" })
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("dune command failed")
+            }
+            BuildSystem::Gleam => {
+                Command::new("gleam")
+                    .arg("export")
+                    .arg("erlang-shipment")
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("gleam command failed")
+            }
+            BuildSystem::Rebar3 => {
+                Command::new("rebar3")
+                    .arg("escriptize")
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("rebar3 command failed")
+            }
+            BuildSystem::Mix => {
+                Command::new("mix")
+                    .arg("escript.build")
+                    .args(&final_flags)
+                    .env("MIX_ENV", "prod")
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("mix command failed")
+            }
+            BuildSystem::QMake => {
+                let qmake = ["qmake6", "qmake", "qmake-qt5"]
+                    .into_iter()
+                    .find(|q| utils::command_exists(q))
+                    .unwrap_or("qmake");
+                let configure_status = Command::new(qmake)
+                    .arg(format!("CONFIG+={}", args.profile))
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("qmake command failed");
+
+                if !configure_status.success() {
+                    drop(eta.take());
+                    if retry_failed(package, "qmake failed", &build_dir, &build_env, args) {
+                        continue 'build;
+                    }
+                    return false;
+                }
+
+                Command::new("make")
+                    .arg(format!("-j{}", utils::parallel_jobs()))
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("Make command failed")
+            }
+            BuildSystem::XMake => {
+                let configure_status = Command::new("xmake")
+                    .arg("f")
+                    .arg("-m")
+                    .arg(&args.profile)
+                    .arg("-y")
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("xmake command failed");
+
+                if !configure_status.success() {
+                    drop(eta.take());
+                    if retry_failed(package, "xmake configure failed", &build_dir, &build_env, args) {
+                        continue 'build;
+                    }
+                    return false;
+                }
+
+                Command::new("xmake")
+                    .arg("-y")
+                    .args(&final_flags)
+                    .envs(&build_env)
+                    .current_dir(&build_dir)
+                    .run_logged()
+                    .expect("xmake command failed")
+            }
+            BuildSystem::Go => {
+                let bin_dir = build_dir.join("bin");
+                let mut last_status = None;
+
+                for (name, target) in build_system::go_targets(&build_dir, repo) {
+                    let mut go_cmd = Command::new("go");
+                    go_cmd.arg("build");
+                    if debug {
+                        go_cmd.arg("-gcflags=all=-N -l");
+                    }
+                    go_cmd
+                        .arg("-o")
+                        .arg(bin_dir.join(format!("{}{}", name, env::consts::EXE_SUFFIX)))
+                        .args(&final_flags)
+                        .arg(&target)
+                        .envs(&build_env)
+                        .current_dir(&build_dir);
+
+                    if let Some(goflags) = &config.goflags {
+                        go_cmd.env("GOFLAGS", goflags);
+                    }
+
+                    let status = go_cmd.run_logged().expect("Go command failed");
+                    let failed = !status.success();
+                    last_status = Some(status);
+                    if failed {
+                        break;
+                    }
+                }
+
+                last_status.expect("Go project has no build targets")
+            }
+        };
+        if status.success() {
+            break;
+        }
+        drop(eta.take());
+        if !retry_failed(package, "Build failed", &build_dir, &build_env, args) {
+            return false;
+        }
+    }
+
+    drop(eta);
+    build_log::finish();
+    if !args.dry_run && !args.from_build {
+        stats::record(package, build_system.name(), build_start.elapsed());
+    }
+
+    if args.with_tests || config.with_tests.iter().any(|p| p == package) {
+        match test_command(build_system, &build_dir, &cmake_build_dir, cmake_config, debug) {
+            Some(mut test) => {
+                println!("~> Running the test suite");
+                build_log::set_phase("test");
+                let status = test.envs(&build_env).run_logged().expect("Test command failed");
+                if !status.success() {
+                    eprintln!("{} The tests failed, so {} was not installed", Red.paint("Error:"), package);
+                    explain_failure(package);
+                    return false;
+                }
+            }
+            None => println!(
+                "{} Found no test suite to run for {} ({}); installing without testing",
+                Yellow.paint("~> Warning:"),
+                package,
+                build_system.display_name()
+            ),
+        }
+    }
+
+    // installing is aurora's own copying, or an install step that writes outside the build
+    sandbox::leave();
+
+    if let (Some(cache), true) = (compiler_cache, log::verbose() && !args.dry_run) {
+        let _ = Command::new(cache).arg("--show-stats").run();
+    }
+
+    if args.dry_run {
+        let dest = if build_system.installs_itself() { self_install_dir(build_system) } else { dest };
+        let dest = (!args.build_only).then_some(dest.as_path());
+        print_plan(args, package, batch, &clone_url, &build_dir, build_system.name(), dest);
+        return true;
+    }
+
+    if args.build_only {
+        // these have their own install step, so their output is only known after it runs
+        let own_install = install_cmd.is_some()
+            || matches!(
+                build_system,
+                BuildSystem::Gradle | BuildSystem::Gleam | BuildSystem::Maven | BuildSystem::Waf | BuildSystem::Dune | BuildSystem::XMake
+            );
+        if !own_install {
+            let project = binary_name(&manifest, build_system, build_file.as_deref(), repo);
+            let binaries = find_binaries(&build_dir, &project, build_system, &before_build, &manifest, &cmake_build_dir, args);
+            println!("~> Artifacts:");
+            for binary in &binaries {
+                println!("   {}", binary.display());
+            }
+        }
+        println!("~> Build directory: {}", build_dir.display());
+        println!("~> Install it with: aurora install --from-build {}", package);
+        println!("{} in {}s", Green.paint("~> BUILD FINISHED"), start.elapsed().as_secs());
+        return true;
+    }
+
+    let mut state = State::load();
+    let mut conflicts = Vec::new();
+    if let Some(previous) = state.packages.get(package) {
+        state::save_version(package, previous);
+    }
+
+    let (dest, installed) = if build_system.installs_itself() {
+        let dest = self_install_dir(build_system);
+        let installed = self_installed_files(&build_dir, build_system, &dest);
+        (dest, installed)
+    } else if let Some(install_cmd) = &install_cmd {
+        println!("~> Running install command");
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(install_cmd)
+            .env("PREFIX", &prefix)
+            .env("DESTDIR", &staging)
+            .env("BUILD_DIR", &build_dir)
+            .envs(&build_env)
+            .current_dir(&build_dir)
+            .run_logged()
+            .expect("Failed to run install command");
+
+        if !status.success() {
+            eprintln!("{}", Red.paint("Install command failed"));
+            return false;
+        }
+
+        let staged_prefix = staging.join(prefix.strip_prefix("/").unwrap_or(&prefix));
+        match install_staged(package, &staged_prefix, &prefix, args, &mut state, &mut conflicts) {
+            Some(installed) => (dest, installed),
+            None => return false,
+        }
+    } else if build_system == BuildSystem::Gradle {
+        match install_gradle_dist(package, &build_dir, &dest, args, &mut state, &mut conflicts) {
+            Some(installed) => (dest, installed),
+            None => {
+                eprintln!("{} No installDist output found in build/install", Red.paint("Error:"));
+                return false;
+            }
+        }
+    } else if build_system == BuildSystem::Gleam {
+        match install_gleam_shipment(package, &build_dir, &dest, args, &mut state, &mut conflicts) {
+            Some(installed) => (dest, installed),
+            None => {
+                eprintln!("{} No erlang shipment found in build/erlang-shipment", Red.paint("Error:"));
+                return false;
+            }
+        }
+    } else if build_system == BuildSystem::Maven {
+        match install_maven_artifact(package, &build_dir, &dest, args, &mut state, &mut conflicts) {
+            Some(installed) => (dest, installed),
+            None => {
+                eprintln!(
+                    "{} No executable artifact found in target/. Expected a jar with a Main-Class manifest entry or appassembler output.",
+                    Red.paint("Error:")
+                );
+                return false;
+            }
+        }
+    } else if build_system == BuildSystem::Waf {
+        let staging = build_dir.join("aurora-staging");
+        let status = Command::new(waf_command(&build_dir))
+            .arg("install")
+            .arg("--destdir")
+            .arg(&staging)
+            .envs(&build_env)
+            .current_dir(&build_dir)
+            .run_logged()
+            .expect("waf install failed");
+
+        if !status.success() {
+            eprintln!("{}", Red.paint("waf install failed"));
+            return false;
+        }
+
+        let staged_prefix = staging.join(prefix.strip_prefix("/").unwrap_or(&prefix));
+        match install_staged(package, &staged_prefix, &prefix, args, &mut state, &mut conflicts) {
+            Some(installed) => (dest, installed),
+            None => return false,
+        }
+    } else if build_system == BuildSystem::Dune {
+        let staging = build_dir.join("aurora-staging");
+        let status = Command::new("dune")
+            .arg("install")
+            .arg("--prefix")
+            .arg(&staging)
+            .envs(&build_env)
+            .current_dir(&build_dir)
+            .run_logged()
+            .expect("dune install failed");
+
+        if !status.success() {
+            eprintln!("{}", Red.paint("dune install failed"));
+            return false;
+        }
+
+        match install_staged(package, &staging, &prefix, args, &mut state, &mut conflicts) {
+            Some(installed) => (dest, installed),
+            None => return false,
+        }
+    } else if build_system == BuildSystem::XMake {
+        let staging = build_dir.join("aurora-staging");
+        let status = Command::new("xmake")
+            .arg("install")
+            .arg("-o")
+            .arg(&staging)
+            .envs(&build_env)
+            .current_dir(&build_dir)
+            .run_logged()
+            .expect("xmake install failed");
+
+        if !status.success() {
+            eprintln!("{}", Red.paint("xmake install failed"));
+            return false;
+        }
+
+        match install_staged(package, &staging, &prefix, args, &mut state, &mut conflicts) {
+            Some(installed) => (dest, installed),
+            None => return false,
+        }
+    } else if let (BuildSystem::Just, Some(prefix)) = (build_system, &args.prefix) {
+        println!("~> Running just install with PREFIX={}", prefix.display());
+        let before = utils::snapshot_tree(prefix);
+        // the recipe runs whatever it likes, so it is sandboxed like the build, with the prefix
+        // writable as well
+        if args.sandboxed() {
+            let _ = fs::create_dir_all(prefix);
+            let mut options = sandbox::Options::new(&build_dir, &config.sandbox_writable, args.sandbox_no_net);
+            options.writable.push(prefix.clone());
+            sandbox::enter(options);
+        }
+        if !args.dry_run {
+            build_log::resume(package, "install");
+        }
+        let status = Command::new("just")
+            .arg("install")
+            .env("PREFIX", prefix)
+            .envs(&build_env)
+            .current_dir(&build_dir)
+            .run_logged();
+        build_log::finish();
+        sandbox::leave();
+
+        let status = match status {
+            Ok(status) => status,
+            Err(e) => {
+                eprintln!("{} Could not run just install: {}", Red.paint("Error:"), e);
+                return false;
+            }
+        };
+        if !status.success() {
+            eprintln!("{}", Red.paint("just install failed"));
+            return false;
+        }
+        (dest, utils::changed_files(prefix, &before))
+    } else {
+        println!("~> Installing...");
+        let project = binary_name(&manifest, build_system, build_file.as_deref(), repo);
+        let mut binaries = find_binaries(&build_dir, &project, build_system, &before_build, &manifest, &cmake_build_dir, args);
+        if binaries.len() > 1 && !args.answers_yes() {
+            binaries = select_binaries(&build_dir, binaries, build_system);
+        }
+
+        if binaries.is_empty() {
+            eprintln!("{}: Failed to find built binary", Red.paint("Error"));
+            return false;
+        }
+
+        // a foreign-architecture binary is of no use locally unless asked for
+        if args.target_triple.is_some() && args.dest.is_none() {
+            println!("~> Built for {}, not installing. Artifacts:", args.target_triple.as_deref().unwrap_or_default());
+            for binary in &binaries {
+                println!("   {}", binary.display());
+            }
+            println!("{} in {}s", Green.paint("~> BUILD FINISHED"), start.elapsed().as_secs());
+            return true;
+        }
+
+        let privileged = args.system && !utils::is_root();
+        if !dest.exists() && !privileged {
+            fs::create_dir_all(&dest).expect("Failed to create local bin directory");
+        }
+
+        let mut installed = Vec::new();
+        for bin_path in &binaries {
+            if let Some(dest_path) = install_binary(package, bin_path, &dest, args, &mut state, &mut conflicts) {
+                installed.push(dest_path);
+            }
+        }
+
+        if installed.is_empty() {
+            return false;
+        }
+        (dest, installed)
+    };
+
+    // user services go next to the user's own units, pointing at what was just installed
+    let mut installed = installed;
+    if cfg!(target_os = "linux") && !args.system {
+        let unit_dir = units::user_dir();
+        let previous_units: Vec<PathBuf> = state
+            .packages
+            .get(package)
+            .map(|record| record.files.iter().filter(|file| file.starts_with(&unit_dir)).cloned().collect())
+            .unwrap_or_default();
+        let written = units::install(package, &units::find(&build_dir, &manifest.units), &installed, &previous_units);
+        units::remove(&previous_units.into_iter().filter(|unit| !written.contains(unit)).collect::<Vec<_>>());
+        installed.extend(written);
+    }
+
+    // reinstalling keeps an explicit install explicit and adds to what requires it
+    let previous = state.packages.get(package);
+    let explicit = args.required_by.is_none() || previous.is_some_and(|r| r.explicit);
+    let mut required_by = previous.map(|r| r.required_by.clone()).unwrap_or_default();
+    if let Some(parent) = &args.required_by {
+        if !required_by.contains(parent) {
+            required_by.push(parent.clone());
+        }
+    }
+
+    let sha256 = installed.iter().filter_map(|file| Some((file.clone(), sha256::file(file)?))).collect();
+    let version = main_binary(package, &installed, &dest).and_then(|binary| smoke_test(&binary, manifest.version_args.as_deref()));
+    state.packages.insert(package.to_string(), InstallRecord {
+        build_system: build_system.name().to_string(),
+        files: installed,
+        sha256,
+        version: version.clone(),
+        installed_at: utils::unix_now(),
+        conflicts,
+        recipe: (!recipe_sources.is_empty()).then(|| recipe_sources.join(" + ")),
+        profile: Some(args.profile.clone()),
+        // a forced install moves the pin along to what was just built
+        pinned: pinned.map(|p| commit.clone().unwrap_or(p)),
+        commit,
+        explicit,
+        required_by,
+        reproducible,
+    });
+    state.save();
+
+    if !run_scripts("post_install", &manifest.post_install, &build_dir, &build_env) {
+        return false;
+    }
+
+    let version = version.map(|v| format!(" {}", v)).unwrap_or_default();
+    if args.system && !build_system.installs_itself() {
+        println!("{}", Green.paint(format!("Installed{} to /usr/local/bin.", version)));
+    } else {
+        println!("{}", Green.paint(format!("Installed{} to {}.", version, dest.display())));
+    }
+
+    if !paths::in_path(&dest) {
+        println!(
+            "{} {} is not in your PATH. Add it with:\n   {}",
+            Yellow.paint("~> Warning:"),
+            dest.display(),
+            paths::path_hint(&dest)
+        );
+    }
+
+    println!("{} in {}s", Green.paint("~> INSTALL FINISHED"), start.elapsed().as_secs());
+    true
+}
+
+// the executable named after the package, or else the first one installed into dest
+fn main_binary(package: &str, installed: &[PathBuf], dest: &Path) -> Option<PathBuf> {
+    let binaries: Vec<&PathBuf> = installed
+        .iter()
+        .filter(|file| file.parent() == Some(dest) && file.is_file() && utils::is_executable(file))
+        .collect();
+    binaries
+        .iter()
+        .find(|file| file.file_stem().is_some_and(|stem| stem == package))
+        .or(binaries.first())
+        .map(|file| file.to_path_buf())
+}
+
+const SMOKE_TEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+// runs the installed binary once so a missing shared library or a crash shows up now rather than
+// on first use; the first line it prints is kept as its version
+fn smoke_test(binary: &Path, version_args: Option<&[String]>) -> Option<String> {
+    let attempts = match version_args {
+        Some(version_args) => vec![version_args.to_vec()],
+        None => vec![vec!["--version".to_string()], vec!["--help".to_string()]],
+    };
+    let name = binary.file_name().unwrap_or_default().to_string_lossy();
+    let mut failure = String::new();
+    for arguments in &attempts {
+        let command = format!("{} {}", name, arguments.join(" "));
+        let output = match Command::new(binary).args(arguments).output_within(SMOKE_TEST_TIMEOUT) {
+            Ok(Some(output)) => output,
+            // trying --help wouldn't go any better
+            Ok(None) => {
+                failure = format!("{} did not exit within {}s", command, SMOKE_TEST_TIMEOUT.as_secs());
+                break;
+            }
+            Err(e) => {
+                failure = format!("{} could not be run: {}", command, e);
+                break;
+            }
+        };
+        // some tools print their version to stderr
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if output.status.success() {
+            return stdout.lines().chain(stderr.lines()).map(str::trim).find(|line| !line.is_empty()).map(String::from);
+        }
+        let status = match output.status.code() {
+            Some(code) => format!("exited with {}", code),
+            None => "crashed".to_string(),
+        };
+        // the dynamic linker's "error while loading shared libraries" ends up here
+        failure = match stderr.lines().map(str::trim).find(|line| !line.is_empty()) {
+            Some(line) => format!("{} {}: {}", command, status, line),
+            None => format!("{} {}", command, status),
+        };
+    }
+    println!(
+        "{} {} was installed, but it doesn't seem to run: {}",
+        Yellow.paint("~> Warning:"),
+        binary.display(),
+        failure
+    );
+    None
+}
+
+fn install_binary(
+    package: &str,
+    bin_path: &Path,
+    dest: &Path,
+    args: &InstallArgs,
+    state: &mut State,
+    conflicts: &mut Vec<Conflict>,
+) -> Option<PathBuf> {
+    let bin_name = bin_path.file_name().unwrap().to_str().unwrap();
+    let dest_path = dest.join(bin_name);
+
+    if !resolve_collision(package, &dest_path, args, state, conflicts) {
+        return None;
+    }
+
+    if let Some(interpreter) = utils::shebang_interpreter(bin_path) {
+        if !utils::command_exists(&interpreter) {
+            println!(
+                "{} {} runs with '{}', which is not in your PATH",
+                Yellow.paint("~> Warning:"),
+                bin_name,
+                interpreter
+            );
+        }
+    }
+
+    if args.system && !utils::is_root() {
+        let status = Command::new(utils::get_privilege_command())
+            .arg("install")
+            .arg("-Dm755")
+            .arg(bin_path)
+            .arg(&dest_path)
+            .run()
+            .expect("Failed to run privileged install");
+
+        if !status.success() {
+            eprintln!("{}", Red.paint("Failed to install binary into /usr/local/bin"));
+            return None;
+        }
+    } else if let Err(e) = copy_over(bin_path, &dest_path) {
+        eprintln!("{} Could not copy {} to {}: {}", Red.paint("Error:"), bin_name, dest_path.display(), e);
+        return None;
+    }
+
+    Some(dest_path)
+}
+
+// a symlink at `dest` is replaced, not written through to whatever it points at
+fn copy_over(source: &Path, dest: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(dest).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+        fs::remove_file(dest)?;
+    }
+    fs::copy(source, dest).map(|_| ())
+}
+
+// returns false when the user chose to skip installing over an existing file
+fn resolve_collision(
+    package: &str,
+    dest_path: &Path,
+    args: &InstallArgs,
+    state: &mut State,
+    conflicts: &mut Vec<Conflict>,
+) -> bool {
+    if !dest_path.exists() && fs::symlink_metadata(dest_path).is_err() {
+        return true;
+    }
+
+    let owner = state.owner_of(dest_path).map(|o| o.to_string());
+    if owner.as_deref() == Some(package) {
+        return true;
+    }
+
+    if let Some(other) = &owner {
+        eprintln!(
+            "{} {} is already installed by aurora package '{}'",
+            Red.bold().paint("~> CONFLICT:"),
+            dest_path.display(),
+            other
+        );
+        conflicts.push(Conflict { path: dest_path.to_path_buf(), package: other.clone() });
+    } else {
+        println!("{} {} already exists", Yellow.paint("~> Warning:"), dest_path.display());
+    }
+    describe_existing_file(dest_path);
+
+    let choice = if args.answers_yes() {
+        "b".to_string()
+    } else {
+        utils::prompt("~> [o]verwrite, [b]ackup and overwrite, or [s]kip? [B/o/s]")
+    };
+
+    match choice.to_ascii_lowercase().as_str() {
+        "o" => {}
+        "s" => {
+            println!(
+                "{} {}",
+                Yellow.paint("Install skipped by user:"),
+                dest_path.file_name().unwrap().to_string_lossy()
+            );
+            return false;
+        }
+        _ => {
+            if let Err(e) = backup_existing_file(dest_path) {
+                eprintln!("{} Could not back up {}: {}; leaving it as it is", Red.paint("Error:"), dest_path.display(), e);
+                return false;
+            }
+        }
+    }
+
+    if let Some(other) = &owner {
+        state.hand_over(dest_path, other, package);
+    }
+    true
+}
+
+fn install_gradle_dist(
+    package: &str,
+    build_dir: &Path,
+    dest: &Path,
+    args: &InstallArgs,
+    state: &mut State,
+    conflicts: &mut Vec<Conflict>,
+) -> Option<Vec<PathBuf>> {
+    let app_dir = fs::read_dir(build_dir.join("build/install"))
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|p| p.is_dir())?;
+    let app = app_dir.file_name()?.to_string_lossy().into_owned();
+
+    install_app_tree(package, &app_dir, &app, dest, args, state, conflicts)
+}
+
+fn install_maven_artifact(
+    package: &str,
+    build_dir: &Path,
+    dest: &Path,
+    args: &InstallArgs,
+    state: &mut State,
+    conflicts: &mut Vec<Conflict>,
+) -> Option<Vec<PathBuf>> {
+    let target_dir = build_dir.join("target");
+    let appassembler = target_dir.join("appassembler");
+    if appassembler.join("bin").is_dir() {
+        return install_app_tree(package, &appassembler, package, dest, args, state, conflicts);
+    }
+
+    let jar = find_executable_jar(&target_dir)?;
+    let jar_name = jar.file_name()?.to_string_lossy().into_owned();
+
+    // stage the jar and its wrapper so both go through the regular install paths
+    let staging = build_dir.join("aurora-staging");
+    fs::create_dir_all(staging.join("lib")).expect("Failed to create staging directory");
+    fs::create_dir_all(staging.join("bin")).expect("Failed to create staging directory");
+    fs::copy(&jar, staging.join("lib").join(&jar_name)).expect("Failed to stage jar");
+
+    let prefix = dest.parent().unwrap_or(dest);
+    let lib_dir = prefix.join("lib").join(package);
+    let installed_jar = lib_dir.join(&jar_name);
+    let wrapper = staging.join("bin").join(package);
+    fs::write(
+        &wrapper,
+        format!("#!/bin/sh\nexec java -jar \"{}\" \"$@\"\n", installed_jar.display()),
+    )
+    .expect("Failed to write wrapper script");
+    utils::make_executable(&wrapper);
+
+    println!("~> Installing {} into {}", jar_name, lib_dir.display());
+    let mut installed = utils::install_tree(&staging.join("lib"), &lib_dir, args.system && !utils::is_root())?;
+    installed.extend(install_binary(package, &wrapper, dest, args, state, conflicts));
+    Some(installed)
+}
+
+fn install_gleam_shipment(
+    package: &str,
+    build_dir: &Path,
+    dest: &Path,
+    args: &InstallArgs,
+    state: &mut State,
+    conflicts: &mut Vec<Conflict>,
+) -> Option<Vec<PathBuf>> {
+    let shipment = build_dir.join("build/erlang-shipment");
+    if !shipment.join("entrypoint.sh").is_file() {
+        return None;
+    }
+
+    let name = build_system::gleam_name(build_dir).unwrap_or_else(|| package.to_string());
+    let prefix = dest.parent().unwrap_or(dest);
+    let target = prefix.join("lib").join(&name);
+
+    println!("~> Installing erlang shipment into {}", target.display());
+    let mut installed = utils::install_tree(&shipment, &target, args.system && !utils::is_root())?;
+
+    let wrapper = build_dir.join("aurora-staging").join(&name);
+    fs::create_dir_all(wrapper.parent()?).expect("Failed to create staging directory");
+    fs::write(
+        &wrapper,
+        format!("#!/bin/sh\nexec \"{}\" run \"$@\"\n", target.join("entrypoint.sh").display()),
+    )
+    .expect("Failed to write wrapper script");
+    utils::make_executable(&wrapper);
+
+    installed.extend(install_binary(package, &wrapper, dest, args, state, conflicts));
+    Some(installed)
+}
+
+fn find_executable_jar(target_dir: &Path) -> Option<PathBuf> {
+    let mut jars: Vec<PathBuf> = fs::read_dir(target_dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().map(|e| e == "jar").unwrap_or(false))
+        .filter(|p| {
+            let name = p.file_name().unwrap_or_default().to_string_lossy();
+            !(name.starts_with("original-")
+                || name.ends_with("-sources.jar")
+                || name.ends_with("-javadoc.jar")
+                || name.ends_with("-tests.jar"))
+        })
+        .collect();
+
+    // shaded jars bundle their dependencies and are the largest candidate
+    jars.sort_by_key(|p| std::cmp::Reverse(fs::metadata(p).map(|m| m.len()).unwrap_or(0)));
+
+    if !utils::command_exists("unzip") {
+        return jars.into_iter().next();
+    }
+
+    jars.into_iter().find(|jar| {
+        Command::new("unzip")
+            .arg("-p")
+            .arg(jar)
+            .arg("META-INF/MANIFEST.MF")
+            .output_always()
+            .map(|o| String::from_utf8_lossy(&o.stdout).contains("Main-Class:"))
+            .unwrap_or(false)
+    })
+}
+
+fn install_app_tree(
+    package: &str,
+    app_dir: &Path,
+    app: &str,
+    dest: &Path,
+    args: &InstallArgs,
+    state: &mut State,
+    conflicts: &mut Vec<Conflict>,
+) -> Option<Vec<PathBuf>> {
+    // the start scripts resolve their own symlinks, so the tree can live
+    // outside of bin and only the launchers get linked into it
+    let prefix = dest.parent().unwrap_or(dest);
+    let target = prefix.join("lib").join(app);
+    let privileged = args.system && !utils::is_root();
+
+    println!("~> Installing {} distribution into {}", app, target.display());
+    let mut installed = utils::install_tree(app_dir, &target, privileged)?;
+
+    for launcher in executables_in(&app_dir.join("bin")) {
+        if launcher.extension().map(|e| e == "bat").unwrap_or(false) {
+            continue;
+        }
+
+        let name = launcher.file_name()?.to_string_lossy().into_owned();
+        let link = dest.join(&name);
+        if !resolve_collision(package, &link, args, state, conflicts) {
+            continue;
+        }
+
+        if utils::install_symlink(&target.join("bin").join(&name), &link, privileged) {
+            installed.push(link);
+        }
+    }
+
+    Some(installed)
+}
+
+fn choose_cmake_preset(build_dir: &Path, requested: Option<&str>, debug: bool) -> Result<Option<CMakePreset>, ()> {
+    let mut presets = build_system::cmake_presets(build_dir);
+    let names: Vec<String> = presets.iter().map(|p| p.name.clone()).collect();
+
+    if let Some(requested) = requested {
+        if !names.iter().any(|n| n == requested) {
+            eprintln!("{} No configure preset named '{}'", Red.paint("Error:"), requested);
+            if !names.is_empty() {
+                eprintln!("~> Available presets: {}", names.join(", "));
+            }
+            return Err(());
+        }
+    } else if !presets.is_empty() {
+        println!("~> Configure presets: {}", names.join(", "));
+    }
+
+    let wanted = requested
+        .map(|r| vec![r])
+        .unwrap_or_else(|| if debug { vec!["debug", "Debug", "default"] } else { vec!["release", "Release", "default"] });
+    let chosen = wanted
+        .iter()
+        .find_map(|w| presets.iter().position(|p| p.name == *w))
+        .map(|i| presets.swap_remove(i));
+
+    if chosen.is_none() && !names.is_empty() {
+        println!("{} No {} or default preset, configuring without presets", Yellow.paint("~> Warning:"), wanted[0]);
+    }
+    Ok(chosen)
+}
+
+fn find_generated_makefile(build_dir: &Path) -> Option<PathBuf> {
+    for dir in ["", "build", "build/gmake2", "gmake2", "projects"] {
+        let dir = build_dir.join(dir);
+        if build_system::find_makefile(&dir).is_some() {
+            return Some(dir);
+        }
+    }
+
+    // premake's `location` can point anywhere, so fall back to a shallow search
+    let mut pending = vec![(build_dir.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        for entry in fs::read_dir(&dir).ok()?.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.is_dir() && depth < 2 && entry.file_name() != ".git" {
+                if build_system::find_makefile(&path).is_some() {
+                    return Some(path);
+                }
+                pending.push((path, depth + 1));
+            }
+        }
+    }
+    None
+}
+
+fn waf_command(build_dir: &Path) -> PathBuf {
+    let bundled = build_dir.join("waf");
+    if bundled.is_file() {
+        PathBuf::from("./waf")
+    } else {
+        PathBuf::from("waf")
+    }
+}
+
+// merges a staged install tree laid out like the prefix into the prefix
+fn install_staged(
+    package: &str,
+    staged_prefix: &Path,
+    prefix: &Path,
+    args: &InstallArgs,
+    state: &mut State,
+    conflicts: &mut Vec<Conflict>,
+) -> Option<Vec<PathBuf>> {
+    if !staged_prefix.is_dir() {
+        eprintln!("{} Nothing was installed into {}", Red.paint("Error:"), staged_prefix.display());
+        return None;
+    }
+
+    for staged in utils::snapshot_tree(staged_prefix).into_keys() {
+        let rel = staged.strip_prefix(staged_prefix).ok()?;
+        if !resolve_collision(package, &prefix.join(rel), args, state, conflicts) {
+            let _ = fs::remove_file(&staged);
+        }
+    }
+
+    println!("~> Installing staged files into {}", prefix.display());
+    utils::install_tree(staged_prefix, prefix, args.system && !utils::is_root())
+}
+
+// how to hook a compiler cache into each build system; compilers the user picked get wrapped
+fn compiler_cache_env(cache: &str, build_system: BuildSystem, build_env: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    let mut env = BTreeMap::new();
+    match build_system {
+        BuildSystem::CMake => {
+            env.insert("CMAKE_C_COMPILER_LAUNCHER".to_string(), cache.to_string());
+            env.insert("CMAKE_CXX_COMPILER_LAUNCHER".to_string(), cache.to_string());
+        }
+        BuildSystem::Make
+        | BuildSystem::Autotools
+        | BuildSystem::Meson
+        | BuildSystem::QMake
+        | BuildSystem::Premake => {
+            for (var, default) in [("CC", "cc"), ("CXX", "c++")] {
+                let compiler = build_env
+                    .get(var)
+                    .cloned()
+                    .or_else(|| env::var(var).ok())
+                    .unwrap_or_else(|| default.to_string());
+                if !compiler.starts_with(cache) {
+                    env.insert(var.to_string(), format!("{} {}", cache, compiler));
+                }
+            }
+        }
+        // only sccache understands rustc
+        BuildSystem::Cargo if cache == "sccache" => {
+            env.insert("RUSTC_WRAPPER".to_string(), cache.to_string());
+        }
+        _ => {}
+    }
+    env
+}
+
+// SOURCE_DATE_EPOCH from the last commit, and for cargo and cmake builds debug info and panic
+// messages without the build directory in their paths; flags from the environment stay in front
+fn reproducible_env(build_system: BuildSystem, build_dir: &Path, build_env: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    let mut env = BTreeMap::new();
+    if let Some(time) = utils::git_commit_time(build_dir) {
+        env.insert("SOURCE_DATE_EPOCH".to_string(), time.to_string());
+    }
+    let with = |var: &str, flags: String| match build_env.get(var).cloned().or_else(|| env::var(var).ok()).filter(|v| !v.is_empty()) {
+        Some(existing) => format!("{} {}", existing, flags),
+        None => flags,
+    };
+    // the flags are split on whitespace
+    let cargo_home = env::var_os("CARGO_HOME").map(PathBuf::from).unwrap_or_else(|| paths::home_dir().join(".cargo"));
+    let splits = |path: &Path| path.to_string_lossy().contains(char::is_whitespace);
+    match build_system {
+        BuildSystem::Cargo if !splits(build_dir) && !splits(&cargo_home) => {
+            let flags = format!("--remap-path-prefix={}=/build --remap-path-prefix={}=/cargo", build_dir.display(), cargo_home.display());
+            env.insert("RUSTFLAGS".to_string(), with("RUSTFLAGS", flags));
+        }
+        // msvc has no -ffile-prefix-map
+        BuildSystem::CMake if !cfg!(windows) && !splits(build_dir) => {
+            for var in ["CFLAGS", "CXXFLAGS"] {
+                env.insert(var.to_string(), with(var, format!("-ffile-prefix-map={}=.", build_dir.display())));
+            }
+        }
+        _ => {}
+    }
+    env
+}
+
+// checks the build tools exist before anything runs, naming the packages that provide them
+fn preflight(build_system: BuildSystem, build_dir: &Path, args: &InstallArgs) -> bool {
+    // entries like "dmd|ldc2|gdc" are satisfied by any one of the alternatives
+    let mut missing = Vec::new();
+    for tool in build_system.required_tools(build_dir) {
+        match tool.split('|').find_map(utils::find_command) {
+            Some(path) if log::verbose() => println!("~> Found {}", path.display()),
+            Some(_) => {}
+            None => missing.push(tool),
+        }
+    }
+
+    if build_system.needs_java() && env::var_os("JAVA_HOME").is_none() {
+        if utils::command_exists("java") {
+            println!("{} JAVA_HOME is not set; using java from PATH", Yellow.paint("~> Warning:"));
+        } else {
+            missing.push("java");
+        }
+    }
+
+    if missing.is_empty() {
+        return true;
     }
-}
 
-fn install_single(package: &str, flags: &[String], yes: bool) {
-    if Path::new("/etc/arch-release").exists() {
-        install_with_makepkg(package, flags, yes);
+    let names: Vec<String> = missing.iter().map(|tool| tool.replace('|', " or ")).collect();
+    let message = format!(
+        "{} requires {}, which could not be found in PATH",
+        build_system.display_name(),
+        names.join(", ")
+    );
+    if args.dry_run {
+        println!("{} {}", Yellow.paint("~> Warning:"), message);
     } else {
-        install_with_build_system(package, flags, yes);
+        eprintln!("{} {}", Red.paint("Error:"), message);
+    }
+
+    if let Some(manager) = distro::package_manager() {
+        let mut packages: Vec<&str> = Vec::new();
+        for package in missing.iter().filter_map(|tool| manager.package_for(tool)) {
+            if !packages.contains(&package) {
+                packages.push(package);
+            }
+        }
+        if !packages.is_empty() {
+            println!("~> Install with: {} {}", manager.install_command(), packages.join(" "));
+        }
     }
+    if !args.dry_run {
+        println!("~> Pass --skip-preflight to build anyway");
+    }
+    args.dry_run
 }
 
-fn install_with_makepkg(package: &str, flags: &[String], yes: bool) {
-    let start = Instant::now();
-    let tmp = Path::new("/tmp/aurora");
-    let builds = tmp.join("builds");
+// the manifest's "dependencies" for this distro; other distros get the whole list to sort out
+fn system_dependencies(dependencies: &BTreeMap<String, Vec<String>>, args: &InstallArgs) -> bool {
+    if dependencies.is_empty() {
+        return true;
+    }
+    let manager = distro::package_manager();
+    let (manager, packages) = match manager.and_then(|m| Some((m, dependencies.get(m.family())?))) {
+        Some(found) => found,
+        None => {
+            println!("{} The manifest lists system packages for other distros; install the equivalents yourself:", Yellow.paint("~> Warning:"));
+            for (family, packages) in dependencies {
+                println!("   {}: {}", family, packages.join(" "));
+            }
+            return true;
+        }
+    };
+    let missing = manager.missing(packages);
+    if missing.is_empty() {
+        return true;
+    }
 
-    for dir in [tmp, &builds] {
-        if !dir.exists() {
-            fs::create_dir_all(dir).expect("Failed to create temp directory");
+    println!("~> Missing system packages: {}", missing.join(" "));
+    if !args.answers_yes() && !args.dry_run {
+        let input = utils::prompt(&format!("~> Install them with {}? [Y/n]", manager.install_command()));
+        if input.eq_ignore_ascii_case("n") {
+            println!("{}", Yellow.paint("Build cancelled; pass --skip-deps to build without them"));
+            return false;
         }
     }
+    if !manager.install(&missing, args.answers_yes()) {
+        eprintln!("{} Installing {} failed", Red.paint("Error:"), missing.join(" "));
+        return false;
+    }
+    sudo::keep_alive();
+    true
+}
 
-    let build_dir = builds.join(package);
+// the cpu a cross build is aimed at, from flags like --target=<triple>, --host=<triple>
+// (autotools) or CC=<triple>-gcc
+fn flags_arch(flags: &[String]) -> Option<String> {
+    let mut words = flags.iter().map(|f| f.as_str());
+    while let Some(flag) = words.next() {
+        let triple = match flag.split_once('=') {
+            Some(("--target" | "--host" | "-DCMAKE_SYSTEM_PROCESSOR" | "-DCMAKE_SYSTEM_PROCESSOR:STRING", value)) => value,
+            Some(("CC" | "CXX", compiler)) if compiler.matches('-').count() >= 2 => compiler,
+            _ if flag == "--target" || flag == "--host" => words.next()?,
+            _ => continue,
+        };
+        let arch = triple.split('-').next().unwrap_or(triple);
+        if !arch.is_empty() {
+            return Some(arch.to_string());
+        }
+    }
+    None
+}
 
-    if build_dir.exists() {
-        fs::remove_dir_all(&build_dir).expect("Failed to clean previous build");
+// the name find_binaries looks for
+fn binary_name(manifest: &AuroraManifest, build_system: BuildSystem, build_file: Option<&Path>, repo: &str) -> String {
+    match (&manifest.binary, build_system) {
+        (Some(binary), _) => binary.clone(),
+        (None, BuildSystem::Nimble) => build_file
+            .and_then(|f| f.file_stem())
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| repo.to_string()),
+        _ => repo.to_string(),
     }
+}
 
-    println!("\x1b[1m~> Cloning AUR repository: {}\x1b[0m", package);
-    let status = Command::new("git")
-        .arg("clone")
-        .arg("--depth=1")
-        .arg(format!("https://aur.archlinux.org/{}.git", package))
-        .arg(&build_dir)
-        .stdout(Stdio::null())
-        .status()
-        .expect("Git command failed");
+// pinned packages build the pinned commit; anything else needs --force
+fn use_pinned_commit(package: &str, pinned: &str, build_dir: &Path, args: &InstallArgs) -> Option<String> {
+    let head = utils::git_head(build_dir);
+    if head.as_deref() == Some(pinned) {
+        return head;
+    }
 
-    if !status.success() {
-        eprintln!("{}", Red.paint("Failed to clone repository"));
-        return;
+    if args.force {
+        println!(
+            "{} {} is pinned at {}, building the latest commit because of --force",
+            Yellow.paint("~> Warning:"),
+            package,
+            utils::short_commit(pinned)
+        );
+        return head;
     }
 
-    println!("\x1b[1m~> Building and installing {} from AUR\x1b[0m", package);
+    // a shallow clone only has the latest commit, and a finished build must not be switched under
+    let git = |git_args: &[&str]| {
+        Command::new("git")
+            .arg("-C")
+            .arg(build_dir)
+            .args(git_args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .run_always()
+            .map(|s| s.success())
+            .unwrap_or(false)
+    };
+    let checked_out = !args.from_build
+        && (args.offline || git(&["fetch", "-q", "--unshallow"]) || git(&["fetch", "-q"]))
+        && git(&["checkout", "-q", pinned]);
+    if checked_out {
+        println!("~> {} is pinned, building {}", package, utils::short_commit(pinned));
+        return Some(pinned.to_string());
+    }
 
-    if !yes {
-        println!("~> PKGBUILD contents:");
-        let pkgbuild_path = build_dir.join("PKGBUILD");
-        if pkgbuild_path.exists() {
-            let status = Command::new("less")
-                .arg(&pkgbuild_path)
-                .status();
+    eprintln!(
+        "{} {} is pinned at {} but the sources are at {}. Pass --force to install them, or run aurora unpin {}",
+        Red.paint("Error:"),
+        package,
+        utils::short_commit(pinned),
+        head.as_deref().map(utils::short_commit).unwrap_or("an unknown commit"),
+        package
+    );
+    None
+}
 
-            if status.is_err() || !status.unwrap().success() {
-                let _ = Command::new("cat")
-                    .arg(&pkgbuild_path)
-                    .status();
-            }
+pub fn aur_url(package: &str) -> String {
+    format!("https://aur.archlinux.org/{}.git", package)
+}
 
-            print!("~> Proceed with build? [Y/n] ");
-            io::stdout().flush().unwrap();
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
+// a dry run clones somewhere else so it never clobbers a real build; --offline reuses whichever
+// clone is already there, from an earlier dry run or `aurora fetch`
+fn clone_package(url: &str, build_dir: &Path, args: &InstallArgs) -> Option<PathBuf> {
+    let build_dir = if args.dry_run {
+        paths::temp_dir().join("dry-run").join(build_dir.file_name()?)
+    } else {
+        build_dir.to_path_buf()
+    };
 
-            if input.trim().eq_ignore_ascii_case("n") {
-                println!("{}", Yellow.paint("Build cancelled by user"));
-                return;
-            }
+    if args.from_build {
+        if !build_dir.is_dir() {
+            eprintln!(
+                "{} No build in {}; run aurora build first",
+                Red.paint("Error:"),
+                build_dir.display()
+            );
+            return None;
         }
+        return Some(build_dir);
     }
 
-    let mut makepkg = Command::new("makepkg");
-    makepkg.arg("-si");
-
-    if yes {
-        makepkg.arg("--noconfirm");
+    if args.shell && build_dir.join(".git").is_dir() {
+        println!("~> Using the cached clone in {}", build_dir.display());
+        return Some(build_dir);
     }
 
-    for flag in flags {
-        makepkg.arg(flag);
+    if args.offline {
+        if !build_dir.join(".git").is_dir() {
+            eprintln!("{} No cached clone in {}", Red.paint("Error:"), build_dir.display());
+            return None;
+        }
+        return Some(build_dir);
     }
 
-    let status = makepkg
-        .current_dir(&build_dir)
-        .status()
-        .expect("makepkg command failed");
+    if build_dir.exists() {
+        fs::remove_dir_all(&build_dir).expect("Failed to clean previous build");
+    }
 
-    if !status.success() {
-        eprintln!("{}", Red.paint("Build/install failed"));
-        return;
+    if !utils::git_clone(url, &build_dir) {
+        eprintln!("{}", Red.paint("Failed to clone repository"));
+        return None;
     }
 
-    println!("{} in {}s", Green.paint("~> INSTALL FINISHED"), start.elapsed().as_secs());
+    // the AUR serves an empty repository for any name, so a typo only shows up here
+    let empty = fs::read_dir(&build_dir).map_or(true, |mut entries| entries.all(|e| e.is_ok_and(|e| e.file_name() == ".git")));
+    if empty {
+        let package = build_dir.file_name()?.to_string_lossy().into_owned();
+        let _ = fs::remove_dir_all(&build_dir);
+        not_found(&package);
+        return None;
+    }
+    Some(build_dir)
 }
 
-fn install_with_build_system(package: &str, flags: &[String], yes: bool) {
-    let start = Instant::now();
-    let tmp = Path::new("/tmp/aurora");
-    let builds = tmp.join("builds");
+fn not_found(package: &str) {
+    match aur::info(&[package.to_string()]) {
+        Ok(found) if found.contains_key(package) => {
+            eprintln!("{} The AUR repository for {} is empty; try again later", Red.paint("Error:"), package);
+            return;
+        }
+        Ok(_) => {}
+        Err(_) => {
+            eprintln!("{} Package '{}' was not found in the AUR (its repository is empty)", Red.paint("Error:"), package);
+            return;
+        }
+    }
 
-    for dir in [tmp, &builds] {
-        if !dir.exists() {
-            fs::create_dir_all(dir).expect("Failed to create temp directory");
+    // a search needs two characters; a typo late in the name still matches on the first half
+    let half: String = package.chars().take(package.chars().count().div_ceil(2).max(2)).collect();
+    let mut candidates: Vec<(usize, String)> = Vec::new();
+    for query in [package, half.as_str()] {
+        if query.chars().count() < 2 || !candidates.is_empty() {
+            continue;
+        }
+        for result in aur::search(query, "name").unwrap_or_default() {
+            let distance = utils::edit_distance(package, &result.name);
+            if distance <= (package.chars().count() / 3).max(2) {
+                candidates.push((distance, result.name));
+            }
         }
     }
+    candidates.sort();
+    let suggestions: Vec<String> = candidates.into_iter().take(3).map(|(_, name)| name).collect();
 
-    let domain = "aur.archlinux.org";
-    let repo = package;
-    let build_dir = builds.join(repo);
+    if suggestions.is_empty() {
+        eprintln!("{} Package '{}' was not found in the AUR", Red.paint("Error:"), package);
+    } else {
+        eprintln!("{} Package '{}' was not found in the AUR; did you mean {}?", Red.paint("Error:"), package, suggestions.join(", "));
+    }
+}
 
-    if build_dir.exists() {
-        fs::remove_dir_all(&build_dir).expect("Failed to clean previous build");
+fn print_plan(
+    args: &InstallArgs,
+    package: &str,
+    batch: Batch,
+    clone_url: &str,
+    build_dir: &Path,
+    build_system: &str,
+    dest: Option<&Path>,
+) {
+    let commands = runner::take_plan();
+
+    if args.json {
+        let plan = serde_json::json!({
+            "package": package,
+            "index": batch.index,
+            "total": batch.total,
+            "clone_url": clone_url,
+            "build_dir": build_dir,
+            "build_system": build_system,
+            "commands": commands,
+            "dest": dest,
+        });
+        println!("{}", serde_json::to_string_pretty(&plan).expect("Failed to serialize plan"));
+        return;
     }
 
-    println!("\x1b[1m~> Cloning repository: {}\x1b[0m", package);
-    let status = Command::new("git")
-        .arg("clone")
-        .arg("--depth=1")
-        .arg(format!("https://{}/{}.git", domain, package))
-        .arg(&build_dir)
-        .stdout(Stdio::null())
-        .status()
-        .expect("Git command failed");
+    println!("{}", Yellow.paint("~> Dry run, nothing was built or installed"));
+    println!("   clone: {}", clone_url);
+    println!("   build system: {}", build_system);
+    for command in &commands {
+        println!("   run: {}", command.shell_line());
+    }
+    if let Some(dest) = dest {
+        println!("   install to: {}", dest.display());
+    }
+}
 
-    if !status.success() {
-        eprintln!("{}", Red.paint("Failed to clone repository"));
-        return;
+fn run_scripts(
+    stage: &str,
+    scripts: &[String],
+    build_dir: &Path,
+    build_env: &BTreeMap<String, String>,
+) -> bool {
+    build_log::set_phase(stage);
+    for script in scripts {
+        println!("~> Running {} script: {}", stage, script);
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .envs(build_env)
+            .current_dir(build_dir)
+            .run_logged();
+
+        if !status.map(|s| s.success()).unwrap_or(false) {
+            eprintln!("{} {} script failed: {}", Red.paint("Error:"), stage, script);
+            return false;
+        }
     }
+    true
+}
 
-    println!("\x1b[1m~> Searching for build file\x1b[0m");
-    let makefiles = ["Makefile", "makefile", "GNUMakefile"];
-    let has_makefile = makefiles.iter().any(|f| build_dir.join(f).exists());
-
-    let aurora_json_path = build_dir.join("aurora.json");
-    let (build_system, custom_flags) = if aurora_json_path.exists() {
-        parse_aurora_json(&aurora_json_path)
-    } else if has_makefile {
-        ("make".to_string(), vec![])
-    } else if build_dir.join("configure").exists() {
-        ("autotools".to_string(), vec![])
-    } else if build_dir.join("Cargo.toml").exists() {
-        ("cargo".to_string(), vec![])
-    } else if build_dir.join("CMakeLists.txt").exists() {
-        ("cmake".to_string(), vec![])
-    } else if build_dir.join("meson.build").exists() {
-        ("meson".to_string(), vec![])
-    } else if build_dir.join("build.ninja").exists() {
-        ("ninja".to_string(), vec![])
-    } else if build_dir.join("*.nimble").exists() {
-        ("nimble".to_string(), vec![])
-    } else if build_dir.join("stack.yaml").exists() {
-        ("stack".to_string(), vec![])
-    } else {
-        eprintln!("{}", Red.paint("No build system found"));
-        return;
+// the project's own test suite, run by --with-tests between the build and the install
+fn test_command(build_system: BuildSystem, build_dir: &Path, cmake_build_dir: &Path, cmake_config: &str, debug: bool) -> Option<Command> {
+    let command = |program: &str, arguments: &[&str]| {
+        let mut command = Command::new(program);
+        command.args(arguments).current_dir(build_dir);
+        command
     };
+    let wrapper = |wrapper: &str, program: &str| {
+        if build_dir.join(wrapper).exists() { format!("./{}", wrapper) } else { program.to_string() }
+    };
+    let test = match build_system {
+        BuildSystem::Cargo => {
+            let mut cargo = command("cargo", &["test"]);
+            if !debug {
+                cargo.arg("--release");
+            }
+            cargo.arg("--target-dir").arg(build_dir.join("target"));
+            cargo
+        }
+        BuildSystem::Make | BuildSystem::Autotools => {
+            let makefile = build_system::find_makefile(build_dir).unwrap_or_else(|| build_dir.join("Makefile"));
+            // `make -n` fails when there is no rule for the target
+            let target = ["check", "test"].into_iter().find(|target| {
+                Command::new("make")
+                    .arg("-f")
+                    .arg(&makefile)
+                    .arg("-n")
+                    .arg(target)
+                    .current_dir(build_dir)
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .run_always()
+                    .is_ok_and(|s| s.success())
+            })?;
+            let mut make = command("make", &[]);
+            make.arg("-f").arg(&makefile).arg(target);
+            make
+        }
+        BuildSystem::CMake => {
+            let mut ctest = command("ctest", &["--output-on-failure", "-C", cmake_config]);
+            ctest.current_dir(cmake_build_dir);
+            ctest
+        }
+        BuildSystem::Meson => command("meson", &["test", "-C", "build"]),
+        BuildSystem::Go => command("go", &["test", "./..."]),
+        BuildSystem::Zig => command("zig", &["build", "test"]),
+        BuildSystem::Nimble => command("nimble", &["test"]),
+        BuildSystem::Stack => command("stack", &["test"]),
+        BuildSystem::Cabal => command("cabal", &["test"]),
+        BuildSystem::Dune => command("dune", &["test"]),
+        BuildSystem::Gleam => command("gleam", &["test"]),
+        BuildSystem::Rebar3 => command("rebar3", &["eunit"]),
+        BuildSystem::Mix => command("mix", &["test"]),
+        BuildSystem::Swift => command("swift", &["test"]),
+        BuildSystem::Crystal => command("crystal", &["spec"]),
+        BuildSystem::Dub => command("dub", &["test"]),
+        BuildSystem::Gradle => command(&wrapper("gradlew", "gradle"), &["test"]),
+        BuildSystem::Maven => command(&wrapper("mvnw", "mvn"), &["test"]),
+        _ => return None,
+    };
+    Some(test)
+}
 
-    let mut final_flags = custom_flags;
-    final_flags.extend(flags.iter().cloned());
-
-    println!("~> Build system: {}", match build_system.as_str() {
-        "make" => Green.paint("Make"),
-        "autotools" => Green.paint("Autotools"),
-        "cargo" => Green.paint("Cargo"),
-        "cmake" => Green.paint("CMake"),
-        "meson" => Green.paint("Meson"),
-        "ninja" => Green.paint("Ninja"),
-        "nimble" => Green.paint("Nimble"),
-        "stack" => Green.paint("Stack"),
-        _ => unreachable!()
-    });
+// `aurora shell`: the build environment without the build; true to go on with the build in
+// whatever state the shell left the directory
+fn enter_shell(package: &str, build_dir: &Path, build_env: &BTreeMap<String, String>, command: &str) -> bool {
+    println!("~> aurora would run: {}", command.trim());
+    println!("~> Starting a shell in {}; exit it to continue", build_dir.display());
+    utils::shell(build_dir, build_env);
+    let input = utils::prompt(&format!("~> Build {} from this directory now? [Y/n]", package));
+    if input.eq_ignore_ascii_case("n") {
+        println!("{}", Yellow.paint("Build cancelled by user"));
+        return false;
+    }
+    true
+}
 
-    let build_file = match build_system.as_str() {
-        "make" => makefiles.iter().find(|f| build_dir.join(f).exists()).map(|f| f.to_string()),
-        "autotools" => Some("configure".to_string()),
-        "cargo" => Some("Cargo.toml".to_string()),
-        "cmake" => Some("CMakeLists.txt".to_string()),
-        "meson" => Some("meson.build".to_string()),
-        "ninja" => Some("build.ninja".to_string()),
-        "nimble" => build_dir.join("*.nimble").exists().then(|| "*.nimble".to_string()),
-        "stack" => Some("stack.yaml".to_string()),
-        _ => None,
-    };
-
-    if !yes {
-        if let Some(file) = &build_file {
-            let file_path = if file == "*.nimble" {
-                let nimble_files: Vec<_> = fs::read_dir(&build_dir)
-                    .unwrap()
-                    .filter_map(|e| e.ok())
-                    .map(|e| e.path())
-                    .filter(|p| p.extension().map(|e| e == "nimble").unwrap_or(false))
-                    .collect();
-                if nimble_files.is_empty() {
-                    None
+// the failure menu, for a terminal only: true to run the failed phase again. quitting, or any
+// run that can't answer, fails the install as before
+fn retry_failed(package: &str, message: &str, build_dir: &Path, build_env: &BTreeMap<String, String>, args: &InstallArgs) -> bool {
+    build_failed(package, message);
+    if args.answers_yes() || args.dry_run || !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return false;
+    }
+    loop {
+        let input = utils::prompt("~> [r]etry, [s]hell in the build directory, [l]og or [q]uit?");
+        match input.to_lowercase().as_str() {
+            "r" | "retry" => {
+                println!("~> Retrying the failed step of {}", package);
+                build_log::start(package);
+                return true;
+            }
+            "s" | "shell" => {
+                println!("~> Starting a shell in {}; exit it to come back to this menu", build_dir.display());
+                utils::shell(build_dir, build_env);
+            }
+            "l" | "log" => {
+                let log = build_log::path(package);
+                if log.is_file() {
+                    utils::page_file(&log);
                 } else {
-                    nimble_files.get(0).cloned()
-                }
-            } else {
-                Some(build_dir.join(file))
-            };
-
-            if let Some(file_path) = file_path {
-                if file_path.exists() {
-                    println!("~> Build file: {}", file);
-                    let status = Command::new("less")
-                        .arg(&file_path)
-                        .status();
-
-                    if status.is_err() || !status.unwrap().success() {
-                        let _ = Command::new("cat")
-                            .arg(&file_path)
-                            .status();
-                    }
-
-                    print!("~> Proceed with build? [Y/n] ");
-                    io::stdout().flush().unwrap();
-                    let mut input = String::new();
-                    io::stdin().read_line(&mut input).unwrap();
-
-                    if input.trim().eq_ignore_ascii_case("n") {
-                        println!("{}", Yellow.paint("Build cancelled by user"));
-                        return;
-                    }
+                    println!("~> No build log was written for {}", package);
                 }
             }
+            _ => return false,
         }
     }
+}
 
-    println!("~> Building with flags: {:?}", final_flags);
-    let build_status = match build_system.as_str() {
-        "make" => {
-            let makefile = makefiles.iter()
-                .find(|f| build_dir.join(f).exists())
-                .unwrap_or(&"Makefile");
-
-            let mut cmd = Command::new("make");
-            cmd.arg("-f").arg(makefile)
-                .args(&final_flags)
-                .current_dir(&build_dir)
-                .stdout(Stdio::null());
-
-            cmd.status().expect("Make command failed")
-        }
-        "autotools" => {
-            let configure_status = Command::new("./configure")
-                .args(&final_flags)
-                .current_dir(&build_dir)
-                .status()
-                .expect("Configure command failed");
-            
-            if !configure_status.success() {
-                eprintln!("{}", Red.paint("Configure failed"));
-                return;
-            }
-            
-            Command::new("make")
-                .current_dir(&build_dir)
-                .stdout(Stdio::null())
-                .status()
-                .expect("Make command failed")
-        }
-        "cmake" => {
-            let cmake_build_dir = build_dir.join("build");
-            fs::create_dir_all(&cmake_build_dir).expect("Failed to create build dir");
-
-            let mut cmake_cmd = Command::new("cmake");
-            cmake_cmd
-                .arg("-DCMAKE_BUILD_TYPE=Release")
-                .args(&final_flags)
-                .arg("..")
-                .current_dir(&cmake_build_dir)
-                .stdout(Stdio::null());
-
-            let cmake_status = cmake_cmd.status();
-
-            if cmake_status.is_err() || !cmake_status.as_ref().unwrap().success() {
-                Command::new("cmake")
-                    .args(&final_flags)
-                    .arg("..")
-                    .current_dir(&cmake_build_dir)
-                    .stdout(Stdio::null())
-                    .status()
-                    .expect("CMake command failed")
-            } else {
-                cmake_status.unwrap()
-            }
-        }
-        "cargo" => {
-            let mut cargo_cmd = Command::new("cargo");
-            cargo_cmd
-                .arg("build")
-                .arg("--release")
-                .args(&final_flags)
-                .arg("--manifest-path")
-                .arg(build_dir.join("Cargo.toml"))
-                .arg("--target-dir")
-                .arg(build_dir.join("target"))
-                .current_dir(&build_dir)
-                .stdout(Stdio::null());
-
-            cargo_cmd.status().expect("Cargo command failed")
-        }
-        "meson" => {
-            let meson_build_dir = build_dir.join("build");
-            fs::create_dir_all(&meson_build_dir).expect("Failed to create build dir");
-
-            let meson_status = Command::new("meson")
-                .arg("setup")
-                .args(&final_flags)
-                .arg(&meson_build_dir)
-                .current_dir(&build_dir)
-                .stdout(Stdio::null())
-                .status();
-
-            if meson_status.is_err() || !meson_status.as_ref().unwrap().success() {
-                Command::new("meson")
-                    .arg(&meson_build_dir)
-                    .current_dir(&build_dir)
-                    .stdout(Stdio::null())
-                    .status()
-                    .expect("Meson setup failed");
-            }
-
-            Command::new("ninja")
-                .arg("-C")
-                .arg(&meson_build_dir)
-                .stdout(Stdio::null())
-                .status()
-                .expect("Ninja build failed")
-        }
-        "ninja" => {
-            Command::new("ninja")
-                .args(&final_flags)
-                .current_dir(&build_dir)
-                .stdout(Stdio::null())
-                .status()
-                .expect("Ninja build failed")
-        }
-        "nimble" => {
-            Command::new("nimble")
-                .arg("build")
-                .args(&final_flags)
-                .current_dir(&build_dir)
-                .stdout(Stdio::null())
-                .status()
-                .expect("Nimble command failed")
-        }
-        "stack" => {
-            Command::new("stack")
-                .arg("install")
-                .args(&final_flags)
-                .arg("--local-bin-path")
-                .arg(build_dir.join("bin"))
-                .current_dir(&build_dir)
-                .stdout(Stdio::null())
-                .status()
-                .expect("Stack command failed")
-        }
-        _ => unreachable!()
-    };
-
-    if !build_status.success() {
-        eprintln!("{}", Red.paint("Build failed"));
+fn build_failed(package: &str, message: &str) {
+    eprintln!("{}", Red.paint(message));
+    explain_failure(package);
+}
+
+// points at the captured output, with a hint when it shows a well-known missing dependency
+fn explain_failure(package: &str) {
+    build_log::finish();
+    let path = build_log::path(package);
+    if !path.is_file() {
         return;
     }
+    let output = build_log::read(package);
+    if sandbox::wanted_network(&output) {
+        eprintln!(
+            "{} The build tried to reach the network, which --sandbox-no-net blocks; build with --sandbox instead",
+            Red.paint("Error:")
+        );
+    }
+    for suggestion in hints::suggestions(&output, distro::package_manager()) {
+        println!("{} {}", Yellow.paint("~> Hint:"), suggestion);
+    }
+    println!("~> Build log: {}", path.display());
+}
 
-    println!("~> Installing...");
-    let bin_path = find_binary_path(&build_dir, repo, &build_system);
+fn python_has_pip() -> bool {
+    Command::new("python3")
+        .arg("-m")
+        .arg("pip")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .run_always()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
 
-    if bin_path.is_none() {
-        eprintln!("{}: Failed to find built binary", Red.paint("Error"));
-        return;
+fn self_install_dir(build_system: BuildSystem) -> PathBuf {
+    match build_system {
+        BuildSystem::Python => env::var_os("PIPX_BIN_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| paths::home_dir().join(".local/bin")),
+        BuildSystem::Node => paths::home_dir().join(".local/bin"),
+        _ => paths::bin_dir(),
     }
-    let bin_path = bin_path.unwrap();
+}
+
+fn self_installed_files(build_dir: &Path, build_system: BuildSystem, dest: &Path) -> Vec<PathBuf> {
+    let names = match build_system {
+        BuildSystem::Python => build_system::python_scripts(build_dir),
+        BuildSystem::Node => build_system::node_bins(build_dir),
+        _ => vec![],
+    };
 
-    let home = env::var("HOME").expect("HOME environment variable not set");
-    let dest = PathBuf::from(home).join(".local/bin");
-    if !dest.exists() {
-        fs::create_dir_all(&dest).expect("Failed to create local bin directory");
+    if names.is_empty() {
+        println!("{} Could not determine which executables were installed", Yellow.paint("~> Warning:"));
+    } else {
+        println!("~> Entry points: {}", names.join(", "));
     }
 
-    let bin_name = bin_path.file_name().unwrap().to_str().unwrap();
-    let dest_path = dest.join(bin_name);
+    names.iter().map(|name| dest.join(name)).collect()
+}
 
-    fs::copy(&bin_path, &dest_path)
-        .expect("Failed to copy binary to local directory");
+fn select_binaries(build_dir: &Path, binaries: Vec<PathBuf>, build_system: BuildSystem) -> Vec<PathBuf> {
+    let targets = match build_system {
+        BuildSystem::Stack => stack_exe_targets(build_dir),
+        _ => vec![],
+    };
 
-    println!(
-        "{}",
-        Green.paint("Installed to ~/.local/bin. Make sure this directory is in your PATH.")
-    );
+    println!("~> The build produced several executables:");
+    for (i, bin) in binaries.iter().enumerate() {
+        let name = bin.file_name().unwrap().to_string_lossy();
+        match targets.iter().find(|t| t.ends_with(&format!(":exe:{}", name))) {
+            Some(target) => println!("   {}) {} ({})", i + 1, name, target),
+            None => println!("   {}) {}", i + 1, name),
+        }
+    }
 
-    println!("{} in {}s", Green.paint("~> INSTALL FINISHED"), start.elapsed().as_secs());
+    let input = utils::prompt("~> Install which executables? [A]ll or numbers separated by spaces:");
+    if input.is_empty() || input.eq_ignore_ascii_case("a") {
+        return binaries;
+    }
+
+    input
+        .split_whitespace()
+        .filter_map(|n| n.parse::<usize>().ok())
+        .filter_map(|n| n.checked_sub(1).and_then(|i| binaries.get(i).cloned()))
+        .collect()
 }
 
-fn parse_aurora_json(path: &Path) -> (String, Vec<String>) {
-    let file = std::fs::File::open(path).expect("Failed to open aurora.json");
-    let reader = std::io::BufReader::new(file);
-    let json: serde_json::Value = serde_json::from_reader(reader).expect("Invalid aurora.json");
+fn swift_executable_products(build_dir: &Path) -> Vec<String> {
+    let output = match Command::new("swift")
+        .arg("package")
+        .arg("describe")
+        .arg("--type")
+        .arg("json")
+        .current_dir(build_dir)
+        .stderr(Stdio::null())
+        .output_always()
+    {
+        Ok(output) if output.status.success() => output,
+        _ => return vec![],
+    };
+    executable_products(&output.stdout)
+}
 
-    let build_system = json["build_system"]
-        .as_str()
-        .unwrap_or("make")
-        .to_string();
+// the executables in the output of `swift package describe --type json`
+fn executable_products(description: &[u8]) -> Vec<String> {
+    let json: serde_json::Value = match serde_json::from_slice(description) {
+        Ok(v) => v,
+        Err(_) => return vec![],
+    };
 
-    let flags = json["flags"]
+    // libraries are described as {"library": [...]}, executables as {"executable": null}
+    json["products"]
         .as_array()
-        .map(|arr| {
-            arr.iter()
-                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+        .map(|products| {
+            products
+                .iter()
+                .filter(|p| p["type"].get("executable").is_some())
+                .filter_map(|p| p["name"].as_str().map(|s| s.to_string()))
                 .collect()
         })
-        .unwrap_or_default();
+        .unwrap_or_default()
+}
+
+fn bazel_targets(manifest: &AuroraManifest) -> Vec<String> {
+    if manifest.target.is_empty() {
+        vec!["//:all".to_string()]
+    } else {
+        manifest.target.clone()
+    }
+}
+
+fn bazel_outputs(build_dir: &Path, targets: &[String]) -> Vec<PathBuf> {
+    // `cquery` takes a single expression, so several targets become a union
+    let output = Command::new("bazel")
+        .arg("cquery")
+        .arg("--output=files")
+        .arg(targets.join(" + "))
+        .current_dir(build_dir)
+        .stderr(Stdio::null())
+        .output_always();
+
+    let mut outputs: Vec<PathBuf> = match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|l| build_dir.join(l.trim()))
+            .filter(|p| p.is_file() && utils::is_executable(p))
+            .collect(),
+        _ => vec![],
+    };
+
+    if outputs.is_empty() {
+        // bazel-bin is a symlink into the output base; scan it directly
+        outputs = executables_in(&build_dir.join("bazel-bin"));
+    }
+    outputs
+}
+
+fn new_executables(build_dir: &Path, before_build: &BTreeMap<PathBuf, SystemTime>) -> Vec<PathBuf> {
+    utils::changed_files(build_dir, before_build)
+        .into_iter()
+        .filter(|p| !p.starts_with(build_dir.join(".git")))
+        .filter(|p| {
+            let name = p.file_name().unwrap_or_default().to_string_lossy();
+            !(name.ends_with(".so") || name.contains(".so.") || name.ends_with(".dylib") || name.ends_with(".o"))
+        })
+        .filter(|p| utils::is_executable(p))
+        .collect()
+}
+
+fn executables_in(dir: &Path) -> Vec<PathBuf> {
+    let mut binaries: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && utils::is_executable(p))
+            .collect(),
+        Err(_) => return vec![],
+    };
+    binaries.sort();
+    binaries
+}
+
+fn stack_exe_targets(build_dir: &Path) -> Vec<String> {
+    let output = match Command::new("stack")
+        .arg("ide")
+        .arg("targets")
+        .current_dir(build_dir)
+        .output_always()
+    {
+        Ok(output) => output,
+        Err(_) => return vec![],
+    };
+
+    // stack prints the target list to stderr
+    String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .chain(String::from_utf8_lossy(&output.stdout).lines())
+        .filter(|l| l.contains(":exe:"))
+        .map(|l| l.trim().to_string())
+        .collect()
+}
+
+fn describe_existing_file(path: &Path) {
+    if let Ok(meta) = fs::metadata(path) {
+        let modified = meta.modified()
+            .ok()
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .map(|d| utils::format_timestamp(d.as_secs()))
+            .unwrap_or_else(|| "unknown".to_string());
+        println!("   size: {}, modified: {}", utils::format_size(meta.len()), modified);
+    }
+}
+
+fn backup_existing_file(path: &Path) -> io::Result<()> {
+    let backup_path = backup_file(path, &state::state_dir().join("backups"))?;
+    println!("~> Backed up existing file to {}", backup_path.display());
+    Ok(())
+}
+
+// copies `path` into `backups` under its name and the time
+fn backup_file(path: &Path, backups: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(backups)?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let backup_path = backups.join(format!("{}.{}", name, utils::unix_now()));
+    fs::copy(path, &backup_path)?;
+    Ok(backup_path)
+}
+
+// the repository's (or registry's) manifest with the local recipe laid over it
+fn load_manifest(path: Option<&Path>, local_recipe: Option<&Path>) -> Option<(AuroraManifest, Vec<String>)> {
+    let (manifest, local_fields, source) = match (path, local_recipe) {
+        (None, None) => return Some((AuroraManifest::default(), vec![])),
+        (Some(path), None) => match AuroraManifest::load(path) {
+            Ok(manifest) => (manifest, vec![], path),
+            Err(e) => return invalid_manifest(path, e),
+        },
+        (base, Some(local)) => {
+            let mut value = match base {
+                Some(base) => match AuroraManifest::read_value(base) {
+                    Ok(value) => value,
+                    Err(e) => return invalid_manifest(base, e),
+                },
+                None => serde_json::Value::Object(Default::default()),
+            };
+            let overlay = match AuroraManifest::read_value(local) {
+                Ok(value) => value,
+                Err(e) => return invalid_manifest(local, e),
+            };
+            let local_fields = manifest::merge(&mut value, overlay);
+            match AuroraManifest::from_value(value) {
+                Ok(manifest) => (manifest, local_fields, local),
+                Err(e) => return invalid_manifest(local, e),
+            }
+        }
+    };
+
+    for key in manifest.unknown_fields() {
+        println!(
+            "{} Unknown field '{}' in {}",
+            Yellow.paint("~> Warning:"),
+            key,
+            source.file_name().unwrap_or_default().to_string_lossy()
+        );
+    }
+    Some((manifest, local_fields))
+}
+
+fn invalid_manifest<T>(path: &Path, e: String) -> Option<T> {
+    eprintln!("{} Invalid {}: {}", Red.paint("Error:"), path.display(), e);
+    None
+}
 
-    (build_system, flags)
+fn expand_install_cmd(install_cmd: &str, prefix: &Path, destdir: &Path, build_dir: &Path) -> String {
+    install_cmd
+        .replace("$PREFIX", &prefix.to_string_lossy())
+        .replace("$DESTDIR", &destdir.to_string_lossy())
+        .replace("$BUILD_DIR", &build_dir.to_string_lossy())
 }
 
 fn get_cargo_binary_name(build_dir: &Path) -> Option<String> {
@@ -468,64 +3335,464 @@ fn get_cargo_binary_name(build_dir: &Path) -> Option<String> {
     None
 }
 
-fn find_binary_path(build_dir: &Path, repo: &str, build_system: &str) -> Option<PathBuf> {
-    match build_system {
-        "cargo" => {
-            let binary_name = get_cargo_binary_name(build_dir).unwrap_or_else(|| repo.to_string());
-            let release_path = build_dir.join("target/release").join(&binary_name);
-            if release_path.exists() {
-                return Some(release_path);
-            }
-            let debug_path = build_dir.join("target/debug").join(&binary_name);
-            if debug_path.exists() {
-                return Some(debug_path);
-            }
-            None
+fn find_binaries(
+    build_dir: &Path,
+    repo: &str,
+    build_system: BuildSystem,
+    before_build: &BTreeMap<PathBuf, SystemTime>,
+    manifest: &AuroraManifest,
+    cmake_build_dir: &Path,
+    args: &InstallArgs,
+) -> Vec<PathBuf> {
+    let exe = format!("{}{}", repo, env::consts::EXE_SUFFIX);
+    let repo = exe.as_str();
+
+    let found = match build_system {
+        BuildSystem::Cargo => {
+            // cross builds land in target/<triple>/ and carry the target's suffix
+            let (target_dir, suffix) = match &args.target_triple {
+                Some(triple) => (
+                    build_dir.join("target").join(triple),
+                    if triple.contains("windows") { ".exe" } else { "" },
+                ),
+                None => (build_dir.join("target"), env::consts::EXE_SUFFIX),
+            };
+            let binary_name = manifest
+                .binary
+                .clone()
+                .or_else(|| get_cargo_binary_name(build_dir))
+                .map(|name| format!("{}{}", name, suffix))
+                .unwrap_or_else(|| repo.to_string());
+            let profiles = if args.profile == "debug" { ["debug", "release"] } else { ["release", "debug"] };
+            profiles
+                .iter()
+                .map(|profile| target_dir.join(profile).join(&binary_name))
+                .find(|p| p.exists())
         },
-        "make" | "autotools" | "ninja" => {
+        BuildSystem::Make | BuildSystem::Autotools | BuildSystem::Ninja => {
             let path = build_dir.join(repo);
             if path.exists() { Some(path) } else { None }
         },
-        "cmake" => {
-            let path = build_dir.join("build").join(repo);
-            if path.exists() { Some(path) } else { None }
+        BuildSystem::CMake => {
+            let path = cmake_build_dir.join(repo);
+            let config = if args.profile == "debug" { "Debug" } else { "Release" };
+            let multi_config_path = cmake_build_dir.join(config).join(repo);
+            if path.exists() {
+                Some(path)
+            } else if multi_config_path.exists() {
+                Some(multi_config_path)
+            } else {
+                None
+            }
         },
-        "meson" => {
+        BuildSystem::Meson => {
             let build_output_dir = build_dir.join("build");
             find_executable_in_dir(&build_output_dir, repo)
         },
-        "nimble" => {
+        BuildSystem::Nimble => {
             let path = build_dir.join(repo);
             if path.exists() { Some(path) } else { None }
         },
-        "stack" => {
+        BuildSystem::Zig => return executables_in(&build_dir.join("zig-out/bin")),
+        BuildSystem::Stack | BuildSystem::Go => return executables_in(&build_dir.join("bin")),
+        BuildSystem::Cabal => return executables_in(&build_dir.join("aurora-staging")),
+        BuildSystem::Rebar3 => return executables_in(&build_dir.join("_build/default/bin")),
+        BuildSystem::Mix => return new_executables(build_dir, before_build),
+        BuildSystem::Python
+        | BuildSystem::Node
+        | BuildSystem::Gradle
+        | BuildSystem::Maven
+        | BuildSystem::Waf
+        | BuildSystem::Dune
+        | BuildSystem::Gleam
+        | BuildSystem::XMake => None,
+        BuildSystem::QMake => {
+            let target = build_system::find_file_with_extension(build_dir, "pro")
+                .and_then(|pro| {
+                    build_system::qmake_target(&pro)
+                        .or_else(|| pro.file_stem().map(|s| s.to_string_lossy().into_owned()))
+                })
+                .map(|t| format!("{}{}", t, env::consts::EXE_SUFFIX))
+                .unwrap_or_else(|| repo.to_string());
+            find_executable_in_dir(build_dir, &target)
+        }
+        BuildSystem::SCons => return new_executables(build_dir, before_build),
+        BuildSystem::Swift => {
+            let release_dir = build_dir.join(".build").join(&args.profile);
+            let products = swift_executable_products(build_dir);
+            if products.is_empty() {
+                return vec![];
+            }
+            return products
+                .iter()
+                .map(|p| release_dir.join(format!("{}{}", p, env::consts::EXE_SUFFIX)))
+                .filter(|p| p.is_file())
+                .collect();
+        }
+        BuildSystem::Dub => {
+            let target = build_system::dub_target(build_dir).unwrap_or_else(|| repo.to_string());
+            let path = build_dir.join(format!("{}{}", target, env::consts::EXE_SUFFIX));
+            if path.is_file() { Some(path) } else { None }
+        }
+        BuildSystem::Crystal => {
             let bin_dir = build_dir.join("bin");
-            if bin_dir.exists() {
-                find_executable_in_dir(&bin_dir, repo)
-            } else {
-                None
+            let targets = build_system::shard_targets(build_dir);
+            if targets.is_empty() {
+                return executables_in(&bin_dir);
             }
-        },
-        _ => None
-    }
+            return targets
+                .iter()
+                .map(|t| bin_dir.join(format!("{}{}", t, env::consts::EXE_SUFFIX)))
+                .filter(|p| p.is_file())
+                .collect();
+        }
+        BuildSystem::Premake => {
+            let built = new_executables(build_dir, before_build);
+            let (in_bin, elsewhere): (Vec<PathBuf>, Vec<PathBuf>) = built
+                .into_iter()
+                .partition(|p| p.starts_with(build_dir.join("bin")));
+            return if in_bin.is_empty() { elsewhere } else { in_bin };
+        }
+        BuildSystem::Bazel => return bazel_outputs(build_dir, &bazel_targets(manifest)),
+        BuildSystem::Just => ["", "bin", "build", "out", "dist", "target/release"]
+            .iter()
+            .map(|dir| build_dir.join(dir).join(repo))
+            .find(|p| p.is_file() && utils::is_executable(p)),
+    };
+
+    found.into_iter().collect()
 }
 
+// limits on the search through the build tree, which can be huge (target/, node_modules) and
+// contain symlink loops
+const SEARCH_MAX_DEPTH: usize = 10;
+const SEARCH_MAX_ENTRIES: usize = 100_000;
+const SEARCH_SKIPPED: [&str; 5] = [".git", "node_modules", "CMakeFiles", "target/debug/deps", "target/release/deps"];
+
 fn find_executable_in_dir(dir: &Path, name: &str) -> Option<PathBuf> {
-    if let Ok(entries) = fs::read_dir(dir) {
-        for entry in entries.filter_map(|e| e.ok()) {
-            let path = entry.path();
-            if path.is_dir() {
-                if let Some(exec) = find_executable_in_dir(&path, name) {
+    let mut examined = 0;
+    let found = search_dir(dir, name, 0, &mut examined);
+    if found.is_none() && examined >= SEARCH_MAX_ENTRIES {
+        println!(
+            "{} Stopped looking for {} after {} files in {}",
+            Yellow.paint("~> Warning:"),
+            name,
+            SEARCH_MAX_ENTRIES,
+            dir.display()
+        );
+    }
+    found
+}
+
+fn search_dir(dir: &Path, name: &str, depth: usize, examined: &mut usize) -> Option<PathBuf> {
+    let entries = fs::read_dir(dir).ok()?;
+    for entry in entries.filter_map(|e| e.ok()) {
+        *examined += 1;
+        if *examined >= SEARCH_MAX_ENTRIES {
+            return None;
+        }
+        let path = entry.path();
+        // file_type doesn't follow symlinks, so linked directories are never entered
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            if depth < SEARCH_MAX_DEPTH && !SEARCH_SKIPPED.iter().any(|skipped| path.ends_with(skipped)) {
+                if let Some(exec) = search_dir(&path, name, depth + 1, examined) {
                     return Some(exec);
                 }
-            } else if path.is_file() {
-                if let Some(filename) = path.file_name() {
-                    if filename == name {
-                        return Some(path);
-                    }
-                }
             }
+        } else if path.is_file() && entry.file_name() == name && utils::is_executable(&path) {
+            return Some(path);
         }
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use super::*;
+
+    #[cfg(unix)]
+    fn script(path: &Path) {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn search_ends_in_a_symlink_loop() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("sub/loop")).unwrap();
+        std::os::unix::fs::symlink("..", dir.path().join("sub/parent")).unwrap();
+
+        let mut examined = 0;
+        assert_eq!(search_dir(dir.path(), "tool", 0, &mut examined), None);
+        assert_eq!(examined, 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn search_skips_linked_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        script(&outside.path().join("tool"));
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("linked")).unwrap();
+
+        let mut examined = 0;
+        assert_eq!(search_dir(dir.path(), "tool", 0, &mut examined), None);
+        script(&dir.path().join("bin/tool"));
+        assert_eq!(search_dir(dir.path(), "tool", 0, &mut 0), Some(dir.path().join("bin/tool")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn search_stops_at_the_entry_cap() {
+        // a/, a/b/ and a/b/tool are one entry each, and the cap is reached on the last one
+        let dir = tempfile::tempdir().unwrap();
+        script(&dir.path().join("a/b/tool"));
+
+        let mut examined = SEARCH_MAX_ENTRIES - 4;
+        assert_eq!(search_dir(dir.path(), "tool", 0, &mut examined), Some(dir.path().join("a/b/tool")));
+        let mut examined = SEARCH_MAX_ENTRIES - 3;
+        assert_eq!(search_dir(dir.path(), "tool", 0, &mut examined), None);
+        assert_eq!(examined, SEARCH_MAX_ENTRIES);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn search_stops_at_the_depth_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut deep = dir.path().to_path_buf();
+        for _ in 0..=SEARCH_MAX_DEPTH {
+            deep.push("d");
+        }
+        script(&deep.join("tool"));
+        assert_eq!(search_dir(dir.path(), "tool", 0, &mut 0), None);
+        assert_eq!(search_dir(&dir.path().join("d"), "tool", 0, &mut 0), Some(deep.join("tool")));
+    }
+
+    fn args(argv: &[&str]) -> InstallArgs {
+        InstallArgs::try_parse_from(["install"].iter().chain(argv)).unwrap()
+    }
+
+    #[test]
+    fn flags_are_split_per_package() {
+        let flags = package_flags(&args(&["foo=-DFOO=1,-GNinja", "bar", "baz=", "--pkg-flag", "bar:--verbose", "--pkg-flag=foo:-DX=a:b"])).unwrap();
+        assert_eq!(
+            flags,
+            [
+                ("foo".to_string(), vec!["-DFOO=1".to_string(), "-GNinja".to_string(), "-DX=a:b".to_string()]),
+                ("bar".to_string(), vec!["--verbose".to_string()]),
+                ("baz".to_string(), vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn package_flags_come_after_the_global_ones() {
+        let flags = package_flags(&args(&["foo=-DFOO=2", "bar", "-f=-DFOO=1", "--pkg-flag", "bar:-DFOO=3", "--flags=-GNinja"])).unwrap();
+        assert_eq!(flags[0].1, ["-DFOO=1", "-GNinja", "-DFOO=2"]);
+        assert_eq!(flags[1].1, ["-DFOO=1", "-GNinja", "-DFOO=3"]);
+    }
+
+    #[test]
+    fn pkg_flags_need_a_package_being_installed() {
+        let error = package_flags(&args(&["foo", "--pkg-flag", "bar:-DFOO=1"])).unwrap_err();
+        assert_eq!(error, "--pkg-flag refers to 'bar', which is not being installed");
+        let error = package_flags(&args(&["foo", "--pkg-flag=-DFOO=1"])).unwrap_err();
+        assert_eq!(error, "--pkg-flag expects <package>:<flag>, got '-DFOO=1'");
+    }
+
+    #[test]
+    fn nimble_binaries_are_named_after_the_nimble_file() {
+        let manifest = AuroraManifest::default();
+        let nimble = Path::new("/build/foo-git/foo.nimble");
+        assert_eq!(binary_name(&manifest, BuildSystem::Nimble, Some(nimble), "foo-git"), "foo");
+        assert_eq!(binary_name(&manifest, BuildSystem::Nimble, None, "foo-git"), "foo-git");
+        assert_eq!(binary_name(&manifest, BuildSystem::Make, Some(Path::new("/build/foo-git/Makefile")), "foo-git"), "foo-git");
+        let manifest = AuroraManifest { binary: Some("bar".to_string()), ..AuroraManifest::default() };
+        assert_eq!(binary_name(&manifest, BuildSystem::Nimble, Some(nimble), "foo-git"), "bar");
+    }
+
+    fn binaries(build_system: BuildSystem, build_dir: &Path, repo: &str) -> Vec<PathBuf> {
+        let args = args(&[repo]);
+        find_binaries(build_dir, repo, build_system, &BTreeMap::new(), &AuroraManifest::default(), &build_dir.join("build"), &args)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn zig_binaries_are_everything_in_zig_out_bin() {
+        let dir = tempfile::tempdir().unwrap();
+        script(&dir.path().join("zig-out/bin/foo"));
+        script(&dir.path().join("zig-out/bin/foo-helper"));
+        fs::write(dir.path().join("zig-out/bin/README"), "").unwrap();
+        script(&dir.path().join("foo"));
+        assert_eq!(
+            binaries(BuildSystem::Zig, dir.path(), "foo"),
+            [dir.path().join("zig-out/bin/foo"), dir.path().join("zig-out/bin/foo-helper")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn just_binaries_are_looked_for_in_the_usual_places() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(binaries(BuildSystem::Just, dir.path(), "foo").is_empty());
+        script(&dir.path().join("target/release/foo"));
+        assert_eq!(binaries(BuildSystem::Just, dir.path(), "foo"), [dir.path().join("target/release/foo")]);
+        script(&dir.path().join("bin/foo"));
+        assert_eq!(binaries(BuildSystem::Just, dir.path(), "foo"), [dir.path().join("bin/foo")]);
+        // a directory with the name is not the binary
+        fs::create_dir(dir.path().join("foo")).unwrap();
+        assert_eq!(binaries(BuildSystem::Just, dir.path(), "foo"), [dir.path().join("bin/foo")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn scons_binaries_are_what_the_build_added() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("SConstruct"), "Program('foo.c')\n").unwrap();
+        script(&dir.path().join("tools/helper"));
+        let before = utils::snapshot_tree(dir.path());
+
+        for built in ["foo", "build/bar", "libfoo.so", "libfoo.so.1", "foo.o", ".git/hooks/post-commit"] {
+            script(&dir.path().join(built));
+        }
+        fs::write(dir.path().join("foo.os"), "").unwrap();
+        let args = args(&["foo"]);
+        let found = find_binaries(dir.path(), "foo", BuildSystem::SCons, &before, &AuroraManifest::default(), dir.path(), &args);
+        assert_eq!(found, [dir.path().join("build/bar"), dir.path().join("foo")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dub_binaries_come_from_the_dub_target() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("dub.json"), r#"{ "name": "tool", "targetPath": "bin" }"#).unwrap();
+        assert!(binaries(BuildSystem::Dub, dir.path(), "tool-git").is_empty());
+        script(&dir.path().join("bin/tool"));
+        assert_eq!(binaries(BuildSystem::Dub, dir.path(), "tool-git"), [dir.path().join("bin/tool")]);
+    }
+
+    #[test]
+    fn only_executable_swift_products_are_installed() {
+        let description = r#"{
+            "name": "tool",
+            "products": [
+                { "name": "tool", "targets": ["tool"], "type": { "executable": null } },
+                { "name": "ToolKit", "targets": ["ToolKit"], "type": { "library": ["automatic"] } },
+                { "name": "tool-helper", "targets": ["helper"], "type": { "executable": null } },
+                { "name": "ToolPlugin", "targets": ["plugin"], "type": { "plugin": null } }
+            ]
+        }"#;
+        assert_eq!(executable_products(description.as_bytes()), ["tool", "tool-helper"]);
+        assert!(executable_products(br#"{ "products": [] }"#).is_empty());
+        assert!(executable_products(b"error: no Package.swift").is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rebar3_escripts_come_from_the_default_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        script(&dir.path().join("_build/test/bin/tool"));
+        assert!(binaries(BuildSystem::Rebar3, dir.path(), "tool").is_empty());
+        script(&dir.path().join("_build/default/bin/tool"));
+        assert_eq!(binaries(BuildSystem::Rebar3, dir.path(), "tool"), [dir.path().join("_build/default/bin/tool")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn qmake_binaries_are_named_by_the_target() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("tool.pro"), "TARGET = viewer\n").unwrap();
+        script(&dir.path().join("tool"));
+        assert!(binaries(BuildSystem::QMake, dir.path(), "tool-git").is_empty());
+        script(&dir.path().join("release/viewer"));
+        assert_eq!(binaries(BuildSystem::QMake, dir.path(), "tool-git"), [dir.path().join("release/viewer")]);
+
+        // without a TARGET, qmake names it after the .pro file
+        fs::write(dir.path().join("tool.pro"), "SOURCES += main.cpp\n").unwrap();
+        assert_eq!(binaries(BuildSystem::QMake, dir.path(), "tool-git"), [dir.path().join("tool")]);
+    }
+
+    #[test]
+    fn backups_copy_the_file_or_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("tool");
+        fs::write(&file, "old").unwrap();
+        let backup = backup_file(&file, &dir.path().join("backups")).unwrap();
+        assert_eq!(fs::read_to_string(backup).unwrap(), "old");
+        assert_eq!(fs::read_to_string(&file).unwrap(), "old");
+
+        assert!(backup_file(&dir.path().join("gone"), &dir.path().join("backups")).is_err());
+        // the backup directory can't be created over a file
+        assert!(backup_file(&file, &file.join("backups")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copies_replace_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let (source, target, dest) = (dir.path().join("new"), dir.path().join("target"), dir.path().join("tool"));
+        fs::write(&source, "new").unwrap();
+        fs::write(&target, "old").unwrap();
+        std::os::unix::fs::symlink(&target, &dest).unwrap();
+        copy_over(&source, &dest).unwrap();
+        assert!(!fs::symlink_metadata(&dest).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "new");
+        assert_eq!(fs::read_to_string(&target).unwrap(), "old");
+
+        let dangling = dir.path().join("dangling");
+        std::os::unix::fs::symlink(dir.path().join("gone"), &dangling).unwrap();
+        copy_over(&source, &dangling).unwrap();
+        assert_eq!(fs::read_to_string(&dangling).unwrap(), "new");
+        assert!(copy_over(&dir.path().join("gone"), &dir.path().join("other")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn bazel_binaries_fall_back_to_bazel_bin() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("MODULE.bazel"), "").unwrap();
+        script(&dir.path().join("bazel-bin/tool"));
+        fs::write(dir.path().join("bazel-bin/tool.params"), "").unwrap();
+        // outside a bazel workspace there is nothing cquery can answer
+        assert_eq!(bazel_outputs(dir.path(), &bazel_targets(&AuroraManifest::default())), [dir.path().join("bazel-bin/tool")]);
+        assert_eq!(bazel_targets(&AuroraManifest::default()), ["//:all"]);
+    }
+
+    fn preset(build_dir: &Path, requested: Option<&str>, debug: bool) -> Result<Option<String>, ()> {
+        choose_cmake_preset(build_dir, requested, debug).map(|preset| preset.map(|p| p.name))
+    }
+
+    #[test]
+    fn cmake_presets_are_chosen_by_build_type() {
+        let presets = |names: &[&str]| {
+            let configure: Vec<String> = names.iter().map(|name| format!("{{\"name\": \"{}\"}}", name)).collect();
+            let dir = tempfile::tempdir().unwrap();
+            fs::write(dir.path().join("CMakePresets.json"), format!("{{\"configurePresets\": [{}]}}", configure.join(", "))).unwrap();
+            dir
+        };
+
+        let dir = presets(&["default", "debug", "release"]);
+        assert_eq!(preset(dir.path(), None, false), Ok(Some("release".to_string())));
+        assert_eq!(preset(dir.path(), None, true), Ok(Some("debug".to_string())));
+        assert_eq!(preset(dir.path(), Some("default"), false), Ok(Some("default".to_string())));
+        assert_eq!(preset(dir.path(), Some("ci"), false), Err(()));
+
+        let dir = presets(&["Release", "default"]);
+        assert_eq!(preset(dir.path(), None, false), Ok(Some("Release".to_string())));
+        assert_eq!(preset(dir.path(), None, true), Ok(Some("default".to_string())));
+
+        let dir = presets(&["ci"]);
+        assert_eq!(preset(dir.path(), None, false), Ok(None));
+        assert_eq!(preset(tempfile::tempdir().unwrap().path(), None, false), Ok(None));
+        assert_eq!(preset(tempfile::tempdir().unwrap().path(), Some("release"), false), Err(()));
+    }
+}
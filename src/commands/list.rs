@@ -0,0 +1,99 @@
+use std::process::{Command, Stdio};
+use ansi_term::Colour::{Green, Red, Yellow};
+use crate::distro;
+use crate::runner::Run;
+use crate::state::State;
+use crate::utils;
+
+pub fn list(orphans: bool) {
+    let state = State::load();
+    if orphans {
+        list_orphans(&state);
+        return;
+    }
+
+    if state.packages.is_empty() {
+        println!("~> No packages installed with aurora");
+        return;
+    }
+
+    for (name, record) in &state.packages {
+        let profile = match record.profile.as_deref() {
+            Some(profile) if profile != "release" => format!(", {} build", profile),
+            _ => String::new(),
+        };
+        let pinned = match &record.pinned {
+            Some(commit) => format!(", {} at {}", Yellow.paint("pinned"), utils::short_commit(commit)),
+            None => String::new(),
+        };
+        let version = record.version.as_deref().map(|v| format!(" {}", version_number(v))).unwrap_or_default();
+        println!(
+            "{}{} ({}{}, installed {}{})",
+            Green.bold().paint(name),
+            version,
+            record.build_system,
+            profile,
+            utils::format_timestamp(record.installed_at),
+            pinned
+        );
+
+        for file in &record.files {
+            println!("   {}", file.display());
+        }
+
+        for conflict in &record.conflicts {
+            println!(
+                "   {} {} is shared with '{}'",
+                Red.bold().paint("conflict:"),
+                conflict.path.display(),
+                conflict.package
+            );
+        }
+    }
+}
+
+// "ripgrep 14.1.0 (rev abc)" -> "14.1.0"; lines without anything that looks like a version are
+// shown whole
+fn version_number(line: &str) -> &str {
+    line.split_whitespace()
+        .find(|word| word.trim_start_matches('v').starts_with(|c: char| c.is_ascii_digit()))
+        .unwrap_or(line)
+}
+
+// dependencies whose dependents are all gone, from aurora's database and, on arch, pacman's
+// foreign packages
+fn list_orphans(state: &State) {
+    let parents: Vec<String> = state.packages.values().flat_map(|r| r.required_by.iter().cloned()).collect();
+    let from_pacman = distro::installed(&parents);
+    let mut orphans: Vec<String> = state
+        .packages
+        .iter()
+        .filter(|(_, record)| !record.explicit)
+        .filter(|(_, record)| {
+            record
+                .required_by
+                .iter()
+                .all(|parent| !state.packages.contains_key(parent) && !from_pacman.contains(parent))
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    if utils::command_exists("pacman") {
+        if let Ok(output) = Command::new("pacman").arg("-Qdtmq").stderr(Stdio::null()).output_always() {
+            for name in String::from_utf8_lossy(&output.stdout).lines() {
+                if !orphans.iter().any(|o| o == name) {
+                    orphans.push(name.to_string());
+                }
+            }
+        }
+    }
+
+    if orphans.is_empty() {
+        println!("~> No orphaned dependencies");
+        return;
+    }
+    orphans.sort();
+    for name in orphans {
+        println!("{}", name);
+    }
+}
@@ -0,0 +1,16 @@
+use ansi_term::Colour::Red;
+use crate::build_log;
+use crate::utils;
+
+// pages the package's last build log; --raw gives the output as the terminal got it, colors and all
+pub fn logs(package: &str, raw: bool) {
+    let path = match raw {
+        true => build_log::raw_path(package),
+        false => build_log::path(package),
+    };
+    if !path.is_file() {
+        eprintln!("{} No build log for {}", Red.paint("Error:"), package);
+        std::process::exit(1);
+    }
+    utils::page_file(&path);
+}
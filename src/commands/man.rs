@@ -0,0 +1,216 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use ansi_term::Colour::{Green, Red};
+use clap::{Command, CommandFactory};
+use clap_mangen::Man;
+use crate::cli::Cli;
+
+const ENVIRONMENT: [(&str, &str); 6] = [
+    ("RUST_LOG", "Debug and trace output per module, e.g. aurora::aur=trace,debug."),
+    ("PATH", "Searched for build tools and checked for the install directory."),
+    ("JAVA_HOME", "Used by gradle, maven and sbt builds; a warning is printed when it is missing."),
+    ("PIPX_BIN_DIR", "Where pipx puts python applications."),
+    ("SUDO_USER", "When run through sudo, builds run as this user."),
+    ("DISPLAY, WAYLAND_DISPLAY", "A graphical session, needed for desktop notifications."),
+];
+
+const CONFIG_KEYS: [(&str, &str); 29] = [
+    ("dest", "Install directory for binaries."),
+    ("goflags", "GOFLAGS for go builds."),
+    ("build_priority", "Build systems to prefer when several are present."),
+    ("node_package_manager", "npm, pnpm, yarn or bun."),
+    ("env", "Environment variables for every build."),
+    ("registry", "URL of a recipe registry."),
+    ("compiler_cache", "ccache, sccache or auto."),
+    ("selector", "fzf to pick search results in fzf."),
+    ("notify", "Desktop notifications for installs over a minute."),
+    ("notify_threshold_secs", "Desktop notifications for installs over this many seconds."),
+    ("sign_packages", "Sign packages built with makepkg."),
+    ("gpg_key", "The key to sign packages with."),
+    ("allowed_domains", "Refuse builds that download from any other domain."),
+    ("with_tests", "Packages whose tests always run before they are installed."),
+    ("keep_sudo_alive", "Refresh the sudo timestamp during long builds."),
+    ("ignore_partial_upgrade", "Build on arch without checking for pending updates."),
+    ("prompt_timeout", "Seconds before an unanswered prompt takes its default."),
+    ("clean_env", "Build with a minimal environment, like --clean-env."),
+    ("clean_env_passthrough", "Variables builds with a clean environment still inherit."),
+    ("sandbox_writable", "Directories --sandbox builds may write to."),
+    ("nice", "The niceness builds run at, 19 for the lowest priority."),
+    ("ionice", "Run builds in the idle I/O class."),
+    ("max_load", "Wait before each build while the load average is above this."),
+    ("packager", "Name <email> to put in packages built with makepkg."),
+    ("reproducible", "Build with SOURCE_DATE_EPOCH and without build paths in binaries."),
+    ("[build] env", "Environment variables for every build, after the makepkg.conf ones."),
+    ("[build] inherit_makepkg_conf", "Take CFLAGS, LDFLAGS and friends from makepkg.conf."),
+    ("[rpc] info_ttl", "Seconds AUR package info is cached for."),
+    ("[rpc] search_ttl", "Seconds AUR search results are cached for."),
+];
+
+const PREAMBLE: &str = ".ie \\n(.g .ds Aq \\(aq\n.el .ds Aq '\n";
+
+const EXIT_STATUS: [(&str, &str); 3] = [
+    ("0", "Success."),
+    ("1", "A package failed to build or install, or a lookup failed."),
+    ("2", "Invalid arguments."),
+];
+
+// writes aurora.1 and an aurora-<command>.1 for each subcommand, nested ones included
+pub fn generate_man(out_dir: &Path) {
+    if let Err(e) = fs::create_dir_all(out_dir) {
+        eprintln!("{} Could not create {}: {}", Red.paint("Error:"), out_dir.display(), e);
+        std::process::exit(1);
+    }
+
+    let cli = cli();
+    let mut pages = vec![("aurora.1".to_string(), main_page(&cli))];
+    subcommand_pages(&cli, &mut pages);
+
+    for (name, page) in pages {
+        let path = out_dir.join(&name);
+        if let Err(e) = fs::write(&path, page) {
+            eprintln!("{} Could not write {}: {}", Red.paint("Error:"), path.display(), e);
+            std::process::exit(1);
+        }
+        println!("{} {}", Green.paint("~> Wrote"), path.display());
+    }
+}
+
+// built, so subcommands know their `aurora <command>` names
+fn cli() -> Command {
+    let mut cli = Cli::command().about("builds and installs AUR packages").disable_help_subcommand(true);
+    cli.build();
+    cli
+}
+
+fn subcommand_pages(command: &Command, pages: &mut Vec<(String, String)>) {
+    for subcommand in command.get_subcommands().filter(|c| !c.is_hide_set()) {
+        let man = man(subcommand);
+        pages.push((man.get_filename(), subcommand_page(subcommand, &man)));
+        subcommand_pages(subcommand, pages);
+    }
+}
+
+fn man(command: &Command) -> Man {
+    let name = command.get_display_name().unwrap_or(command.get_name()).to_string();
+    let mut command = command.clone();
+    // the help text lives in comments, so most commands have no about of their own
+    if command.get_about().is_none() {
+        let about = format!("the {} command", command.get_name());
+        command = command.about(about);
+    }
+    Man::new(command).title(name.to_uppercase()).source(format!("aurora {}", env!("CARGO_PKG_VERSION")))
+}
+
+fn main_page(cli: &Command) -> String {
+    let man = man(cli);
+    let mut page = PREAMBLE.to_string();
+    page.push_str(&render(|w| {
+        man.render_title(w)?;
+        man.render_name_section(w)?;
+        man.render_synopsis_section(w)
+    }));
+    page.push_str(".SH DESCRIPTION\nBuilds and installs packages from the AUR with makepkg on Arch, or with the build system the package uses elsewhere. A bare query searches the AUR and offers to install the results.\n");
+    page.push_str(&render(|w| {
+        man.render_options_section(w)?;
+        man.render_subcommands_section(w)
+    }));
+
+    table(&mut page, "ENVIRONMENT", &ENVIRONMENT);
+    page.push_str(".SH FILES\n.TP\n~/.config/aurora/config.toml\nConfiguration, in TOML. The keys are:\n.RS\n");
+    for (key, description) in CONFIG_KEYS {
+        page.push_str(&format!(".TP\n\\fB{}\\fR\n{}\n", escape(key), escape(description)));
+    }
+    page.push_str(".RE\n.TP\n~/.config/aurora/providers.toml\nProviders picked for virtual dependencies.\n");
+    page.push_str(".TP\n~/.local/share/aurora/installed.json\nPackages installed outside the package manager and their files.\n");
+    table(&mut page, "EXIT STATUS", &EXIT_STATUS);
+
+    let see_also: Vec<String> = cli.get_subcommands().filter(|c| !c.is_hide_set()).map(|c| format!("\\fB{}\\fR(1)", escape(&format!("aurora-{}", c.get_name())))).collect();
+    page.push_str(&format!(".SH SEE ALSO\n{}, \\fBmakepkg\\fR(8)\n", see_also.join(", ")));
+    page
+}
+
+fn subcommand_page(command: &Command, man: &Man) -> String {
+    let mut page = PREAMBLE.to_string();
+    page.push_str(&render(|w| {
+        man.render_title(w)?;
+        man.render_name_section(w)?;
+        man.render_synopsis_section(w)?;
+        if command.get_arguments().any(|a| !a.is_hide_set()) {
+            man.render_options_section(w)?;
+        }
+        if command.has_subcommands() {
+            man.render_subcommands_section(w)?;
+        }
+        Ok(())
+    }));
+    table(&mut page, "EXIT STATUS", &EXIT_STATUS);
+    page.push_str(".SH SEE ALSO\n\\fBaurora\\fR(1)\n");
+    page
+}
+
+// the sections clap_mangen renders from the command definition; it starts each one with the
+// same string definitions, which a page only needs once
+fn render(sections: impl FnOnce(&mut dyn Write) -> io::Result<()>) -> String {
+    let mut page = Vec::new();
+    sections(&mut page).expect("Failed to render man page");
+    String::from_utf8_lossy(&page).replace(PREAMBLE, "")
+}
+
+fn table(page: &mut String, section: &str, rows: &[(&str, &str)]) {
+    page.push_str(&format!(".SH {}\n", section));
+    for (name, description) in rows {
+        page.push_str(&format!(".TP\n\\fB{}\\fR\n{}\n", escape(name), escape(description)));
+    }
+}
+
+// roff treats backslashes specially, and dashes and leading dots differently from plain text
+fn escape(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('-', "\\-");
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(name: &str) -> String {
+        let cli = cli();
+        let mut pages = Vec::new();
+        subcommand_pages(&cli, &mut pages);
+        pages.into_iter().find(|(file, _)| file == name).map(|(_, page)| page).expect("no such page")
+    }
+
+    #[test]
+    fn install_synopsis_comes_from_the_cli() {
+        let page = page("aurora-install.1");
+        let synopsis = page.split(".SH SYNOPSIS\n").nth(1).and_then(|rest| rest.lines().next()).unwrap();
+        assert!(synopsis.starts_with("\\fBaurora install\\fR"));
+        for option in ["\\fB\\-\\-dry\\-run\\fR", "\\fB\\-y\\fR|\\fB\\-\\-yes\\fR", "<\\fIPACKAGES\\fR>"] {
+            assert!(synopsis.contains(option), "{} missing from {}", option, synopsis);
+        }
+    }
+
+    #[test]
+    fn nested_subcommands_get_pages() {
+        let page = page("aurora-config-show.1");
+        assert!(page.starts_with(PREAMBLE));
+        assert!(page.contains(".SH SYNOPSIS\n\\fBaurora config show\\fR"));
+        assert_eq!(page.matches(PREAMBLE).count(), 1);
+    }
+
+    #[test]
+    fn main_page_lists_the_config_keys() {
+        let page = main_page(&cli());
+        assert!(page.contains(".SH SYNOPSIS\n\\fBaurora\\fR"));
+        assert!(page.contains("\naurora\\-install(1)\n"));
+        assert!(page.contains("\\fBaurora\\-check\\-updates\\fR(1)"));
+        for (key, _) in CONFIG_KEYS {
+            assert!(page.contains(&format!("\\fB{}\\fR", escape(key))), "{} missing", key);
+        }
+    }
+}
@@ -0,0 +1,23 @@
+pub mod check;
+pub mod clean;
+pub mod comments;
+pub mod config;
+pub mod deps;
+pub mod diff;
+pub mod downgrade;
+pub mod fetch;
+pub mod history;
+pub mod init;
+pub mod install;
+pub mod list;
+pub mod logs;
+pub mod man;
+pub mod pin;
+pub mod preview;
+pub mod rebuild;
+pub mod search;
+pub mod self_update;
+pub mod undo;
+pub mod updates;
+pub mod verify;
+pub mod why;
@@ -0,0 +1,50 @@
+use ansi_term::Colour::{Green, Red, Yellow};
+use crate::state::State;
+use crate::utils;
+
+pub fn pin(package: &str) {
+    let mut state = State::load();
+    let record = match state.packages.get_mut(package) {
+        Some(record) => record,
+        None => {
+            eprintln!("{} {} was not installed with aurora", Red.paint("Error:"), package);
+            std::process::exit(1);
+        }
+    };
+
+    let commit = match &record.commit {
+        Some(commit) => commit.clone(),
+        None => {
+            eprintln!(
+                "{} No commit was recorded when {} was installed; reinstall it before pinning",
+                Red.paint("Error:"),
+                package
+            );
+            std::process::exit(1);
+        }
+    };
+
+    if record.pinned.as_deref() == Some(commit.as_str()) {
+        println!("~> {} is already pinned at {}", package, utils::short_commit(&commit));
+        return;
+    }
+    record.pinned = Some(commit.clone());
+    state.save();
+    println!("{} {} at {}", Green.paint("~> Pinned"), package, utils::short_commit(&commit));
+}
+
+pub fn unpin(package: &str) {
+    let mut state = State::load();
+    match state.packages.get_mut(package) {
+        Some(record) if record.pinned.is_some() => {
+            record.pinned = None;
+            state.save();
+            println!("{} {}", Green.paint("~> Unpinned"), package);
+        }
+        Some(_) => println!("{} {} is not pinned", Yellow.paint("~> Warning:"), package),
+        None => {
+            eprintln!("{} {} was not installed with aurora", Red.paint("Error:"), package);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use std::fs;
+use ansi_term::Colour::Red;
+use crate::aur;
+use crate::paths;
+use crate::utils;
+
+// pages a file from the package's AUR repository without cloning it
+pub fn preview(package: &str, file: &str) {
+    let content = match aur::repo_file(package, file) {
+        Ok(Some(content)) => content,
+        Ok(None) => {
+            // cgit answers 404 both for unknown packages and for files a package doesn't have
+            match aur::info(&[package.to_string()]) {
+                Ok(found) if found.contains_key(package) => {
+                    eprintln!("{} {} has no {} in its AUR repository", Red.paint("Error:"), package, file)
+                }
+                Ok(_) => eprintln!("{} Package '{}' was not found in the AUR", Red.paint("Error:"), package),
+                Err(_) => eprintln!("{} The AUR has no {} for {}", Red.paint("Error:"), file, package),
+            }
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{} Fetching {} for {} failed: {}", Red.paint("Error:"), file, package, e);
+            std::process::exit(1);
+        }
+    };
+
+    // the pager wants a file; it goes in the temp directory, never the build cache
+    let name = file.rsplit('/').next().unwrap_or(file);
+    let path = paths::temp_dir().join(format!("preview-{}-{}", package, name));
+    if fs::create_dir_all(paths::temp_dir()).is_err() || fs::write(&path, &content).is_err() {
+        println!("{}", String::from_utf8_lossy(&content));
+        return;
+    }
+    println!("~> {} of {}:", file, package);
+    utils::page_file(&path);
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use ansi_term::Colour::{Green, Red, Yellow};
+use clap::Parser;
+use crate::cli::InstallArgs;
+use crate::commands::install;
+use crate::distro;
+use crate::runner::Run;
+use crate::state::State;
+use crate::utils;
+
+// finds installed binaries that lost a shared library, usually to a soname bump, and offers to
+// rebuild the packages they came from
+pub fn check_rebuild() {
+    if !utils::command_exists("ldd") {
+        eprintln!("{} check-rebuild needs ldd, which could not be found in PATH", Red.paint("Error:"));
+        std::process::exit(1);
+    }
+
+    let mut packages: BTreeMap<String, Vec<PathBuf>> = State::load()
+        .packages
+        .into_iter()
+        .map(|(name, record)| (name, record.files))
+        .collect();
+    // on arch, AUR packages are installed with pacman and aurora keeps no files for them
+    for (name, files) in distro::foreign_files() {
+        packages.entry(name).or_insert(files);
+    }
+
+    let mut broken: BTreeMap<&str, Vec<(&Path, Vec<String>)>> = BTreeMap::new();
+    for (name, files) in &packages {
+        for file in files.iter().filter(|file| is_elf(file)) {
+            let missing = missing_libraries(file);
+            if !missing.is_empty() {
+                broken.entry(name).or_default().push((file, missing));
+            }
+        }
+    }
+
+    if broken.is_empty() {
+        println!("{} Every installed binary finds its libraries", Green.paint("~>"));
+        return;
+    }
+    for (name, files) in &broken {
+        println!("~> {}", name);
+        for (file, missing) in files {
+            println!("   {} {}", file.display(), Red.paint(format!("needs {}", missing.join(", "))));
+        }
+    }
+
+    let names: Vec<String> = broken.keys().map(|name| name.to_string()).collect();
+    let input = utils::prompt(&format!("~> Rebuild {}? [Y/n]", names.join(", ")));
+    if input.eq_ignore_ascii_case("n") {
+        println!("{}", Yellow.paint("Nothing was rebuilt"));
+        std::process::exit(1);
+    }
+    install::install(&InstallArgs::parse_from(std::iter::once("install".to_string()).chain(names)));
+}
+
+// scripts have nothing for ldd to check
+fn is_elf(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    path.is_file() && File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_ok() && magic == *b"\x7fELF"
+}
+
+// the sonames ldd reports as "not found"; statically linked binaries make ldd fail and list nothing
+fn missing_libraries(path: &Path) -> Vec<String> {
+    let output = match Command::new("ldd").arg(path).stderr(Stdio::null()).output_always() {
+        Ok(output) => output,
+        Err(_) => return vec![],
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().strip_suffix("=> not found"))
+        .map(|soname| soname.trim().to_string())
+        .collect()
+}
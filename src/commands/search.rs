@@ -0,0 +1,217 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::process::{Command, Stdio};
+use ansi_term::Colour::{Green, Red, Yellow};
+use clap::Parser;
+use crate::aur::{self, AurPackage};
+use crate::cli::{InstallArgs, SearchArgs};
+use crate::commands::install;
+use crate::config::Config;
+use crate::distro;
+use crate::paths;
+use crate::runner::{self, Run};
+use crate::state::State;
+use crate::utils;
+
+pub fn search(args: &SearchArgs) {
+    // the RPC searches for one term, the others narrow the results down; with --maintainer
+    // every term does
+    let (results, terms) = match &args.maintainer {
+        Some(maintainer) => (aur::search(maintainer, "maintainer"), &args.query[..]),
+        None => (aur::search(&args.query[0], "name-desc"), &args.query[1..]),
+    };
+    let results: Vec<AurPackage> = match results {
+        Ok(results) => results
+            .into_iter()
+            .filter(|p| terms.iter().all(|term| matches(p, term)))
+            .filter(|p| !args.out_of_date || p.out_of_date.is_some())
+            .filter(|p| args.min_votes.is_none_or(|min| p.num_votes >= min))
+            .collect(),
+        Err(e) => {
+            eprintln!("{} Searching the AUR failed: {}", Red.paint("Error:"), e);
+            std::process::exit(1);
+        }
+    };
+
+    let installed = installed(&results);
+    let results: Vec<AurPackage> = results
+        .into_iter()
+        .filter(|p| !args.installed_only || installed.contains(&p.name))
+        .collect();
+
+    if results.is_empty() {
+        match &args.maintainer {
+            Some(maintainer) => println!("~> No matching AUR packages maintained by {}", maintainer),
+            None => println!("~> No AUR packages match '{}'", args.query.join(" ")),
+        }
+        return;
+    }
+
+    let interactive = (args.interactive || args.fzf) && io::stdin().is_terminal();
+    let fzf = args.fzf || Config::load().selector.as_deref() == Some("fzf");
+    let picked = if interactive && fzf && utils::command_exists("fzf") {
+        pick_with_fzf(&results, &installed)
+    } else {
+        // numbered from the bottom so the best match sits right above the prompt
+        for (n, package) in results.iter().enumerate().rev() {
+            print_result(n + 1, package, installed.contains(&package.name), interactive);
+        }
+        if !interactive {
+            return;
+        }
+        pick(&results)
+    };
+
+    let picked = match picked {
+        Some(picked) if !picked.is_empty() => picked,
+        _ => {
+            println!("~> Nothing selected");
+            return;
+        }
+    };
+    install::install(&InstallArgs::parse_from(std::iter::once("install".to_string()).chain(picked)));
+}
+
+fn matches(package: &AurPackage, term: &str) -> bool {
+    let term = term.to_lowercase();
+    package.name.to_lowercase().contains(&term)
+        || package.description.as_deref().unwrap_or("").to_lowercase().contains(&term)
+}
+
+// installed through pacman or tracked by aurora
+fn installed(results: &[AurPackage]) -> BTreeSet<String> {
+    let names: Vec<String> = results.iter().map(|p| p.name.clone()).collect();
+    let mut installed = distro::installed(&names);
+    let state = State::load();
+    installed.extend(names.into_iter().filter(|name| state.packages.contains_key(name)));
+    installed
+}
+
+fn print_result(n: usize, package: &AurPackage, installed: bool, numbered: bool) {
+    let number = if numbered { format!("{} ", Yellow.paint(n.to_string())) } else { String::new() };
+    let mut marker = String::new();
+    if package.out_of_date.is_some() {
+        marker.push_str(&format!(" {}", Red.paint("(out of date)")));
+    }
+    if installed {
+        marker.push_str(&format!(" {}", Green.paint("[installed]")));
+    }
+    println!(
+        "{}{} {} (+{} {:.2}){}",
+        number,
+        Green.bold().paint(&package.name),
+        package.version,
+        package.num_votes,
+        package.popularity,
+        marker
+    );
+    if let Some(description) = &package.description {
+        println!("    {}", description);
+    }
+}
+
+fn pick(results: &[AurPackage]) -> Option<Vec<String>> {
+    let input = utils::prompt("~> Packages to install (e.g. 1 2 4-6, enter to cancel):");
+    match parse_selection(&input, results.len()) {
+        Ok(picked) => Some(picked.into_iter().map(|n| results[n - 1].name.clone()).collect()),
+        Err(e) => {
+            eprintln!("{} {}", Red.paint("Error:"), e);
+            None
+        }
+    }
+}
+
+// multi-select in fzf, previewing `aurora info` for the highlighted package
+fn pick_with_fzf(results: &[AurPackage], installed: &BTreeSet<String>) -> Option<Vec<String>> {
+    let lines: Vec<String> = results
+        .iter()
+        .map(|p| {
+            let marker = if installed.contains(&p.name) { " [installed]" } else { "" };
+            format!("{}\t{}\t{}{}", p.name, p.version, p.description.as_deref().unwrap_or(""), marker)
+        })
+        .collect();
+    let input = paths::temp_dir().join("search-results");
+    fs::create_dir_all(paths::temp_dir()).ok()?;
+    fs::write(&input, lines.join("\n")).ok()?;
+
+    let aurora = std::env::current_exe().ok()?;
+    let preview = format!("{} info {{1}}", runner::shell_quote(&aurora.to_string_lossy()));
+    let output = Command::new("fzf")
+        .arg("--multi")
+        .arg("--delimiter=\t")
+        .arg("--preview")
+        .arg(preview)
+        .stdin(fs::File::open(&input).ok()?)
+        .stderr(Stdio::inherit())
+        .output_always();
+    let _ = fs::remove_file(&input);
+
+    // fzf exits with 1 when nothing matched and 130 when cancelled
+    let output = output.ok().filter(|o| o.status.success())?;
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split('\t').next())
+            .map(|name| name.to_string())
+            .collect(),
+    )
+}
+
+pub fn info(package: &str) {
+    let found = match aur::info(&[package.to_string()]) {
+        Ok(found) => found,
+        Err(e) => {
+            eprintln!("{} Querying the AUR failed: {}", Red.paint("Error:"), e);
+            std::process::exit(1);
+        }
+    };
+    let package = match found.into_values().next() {
+        Some(package) => package,
+        None => {
+            eprintln!("{} {} was not found in the AUR", Red.paint("Error:"), package);
+            std::process::exit(1);
+        }
+    };
+
+    println!("{} {}", Green.bold().paint(&package.name), package.version);
+    if let Some(description) = &package.description {
+        println!("   {}", description);
+    }
+    if let Some(url) = &package.url {
+        println!("   url: {}", url);
+    }
+    println!("   maintainer: {}", package.maintainer.as_deref().unwrap_or("none (orphaned)"));
+    println!("   votes: {}, popularity: {:.2}", package.num_votes, package.popularity);
+    if let Some(since) = package.out_of_date {
+        println!("   {} since {}", Red.paint("out of date"), utils::format_timestamp(since));
+    }
+    if !package.depends.is_empty() {
+        println!("   depends: {}", package.depends.join(" "));
+    }
+    if !package.make_depends.is_empty() {
+        println!("   make depends: {}", package.make_depends.join(" "));
+    }
+}
+
+// "1 3", "1,3" and "2-4"; numbers start at 1 and duplicates are dropped
+fn parse_selection(input: &str, count: usize) -> Result<Vec<usize>, String> {
+    let mut picked = Vec::new();
+    for word in input.split(|c: char| c.is_whitespace() || c == ',').filter(|w| !w.is_empty()) {
+        let (start, end) = match word.split_once('-') {
+            Some((start, end)) => (start, end),
+            None => (word, word),
+        };
+        let parse = |n: &str| match n.parse::<usize>() {
+            Ok(n) if (1..=count).contains(&n) => Ok(n),
+            _ => Err(format!("'{}' is not a number between 1 and {}", word, count)),
+        };
+        let (start, end) = (parse(start)?, parse(end)?);
+        for n in start.min(end)..=start.max(end) {
+            if !picked.contains(&n) {
+                picked.push(n);
+            }
+        }
+    }
+    Ok(picked)
+}
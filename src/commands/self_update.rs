@@ -0,0 +1,130 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use ansi_term::Colour::{Green, Red, Yellow};
+use crate::paths;
+use crate::runner::Run;
+use crate::utils;
+use crate::version;
+
+const REPOSITORY: &str = "https://github.com/tungstencube-git/aurora";
+const LATEST_RELEASE: &str = "https://api.github.com/repos/tungstencube-git/aurora/releases/latest";
+
+// builds the latest release from source with cargo and swaps it in for the running binary
+pub fn self_update(check: bool) {
+    let current = env!("CARGO_PKG_VERSION");
+    let latest = match latest_release() {
+        Ok(tag) => tag,
+        Err(e) => {
+            eprintln!("{} Could not find the latest release: {}", Red.paint("Error:"), e);
+            std::process::exit(1);
+        }
+    };
+    let latest_version = latest.trim_start_matches('v');
+
+    if version::vercmp(latest_version, current) != Ordering::Greater {
+        println!("~> aurora {} is up to date", current);
+        return;
+    }
+    println!("~> aurora {} is available (running {})", latest_version, current);
+    if check {
+        return;
+    }
+
+    let exe = match std::env::current_exe().and_then(fs::canonicalize) {
+        Ok(exe) => exe,
+        Err(e) => {
+            eprintln!("{} Could not find the running executable: {}", Red.paint("Error:"), e);
+            std::process::exit(1);
+        }
+    };
+    let built = match build(&latest) {
+        Some(built) => built,
+        None => std::process::exit(1),
+    };
+    if let Err(e) = replace(&exe, &built) {
+        eprintln!("{} Could not replace {}: {}", Red.paint("Error:"), exe.display(), e);
+        std::process::exit(1);
+    }
+    println!("{} to {}", Green.paint("~> Updated aurora"), latest_version);
+}
+
+fn latest_release() -> Result<String, String> {
+    if !utils::command_exists("curl") {
+        return Err("curl is needed to check for updates".to_string());
+    }
+    let output = Command::new("curl")
+        .args(["-sSLf", "--max-time", "15", "-H", "Accept: application/vnd.github+json"])
+        .arg(LATEST_RELEASE)
+        .output_always()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    let release: serde_json::Value = serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+    release["tag_name"]
+        .as_str()
+        .map(|tag| tag.to_string())
+        .ok_or_else(|| "the release has no tag".to_string())
+}
+
+fn build(tag: &str) -> Option<PathBuf> {
+    if !utils::command_exists("cargo") {
+        eprintln!("{} cargo is needed to build the update", Red.paint("Error:"));
+        return None;
+    }
+    let dir = paths::temp_dir().join("self-update");
+    if dir.exists() {
+        fs::remove_dir_all(&dir).expect("Failed to clean previous self-update");
+    }
+
+    println!("\x1b[1m~> Cloning {} at {}\x1b[0m", REPOSITORY, tag);
+    let cloned = Command::new("git")
+        .args(["clone", "-q", "--depth=1", "--branch", tag, REPOSITORY])
+        .arg(&dir)
+        .run_always()
+        .is_ok_and(|s| s.success());
+    if !cloned {
+        eprintln!("{} Cloning {} failed", Red.paint("Error:"), REPOSITORY);
+        return None;
+    }
+
+    println!("~> Building");
+    let built = Command::new("cargo")
+        .args(["build", "--release", "--locked"])
+        .current_dir(&dir)
+        .stdout(Stdio::null())
+        .run_always()
+        .is_ok_and(|s| s.success());
+    let binary = dir.join("target/release").join(format!("aurora{}", std::env::consts::EXE_SUFFIX));
+    if !built || !binary.is_file() {
+        eprintln!("{}", Red.paint("Build failed"));
+        return None;
+    }
+    Some(binary)
+}
+
+// the new binary is copied next to the old one and renamed over it; the running one is moved
+// aside first since windows won't replace an executable that is in use
+fn replace(exe: &Path, built: &Path) -> std::io::Result<()> {
+    let name = exe.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let staged = exe.with_file_name(format!(".{}.new", name));
+    let old = exe.with_file_name(format!(".{}.old", name));
+
+    fs::copy(built, &staged)?;
+    utils::make_executable(&staged);
+    if let Err(e) = fs::rename(exe, &old) {
+        let _ = fs::remove_file(&staged);
+        return Err(e);
+    }
+    if let Err(e) = fs::rename(&staged, exe) {
+        let _ = fs::rename(&old, exe);
+        let _ = fs::remove_file(&staged);
+        return Err(e);
+    }
+    if fs::remove_file(&old).is_err() {
+        println!("{} The old binary was left at {}", Yellow.paint("~> Warning:"), old.display());
+    }
+    Ok(())
+}
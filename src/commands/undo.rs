@@ -0,0 +1,166 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use ansi_term::Colour::{Green, Red, Yellow};
+use crate::commands::downgrade;
+use crate::distro;
+use crate::journal::{self, Change, Entry};
+use crate::runner::Run;
+use crate::state::{self, InstallRecord, State};
+use crate::units;
+use crate::utils;
+
+enum Step {
+    // an install that added the package: its files go
+    Remove(Vec<PathBuf>),
+    // an upgrade or downgrade: the copy saved before it goes back
+    Restore(PathBuf, Box<InstallRecord>),
+    // the same for a pacman package, from the package cache
+    Reinstall(PathBuf),
+    // pacman packages aren't removed behind the user's back
+    Manual(String),
+}
+
+// reverts the newest journal entry that wasn't undone yet, after showing what that takes
+pub fn undo(yes: bool) {
+    let Some(entry) = journal::last_undoable() else {
+        println!("~> Nothing to undo");
+        return;
+    };
+    println!(
+        "~> Undoing `{}` from {}:",
+        entry.command.join(" "),
+        utils::format_timestamp(entry.at)
+    );
+
+    let state = State::load();
+    let foreign = distro::foreign();
+    let mut steps = Vec::new();
+    for change in entry.packages.iter().filter(|change| change.outcome != "failed") {
+        match plan(change, &entry, &state, foreign.contains_key(&change.name)) {
+            Ok(Some(step)) => steps.push((change, step)),
+            Ok(None) => println!("   {}: nothing left to undo", change.name),
+            Err(e) => {
+                eprintln!("{} {}; nothing was undone", Red.paint("Error:"), e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if steps.is_empty() {
+        println!("~> Nothing to undo");
+        return;
+    }
+    for (change, step) in &steps {
+        match step {
+            Step::Remove(files) => {
+                println!("   {}: remove", change.name);
+                for file in files {
+                    println!("      {}", file.display());
+                }
+            }
+            Step::Restore(_, record) => println!(
+                "   {}: restore {} ({} build installed {})",
+                change.name,
+                change.before.as_deref().unwrap_or("the previous version"),
+                record.build_system,
+                utils::format_timestamp(record.installed_at)
+            ),
+            Step::Reinstall(file) => println!("   {}: pacman -U {}", change.name, file.display()),
+            Step::Manual(command) => println!("   {}: run `{}` yourself", change.name, command),
+        }
+    }
+
+    if !yes && !utils::prompt("~> Undo this? [y/N]").eq_ignore_ascii_case("y") {
+        println!("{}", Yellow.paint("Nothing was undone"));
+        std::process::exit(1);
+    }
+
+    let mut changes = Vec::new();
+    for (change, step) in steps {
+        let done = match &step {
+            Step::Remove(files) => remove(&change.name, files),
+            Step::Restore(dir, record) => downgrade::restore(&change.name, dir, record, false),
+            Step::Reinstall(file) => downgrade::install_package(file),
+            // still recorded, so the next undo moves on
+            Step::Manual(_) => {
+                changes.push(Change::new(&change.name, change.after.clone(), change.after.clone(), "left to you"));
+                continue;
+            }
+        };
+        let after = if done { change.before.clone() } else { change.after.clone() };
+        changes.push(Change::new(&change.name, change.after.clone(), after, if done { "reverted" } else { "failed" }));
+    }
+    journal::record_undo(entry.at, changes);
+}
+
+// Err when what the step needs is gone
+fn plan(change: &Change, entry: &Entry, state: &State, pacman: bool) -> Result<Option<Step>, String> {
+    let record = state.packages.get(&change.name);
+    if change.before.is_none() && change.outcome == "installed" {
+        return Ok(match (record, pacman) {
+            (Some(record), _) => Some(Step::Remove(record.files.clone())),
+            (None, true) => Some(Step::Manual(format!("{}pacman -R {}", privilege(), change.name))),
+            (None, false) => None,
+        });
+    }
+
+    if record.is_some() {
+        // the newest copy of the version the entry's command replaced
+        return state::saved_versions(&change.name)
+            .into_iter()
+            .rev()
+            .find(|(_, saved)| saved.installed_at < entry.at && same_version(saved, change.before.as_deref()))
+            .map(|(dir, saved)| Some(Step::Restore(dir, Box::new(saved))))
+            .ok_or_else(|| format!("the copy of {} from before this was pruned, aurora only keeps the last few", change.name));
+    }
+    if pacman {
+        let before = change.before.as_deref().unwrap_or_default();
+        let prefix = format!("{}-{}-", change.name, before);
+        return downgrade::cached_packages(&change.name)
+            .into_iter()
+            .find(|file| file.file_name().is_some_and(|name| name.to_string_lossy().starts_with(&prefix)))
+            .map(|file| Some(Step::Reinstall(file)))
+            .ok_or_else(|| format!("{} {} is no longer in aurora's package cache", change.name, before));
+    }
+    Ok(None)
+}
+
+// by the version it printed or the commit it was built from, whichever the journal had
+fn same_version(record: &InstallRecord, version: Option<&str>) -> bool {
+    let commit = record.commit.as_deref().map(utils::short_commit);
+    version.is_some_and(|version| record.version.as_deref() == Some(version) || commit == Some(version))
+}
+
+fn privilege() -> String {
+    match utils::is_root() {
+        true => String::new(),
+        false => format!("{} ", utils::get_privilege_command()),
+    }
+}
+
+// deletes what the install added and forgets the package
+fn remove(package: &str, files: &[PathBuf]) -> bool {
+    let unit_dir = units::user_dir();
+    let (unit_files, files): (Vec<PathBuf>, Vec<PathBuf>) = files.iter().cloned().partition(|file| file.starts_with(&unit_dir));
+    units::remove(&unit_files);
+
+    let mut removed = true;
+    for file in files.iter().filter(|file| file.exists()) {
+        // installed with --system
+        let deleted = fs::remove_file(file).is_ok()
+            || (!utils::is_root()
+                && Command::new(utils::get_privilege_command()).arg("rm").arg("-f").arg(file).run().is_ok_and(|s| s.success()));
+        if !deleted {
+            eprintln!("{} Could not remove {}", Red.paint("Error:"), file.display());
+            removed = false;
+        }
+    }
+    if !removed {
+        return false;
+    }
+    let mut state = State::load();
+    state.packages.remove(package);
+    state.save();
+    println!("{} {}", Green.paint("~> Removed"), package);
+    true
+}
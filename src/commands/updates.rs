@@ -0,0 +1,170 @@
+use std::cmp::Ordering;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use ansi_term::Colour::{Green, Red};
+use crate::aur::{self, AurPackage};
+use tracing::debug;
+use crate::distro;
+use crate::pkgbuild;
+use crate::runner::Run;
+use crate::utils;
+use crate::version;
+
+// like dnf check-update, so scripts can tell "updates available" from a failure
+const UPDATES_AVAILABLE: i32 = 100;
+const LS_REMOTE_TIMEOUT: Duration = Duration::from_secs(30);
+
+struct Update {
+    name: String,
+    installed: String,
+    available: String,
+    // the upstream commit of a VCS package moved on; `available` is the commit
+    devel: bool,
+}
+
+// every package pacman -Qm lists against the AUR's version of it, whoever installed it
+pub fn check_updates(devel: bool, json: bool) {
+    if !utils::command_exists("pacman") {
+        eprintln!("{} check-updates compares pacman's foreign packages with the AUR and needs pacman", Red.paint("Error:"));
+        std::process::exit(1);
+    }
+    let installed = distro::foreign();
+    let names: Vec<String> = installed.keys().cloned().collect();
+    let found = match aur::info(&names) {
+        Ok(found) => found,
+        Err(e) => {
+            eprintln!("{} Asking the AUR for versions failed: {}", Red.paint("Error:"), e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut updates = Vec::new();
+    for (name, installed) in &installed {
+        let Some(package) = found.get(name) else {
+            continue;
+        };
+        if version::vercmp(&package.version, installed) == Ordering::Greater {
+            updates.push(Update { name: name.clone(), installed: installed.clone(), available: package.version.clone(), devel: false });
+        } else if devel && name.ends_with("-git") {
+            let Some(commit) = upstream_commit(package) else {
+                continue;
+            };
+            if has_commit(installed, &commit) == Some(false) {
+                updates.push(Update { name: name.clone(), installed: installed.clone(), available: commit, devel: true });
+            }
+        }
+    }
+
+    if json {
+        let list: Vec<_> = updates
+            .iter()
+            .map(|update| serde_json::json!({
+                "name": update.name,
+                "installed": update.installed,
+                "available": update.available,
+                "devel": update.devel,
+            }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&list).expect("Failed to serialize updates"));
+    } else {
+        for update in &updates {
+            let available = match update.devel {
+                true => format!("latest ({})", &update.available[..update.available.len().min(7)]),
+                false => update.available.clone(),
+            };
+            println!("{} {} -> {}", update.name, Red.paint(&update.installed), Green.paint(available));
+        }
+        let missing: Vec<&str> = names.iter().filter(|name| !found.contains_key(*name)).map(|name| name.as_str()).collect();
+        if !missing.is_empty() {
+            println!("~> Not in the AUR: {}", missing.join(", "));
+        }
+        if updates.is_empty() {
+            println!("{} Every foreign package is up to date", Green.paint("~>"));
+        }
+    }
+    if !updates.is_empty() {
+        std::process::exit(UPDATES_AVAILABLE);
+    }
+}
+
+// the commit the first git source's branch points at now; sources pinned to a tag or commit
+// never move
+fn upstream_commit(package: &AurPackage) -> Option<String> {
+    let base = package.package_base.as_deref().unwrap_or(&package.name);
+    let content = aur::repo_file(base, ".SRCINFO").ok()??;
+    let info = pkgbuild::parse_srcinfo(&String::from_utf8_lossy(&content));
+    let source = info.sources.iter().find_map(|source| {
+        let url = source.split_once("::").map_or(source.as_str(), |(_, url)| url);
+        url.strip_prefix("git+").or_else(|| url.starts_with("git://").then_some(url))
+    })?;
+    let (url, reference) = match source.split_once('#') {
+        Some((url, fragment)) => match fragment.strip_prefix("branch=") {
+            Some(branch) => (url, format!("refs/heads/{}", branch)),
+            None => return None,
+        },
+        None => (source, "HEAD".to_string()),
+    };
+    let url = url.split('?').next().unwrap_or(url);
+
+    let output = Command::new("git")
+        .args(["ls-remote", url, &reference])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output_within(LS_REMOTE_TIMEOUT)
+        .ok()??;
+    let commit = String::from_utf8_lossy(&output.stdout).split_whitespace().next()?.to_string();
+    if commit.is_empty() {
+        debug!("git ls-remote {} {} found nothing", url, reference);
+        return None;
+    }
+    Some(commit)
+}
+
+// whether a pkgver like "r123.abc1234" or "1.2.r5.gabc1234" names the commit; None when it
+// holds no commit hash to compare. a run of digits only counts after `g` or an `r<count>` part,
+// since dates and revision counts look just like it
+fn has_commit(version: &str, commit: &str) -> Option<bool> {
+    let parts: Vec<&str> = version.split(['.', '-', '_', '+']).collect();
+    let revision = |part: &str| part.strip_prefix('r').is_some_and(|count| !count.is_empty() && count.bytes().all(|b| b.is_ascii_digit()));
+    let hashes: Vec<&str> = parts
+        .iter()
+        .enumerate()
+        .filter_map(|(index, part)| {
+            let (hash, prefixed) = match part.strip_prefix('g').filter(|hash| hash.len() >= 7) {
+                Some(hash) => (hash, true),
+                None => (*part, index > 0 && revision(parts[index - 1])),
+            };
+            let hex = hash.len() >= 7 && hash.bytes().all(|b| b.is_ascii_hexdigit());
+            (hex && (prefixed || hash.bytes().any(|b| b.is_ascii_alphabetic()))).then_some(hash)
+        })
+        .collect();
+    if hashes.is_empty() {
+        return None;
+    }
+    Some(hashes.iter().any(|hash| commit.starts_with(&hash.to_ascii_lowercase())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMIT: &str = "abc1234def567890abc1234def567890abc12345";
+
+    #[test]
+    fn commits_are_found_in_git_versions() {
+        assert_eq!(has_commit("r123.abc1234", COMMIT), Some(true));
+        assert_eq!(has_commit("1.2.r5.gabc1234", COMMIT), Some(true));
+        assert_eq!(has_commit("1.2.r5.gabc1234-1", "0000000"), Some(false));
+        assert_eq!(has_commit("r12.1234567", "1234567890"), Some(true));
+        assert_eq!(has_commit("0.9.g1234567", "1234567890"), Some(true));
+    }
+
+    #[test]
+    fn dates_and_counts_are_not_commits() {
+        assert_eq!(has_commit("20240101", COMMIT), None);
+        assert_eq!(has_commit("20240101-1", COMMIT), None);
+        assert_eq!(has_commit("1.0.1234567", COMMIT), None);
+        assert_eq!(has_commit("2.1", COMMIT), None);
+    }
+}
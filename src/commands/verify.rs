@@ -0,0 +1,77 @@
+use ansi_term::Colour::{Green, Red, Yellow};
+use crate::sha256;
+use crate::state::State;
+
+// compares installed files with the sha256 recorded at install time; every package when none
+// are named
+pub fn verify(packages: &[String], json: bool) {
+    let state = State::load();
+    for package in packages {
+        if !state.packages.contains_key(package) {
+            eprintln!("{} {} was not installed with aurora", Red.paint("Error:"), package);
+            std::process::exit(1);
+        }
+    }
+
+    let mut report = Vec::new();
+    let mut failed = false;
+    for (name, record) in state.packages.iter().filter(|(name, _)| packages.is_empty() || packages.contains(name)) {
+        let mut files = Vec::new();
+        for file in &record.files {
+            let expected = record.sha256.get(file);
+            let actual = if file.is_file() { sha256::file(file) } else { None };
+            let status = match (expected, &actual) {
+                (_, None) if !file.exists() => "missing",
+                // linked directories aren't hashed
+                (None, None) if file.is_dir() => "ok",
+                (None, _) => "unrecorded",
+                (Some(expected), Some(actual)) if expected == actual => "ok",
+                _ => "modified",
+            };
+            failed |= status == "missing" || status == "modified";
+            files.push((file, expected, actual, status));
+        }
+
+        if json {
+            report.push(serde_json::json!({
+                "package": name,
+                "files": files
+                    .iter()
+                    .map(|(path, expected, actual, status)| serde_json::json!({
+                        "path": path,
+                        "sha256": expected,
+                        "actual": actual,
+                        "status": status,
+                    }))
+                    .collect::<Vec<_>>(),
+            }));
+            continue;
+        }
+
+        let problems: Vec<_> = files.iter().filter(|(_, _, _, status)| *status != "ok").collect();
+        if problems.is_empty() {
+            let noun = if files.len() == 1 { "file matches" } else { "files match" };
+            println!("{} {}: {} installed {}", Green.paint("~>"), name, files.len(), noun);
+            continue;
+        }
+        println!("~> {}", name);
+        for (path, _, _, status) in problems {
+            match *status {
+                "unrecorded" => println!("   {} {}", Yellow.paint("no hash recorded:"), path.display()),
+                status => println!("   {} {}", Red.paint(format!("{}:", status)), path.display()),
+            }
+        }
+        if files.iter().any(|(_, _, _, status)| *status == "unrecorded") {
+            println!("   reinstall {} to record hashes for every file", name);
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).expect("Failed to serialize verification"));
+    } else if state.packages.is_empty() {
+        println!("~> No packages installed with aurora");
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,82 @@
+use ansi_term::Colour::{Green, Red, Yellow};
+use crate::distro;
+use crate::state::State;
+use crate::utils;
+
+// why a package is installed: explicitly, or the chains of packages that pulled it in
+pub fn why(package: &str) {
+    let state = State::load();
+    let pacman = distro::local_info(package);
+
+    let record = match (state.packages.get(package), &pacman) {
+        (Some(record), _) => record,
+        (None, Some(info)) => {
+            // installed through pacman only, e.g. built with makepkg
+            if info.explicit {
+                println!("~> {} was explicitly installed on {}", Green.bold().paint(package), info.install_date);
+            } else if info.required_by.is_empty() {
+                println!("~> {} was installed as a dependency, but nothing requires it any more", Green.bold().paint(package));
+            } else {
+                println!("~> {} was installed as a dependency of {}", Green.bold().paint(package), info.required_by.join(", "));
+            }
+            return;
+        }
+        (None, None) => {
+            eprintln!("{} {} is not installed", Red.paint("Error:"), package);
+            std::process::exit(1);
+        }
+    };
+
+    let installed = utils::format_timestamp(record.installed_at);
+    if record.explicit {
+        println!("~> {} was explicitly installed on {}", Green.bold().paint(package), installed);
+    } else {
+        println!("~> {} was installed as a dependency on {}", Green.bold().paint(package), installed);
+    }
+    if !record.required_by.is_empty() {
+        println!("~> Required by:");
+        let mut chains = Vec::new();
+        walk(&state, package, &mut vec![package.to_string()], &mut chains);
+        for chain in chains {
+            println!("   {}", chain);
+        }
+    }
+
+    if let Some(info) = pacman {
+        if info.explicit != record.explicit {
+            println!(
+                "{} pacman has {} as {} installed",
+                Yellow.paint("~> Warning:"),
+                package,
+                if info.explicit { "explicitly" } else { "a dependency," }
+            );
+        }
+    }
+}
+
+// every path from the package up to an explicit install, e.g. "libfoo <- foo (explicit)"
+fn walk(state: &State, package: &str, chain: &mut Vec<String>, chains: &mut Vec<String>) {
+    let parents = match state.packages.get(package) {
+        Some(record) if !record.required_by.is_empty() => record.required_by.clone(),
+        Some(record) => {
+            let end = if record.explicit { "explicit" } else { "no longer required" };
+            chains.push(format!("{} ({})", chain.join(" <- "), end));
+            return;
+        }
+        None => {
+            let end = if distro::local_info(package).is_some() { "installed with pacman" } else { "not installed" };
+            chains.push(format!("{} ({})", chain.join(" <- "), end));
+            return;
+        }
+    };
+
+    for parent in parents {
+        if chain.contains(&parent) {
+            chains.push(format!("{} <- {} ({})", chain.join(" <- "), parent, Red.paint("cycle")));
+            continue;
+        }
+        chain.push(parent.clone());
+        walk(state, &parent, chain, chains);
+        chain.pop();
+    }
+}
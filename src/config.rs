@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use ansi_term::Colour::{Red, Yellow};
+use serde::Deserialize;
+use crate::build_system::BuildSystem;
+use crate::paths;
+use crate::utils;
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Config {
+    pub dest: Option<String>,
+    pub goflags: Option<String>,
+    pub build_priority: Vec<String>,
+    pub node_package_manager: Option<String>,
+    pub env: BTreeMap<String, String>,
+    pub registry: Option<String>,
+    pub compiler_cache: Option<String>,
+    // "fzf" to pick search results in fzf
+    pub selector: Option<String>,
+    // desktop notifications for installs that take longer than the threshold (a minute by default)
+    pub notify: bool,
+    pub notify_threshold_secs: Option<u64>,
+    // makepkg --sign, with gpg_key or makepkg's default key
+    pub sign_packages: bool,
+    pub gpg_key: Option<String>,
+    // builds that download from any other domain (or its subdomains) are refused; empty allows all
+    pub allowed_domains: Vec<String>,
+    // packages that always build with --with-tests
+    pub with_tests: Vec<String>,
+    // refresh sudo's timestamp every minute once aurora has authenticated
+    pub keep_sudo_alive: bool,
+    // no warning when the system has pending updates or stale sync databases before a build
+    pub ignore_partial_upgrade: bool,
+    // seconds before an unanswered prompt takes its default, like --prompt-timeout
+    pub prompt_timeout: Option<u64>,
+    // always build as with --clean-env, and the variables such builds still inherit
+    pub clean_env: bool,
+    pub clean_env_passthrough: Vec<String>,
+    // like --nice, --ionice and --max-load
+    pub nice: Option<i32>,
+    pub ionice: bool,
+    pub max_load: Option<f64>,
+    // directories --sandbox builds may write to besides the build directory and tool caches
+    pub sandbox_writable: Vec<String>,
+    // "Name <email>" for makepkg's PACKAGER
+    pub packager: Option<String>,
+    // SOURCE_DATE_EPOCH from the last commit, and build paths kept out of cargo and cmake builds
+    pub reproducible: bool,
+    pub build: BuildConfig,
+    pub rpc: RpcConfig,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct BuildConfig {
+    pub env: BTreeMap<String, String>,
+    pub inherit_makepkg_conf: bool,
+}
+
+// how long AUR answers are reused, in seconds
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct RpcConfig {
+    pub info_ttl: u64,
+    pub search_ttl: u64,
+}
+
+impl Default for RpcConfig {
+    fn default() -> RpcConfig {
+        RpcConfig { info_ttl: 15 * 60, search_ttl: 24 * 60 * 60 }
+    }
+}
+
+const MAKEPKG_VARS: [&str; 7] = ["CFLAGS", "CXXFLAGS", "CPPFLAGS", "LDFLAGS", "LTOFLAGS", "RUSTFLAGS", "MAKEFLAGS"];
+
+pub fn config_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| paths::home_dir().join(".config"))
+        .join("aurora")
+}
+
+impl Config {
+    pub fn load() -> Config {
+        let path = config_dir().join("config.toml");
+        let content = match fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(_) => return Config::default(),
+        };
+
+        match toml::from_str(&content) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("{} Invalid config file {}: {}", Red.paint("Error:"), path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    pub fn dest_dir(&self) -> PathBuf {
+        match &self.dest {
+            Some(dest) => paths::expand_tilde(dest),
+            None => paths::bin_dir(),
+        }
+    }
+
+    // defaults for every build: makepkg.conf if inherited, then [build.env]
+    pub fn build_env(&self) -> BTreeMap<String, String> {
+        let mut env = if self.build.inherit_makepkg_conf {
+            makepkg_env()
+        } else {
+            BTreeMap::new()
+        };
+        env.extend(self.build.env.clone());
+        env
+    }
+
+    // the compiler cache to use, if one is configured and installed
+    pub fn compiler_cache(&self) -> Option<&'static str> {
+        let wanted = self.compiler_cache.as_deref()?;
+        let found = match wanted {
+            "auto" => ["sccache", "ccache"].into_iter().find(|c| utils::command_exists(c)),
+            "ccache" => Some("ccache").filter(|c| utils::command_exists(c)),
+            "sccache" => Some("sccache").filter(|c| utils::command_exists(c)),
+            other => {
+                println!("{} Unknown compiler_cache '{}', expected ccache, sccache or auto", Yellow.paint("~> Warning:"), other);
+                return None;
+            }
+        };
+        if found.is_none() && wanted != "auto" {
+            println!("{} compiler_cache is set to {}, but it is not installed", Yellow.paint("~> Warning:"), wanted);
+        }
+        found
+    }
+
+    pub fn signs_packages(&self) -> bool {
+        self.sign_packages || self.gpg_key.is_some()
+    }
+
+    pub fn build_priority(&self) -> Vec<BuildSystem> {
+        self.build_priority
+            .iter()
+            .filter_map(|name| {
+                let build_system = BuildSystem::from_name(name);
+                if build_system.is_none() {
+                    println!("{} Unknown build system '{}' in build_priority", Yellow.paint("~> Warning:"), name);
+                }
+                build_system
+            })
+            .collect()
+    }
+}
+
+// providers picked for virtual dependencies such as java-runtime, kept apart from config.toml
+// since aurora writes to it
+fn providers_file() -> PathBuf {
+    config_dir().join("providers.toml")
+}
+
+pub fn providers() -> BTreeMap<String, String> {
+    fs::read_to_string(providers_file())
+        .ok()
+        .and_then(|content| toml::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn remember_provider(dependency: &str, provider: &str) {
+    let mut providers = providers();
+    providers.insert(dependency.to_string(), provider.to_string());
+
+    let dir = config_dir();
+    let tmp = dir.join("providers.toml.tmp");
+    let written = fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(&tmp, toml::to_string(&providers).unwrap_or_default()))
+        .and_then(|_| fs::rename(&tmp, providers_file()));
+    if let Err(e) = written {
+        println!("{} Could not save the provider choice: {}", Yellow.paint("~> Warning:"), e);
+    }
+}
+
+// compiler flags from makepkg.conf, read the way makepkg sources it: the system file,
+// its drop-ins, then the user's own
+fn makepkg_env() -> BTreeMap<String, String> {
+    let mut files = vec![PathBuf::from("/etc/makepkg.conf")];
+    if let Ok(entries) = fs::read_dir("/etc/makepkg.conf.d") {
+        let mut dropins: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|e| e == "conf").unwrap_or(false))
+            .collect();
+        dropins.sort();
+        files.extend(dropins);
+    }
+    files.push(
+        dirs::config_dir()
+            .unwrap_or_else(|| paths::home_dir().join(".config"))
+            .join("pacman/makepkg.conf"),
+    );
+    files.push(paths::home_dir().join(".makepkg.conf"));
+
+    let mut env = BTreeMap::new();
+    for file in files {
+        let content = match fs::read_to_string(&file) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        for line in content.lines() {
+            let (key, value) = match line.trim().split_once('=') {
+                Some(kv) => kv,
+                None => continue,
+            };
+            if !MAKEPKG_VARS.contains(&key) {
+                continue;
+            }
+            let value = expand_vars(value.trim().trim_matches(|c| c == '"' || c == '\''), &env);
+            env.insert(key.to_string(), value);
+        }
+    }
+    env
+}
+
+// enough of shell expansion for `CXXFLAGS="$CFLAGS -Wp,-D_GLIBCXX_ASSERTIONS"`
+fn expand_vars(value: &str, vars: &BTreeMap<String, String>) -> String {
+    let mut expanded = value.to_string();
+    for (name, var_value) in vars {
+        expanded = expanded
+            .replace(&format!("${{{}}}", name), var_value)
+            .replace(&format!("${}", name), var_value);
+    }
+    expanded
+}
@@ -0,0 +1,333 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+use crate::runner::Run;
+use crate::utils;
+use crate::version;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PackageManager {
+    Pacman,
+    Apt,
+    Dnf,
+}
+
+// tool, then its package for pacman, apt and dnf; empty when the distro doesn't ship it
+const PACKAGES: [(&str, &str, &str, &str); 36] = [
+    ("make", "make", "make", "make"),
+    ("cc", "gcc", "gcc", "gcc"),
+    ("gcc", "gcc", "gcc", "gcc"),
+    ("clang", "clang", "clang", "clang"),
+    ("c++", "gcc", "g++", "gcc-c++"),
+    ("g++", "gcc", "g++", "gcc-c++"),
+    ("cmake", "cmake", "cmake", "cmake"),
+    ("ninja", "ninja", "ninja-build", "ninja-build"),
+    ("meson", "meson", "meson", "meson"),
+    ("cargo", "rust", "cargo", "cargo"),
+    ("go", "go", "golang-go", "golang"),
+    ("zig", "zig", "", "zig"),
+    ("nimble", "nim", "nim", "nim"),
+    ("stack", "stack", "haskell-stack", "stack"),
+    ("cabal", "cabal-install", "cabal-install", "cabal-install"),
+    ("ghc", "ghc", "ghc", "ghc"),
+    ("npm", "npm", "npm", "npm"),
+    ("just", "just", "just", "just"),
+    ("scons", "scons", "scons", "python3-scons"),
+    ("gradle", "gradle", "gradle", "gradle"),
+    ("mvn", "maven", "maven", "maven"),
+    ("java", "jdk-openjdk", "default-jdk", "java-latest-openjdk-devel"),
+    ("python3", "python", "python3", "python3"),
+    ("premake5", "premake", "premake", "premake"),
+    ("shards", "shards", "crystal", "crystal"),
+    ("crystal", "crystal", "crystal", "crystal"),
+    ("dmd", "dmd", "", ""),
+    ("ldc2", "ldc", "ldc", "ldc"),
+    ("dub", "dub", "dub", "dub"),
+    ("dune", "dune", "ocaml-dune", "ocaml-dune"),
+    ("ocaml", "ocaml", "ocaml", "ocaml"),
+    ("erl", "erlang", "erlang", "erlang"),
+    ("escript", "erlang", "erlang", "erlang"),
+    ("rebar3", "rebar3", "rebar3", "erlang-rebar3"),
+    ("mix", "elixir", "elixir", "elixir"),
+    ("qmake6", "qt6-base", "qmake6", "qt6-qtbase-devel"),
+];
+
+pub fn package_manager() -> Option<PackageManager> {
+    let os_release = fs::read_to_string("/etc/os-release").ok()?;
+    let field = |key: &str| {
+        os_release.lines().find_map(|line| {
+            line.strip_prefix(key)
+                .and_then(|rest| rest.strip_prefix('='))
+                .map(|value| value.trim_matches('"').to_string())
+        })
+    };
+
+    let ids = format!("{} {}", field("ID").unwrap_or_default(), field("ID_LIKE").unwrap_or_default());
+    ids.split_whitespace().find_map(|id| match id {
+        "arch" | "manjaro" | "endeavouros" | "artix" => Some(PackageManager::Pacman),
+        "debian" | "ubuntu" => Some(PackageManager::Apt),
+        "fedora" | "rhel" | "centos" => Some(PackageManager::Dnf),
+        _ => None,
+    })
+}
+
+impl PackageManager {
+    pub fn install_command(self) -> &'static str {
+        match self {
+            PackageManager::Pacman => "sudo pacman -S --needed",
+            PackageManager::Apt => "sudo apt install",
+            PackageManager::Dnf => "sudo dnf install",
+        }
+    }
+
+    // the key for this distro family in a manifest's "dependencies"
+    pub fn family(self) -> &'static str {
+        match self {
+            PackageManager::Pacman => "arch",
+            PackageManager::Apt => "debian",
+            PackageManager::Dnf => "fedora",
+        }
+    }
+
+    // the packages that aren't installed, asking pacman -Q, dpkg -s or rpm -q about each one
+    pub fn missing(self, packages: &[String]) -> Vec<String> {
+        packages
+            .iter()
+            .filter(|package| {
+                let mut query = match self {
+                    PackageManager::Pacman => Command::new("pacman"),
+                    PackageManager::Apt => Command::new("dpkg"),
+                    PackageManager::Dnf => Command::new("rpm"),
+                };
+                query.arg(match self {
+                    PackageManager::Pacman => "-Q",
+                    PackageManager::Apt => "-s",
+                    PackageManager::Dnf => "-q",
+                });
+                !query
+                    .arg(package)
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .run_always()
+                    .is_ok_and(|s| s.success())
+            })
+            .cloned()
+            .collect()
+    }
+
+    // through sudo, doas or run0; `yes` skips the package manager's own confirmation
+    pub fn install(self, packages: &[String], yes: bool) -> bool {
+        let mut install = Command::new(utils::get_privilege_command());
+        match self {
+            PackageManager::Pacman => install.args(["pacman", "-S", "--needed"]),
+            PackageManager::Apt => install.args(["apt", "install"]),
+            PackageManager::Dnf => install.args(["dnf", "install"]),
+        };
+        if yes {
+            install.arg(if self == PackageManager::Pacman { "--noconfirm" } else { "-y" });
+        }
+        install.args(packages).run().is_ok_and(|s| s.success())
+    }
+
+    // the package providing a tool; for "a|b" alternatives the first one with a package wins
+    pub fn package_for(self, tool: &str) -> Option<&'static str> {
+        tool.split('|').find_map(|alternative| {
+            let (_, pacman, apt, dnf) = PACKAGES.iter().find(|(t, ..)| *t == alternative)?;
+            let package = match self {
+                PackageManager::Pacman => *pacman,
+                PackageManager::Apt => *apt,
+                PackageManager::Dnf => *dnf,
+            };
+            (!package.is_empty()).then_some(package)
+        })
+    }
+}
+
+// pacman, through sudo, doas or run0 unless aurora already runs as root
+pub fn privileged_pacman() -> Command {
+    if utils::is_root() {
+        Command::new("pacman")
+    } else {
+        let mut privileged = Command::new(utils::get_privilege_command());
+        privileged.arg("pacman");
+        privileged
+    }
+}
+
+// `pacman -T` prints the dependencies that are not satisfied
+pub fn installed(names: &[String]) -> BTreeSet<String> {
+    if names.is_empty() || !utils::command_exists("pacman") {
+        return BTreeSet::new();
+    }
+    let missing: BTreeSet<String> = match Command::new("pacman").arg("-T").args(names).output_always() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).lines().map(|l| l.to_string()).collect(),
+        Err(_) => return BTreeSet::new(),
+    };
+    names.iter().filter(|name| !missing.contains(*name)).cloned().collect()
+}
+
+// the given names that are real packages in the sync repositories, with their versions
+pub fn in_repos(names: &[String]) -> BTreeMap<String, String> {
+    if names.is_empty() || !utils::command_exists("pacman") {
+        return BTreeMap::new();
+    }
+    repo_info(names).into_iter().map(|(name, version, _)| (name, version)).collect()
+}
+
+// versions of the given packages that are installed under that exact name
+pub fn installed_versions(names: &[String]) -> BTreeMap<String, String> {
+    if names.is_empty() || !utils::command_exists("pacman") {
+        return BTreeMap::new();
+    }
+    match Command::new("pacman").arg("-Q").args(names).stderr(Stdio::null()).output_always() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect(),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+// how long ago the sync databases were last refreshed
+pub fn sync_age() -> Option<Duration> {
+    let newest = fs::read_dir("/var/lib/pacman/sync")
+        .ok()?
+        .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+        .max()?;
+    SystemTime::now().duration_since(newest).ok()
+}
+
+// repository packages with a newer version waiting; checkupdates syncs a scratch copy of the
+// databases first, while pacman -Qu only knows what the last sync saw
+pub fn pending_updates() -> Vec<String> {
+    let mut command = if utils::command_exists("checkupdates") {
+        Command::new("checkupdates")
+    } else if utils::command_exists("pacman") {
+        let mut pacman = Command::new("pacman");
+        pacman.arg("-Qu");
+        pacman
+    } else {
+        return vec![];
+    };
+    match command.stderr(Stdio::null()).output_always() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|line| !line.ends_with("[ignored]"))
+            .filter_map(|line| line.split_whitespace().next())
+            .map(|name| name.to_string())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+// installed packages that didn't come from a sync repository, with their versions
+pub fn foreign() -> BTreeMap<String, String> {
+    if !utils::command_exists("pacman") {
+        return BTreeMap::new();
+    }
+    match Command::new("pacman").arg("-Qm").output_always() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect(),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+// the files of every foreign package
+pub fn foreign_files() -> BTreeMap<String, Vec<PathBuf>> {
+    let mut files: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    let foreign: Vec<String> = foreign().into_keys().collect();
+    if foreign.is_empty() {
+        return files;
+    }
+    if let Ok(output) = Command::new("pacman").arg("-Ql").args(&foreign).output_always() {
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if let Some((package, path)) = line.split_once(' ') {
+                files.entry(package.to_string()).or_default().push(PathBuf::from(path));
+            }
+        }
+    }
+    files
+}
+
+// repository packages whose provides include `dependency`
+pub fn repo_providers(dependency: &str) -> Vec<String> {
+    let candidates: Vec<String> = match Command::new("pacman").arg("-Sqs").arg(dependency).output_always() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).lines().map(|l| l.to_string()).collect(),
+        Err(_) => return vec![],
+    };
+    repo_info(&candidates)
+        .into_iter()
+        .filter(|(_, _, provides)| provides.iter().any(|p| version::parse_dependency(p).0 == dependency))
+        .map(|(name, _, _)| name)
+        .collect()
+}
+
+// name, version and provides of each package `pacman -Si` knows
+// what pacman -Qi says about an installed package: whether it was explicitly installed and what
+// requires it
+pub struct LocalInfo {
+    pub explicit: bool,
+    pub required_by: Vec<String>,
+    pub install_date: String,
+}
+
+pub fn local_info(name: &str) -> Option<LocalInfo> {
+    if !utils::command_exists("pacman") {
+        return None;
+    }
+    let output = Command::new("pacman").arg("-Qi").arg(name).stderr(Stdio::null()).output_always().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let mut info = LocalInfo { explicit: true, required_by: vec![], install_date: String::new() };
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        match line.split_once(" : ").map(|(key, value)| (key.trim(), value.trim())) {
+            Some(("Install Reason", reason)) => info.explicit = reason.starts_with("Explicitly"),
+            Some(("Required By", "None")) => {}
+            Some(("Required By", packages)) => info.required_by = packages.split_whitespace().map(|p| p.to_string()).collect(),
+            Some(("Install Date", date)) => info.install_date = date.to_string(),
+            _ => {}
+        }
+    }
+    Some(info)
+}
+
+fn repo_info(names: &[String]) -> Vec<(String, String, Vec<String>)> {
+    if names.is_empty() {
+        return vec![];
+    }
+    let output = match Command::new("pacman").arg("-Si").args(names).output_always() {
+        Ok(output) => output,
+        Err(_) => return vec![],
+    };
+
+    let mut packages: Vec<(String, String, Vec<String>)> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let (key, value) = match line.split_once(" : ") {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+        match key {
+            "Name" => packages.push((value.to_string(), String::new(), vec![])),
+            "Version" => {
+                if let Some((_, version, _)) = packages.last_mut() {
+                    *version = value.to_string();
+                }
+            }
+            "Provides" if value != "None" => {
+                if let Some((_, _, provides)) = packages.last_mut() {
+                    provides.extend(value.split_whitespace().map(|p| p.to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+    packages
+}
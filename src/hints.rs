@@ -0,0 +1,201 @@
+use crate::distro::PackageManager;
+
+// what a failed build was missing, recognised from its output
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Missing {
+    CCompiler,
+    CxxCompiler,
+    PkgConfig,
+    PythonHeaders,
+    OpenSsl,
+    Zlib,
+}
+
+// lowercase snippets of compiler, linker and configure output, checked in order
+const PATTERNS: [(&str, Missing); 22] = [
+    ("cc: command not found", Missing::CCompiler),
+    ("cc: not found", Missing::CCompiler),
+    ("gcc: command not found", Missing::CCompiler),
+    ("linker `cc` not found", Missing::CCompiler),
+    ("no cmake_c_compiler could be found", Missing::CCompiler),
+    ("c compiler cannot create executables", Missing::CCompiler),
+    ("no acceptable c compiler found", Missing::CCompiler),
+    ("g++: command not found", Missing::CxxCompiler),
+    ("c++: command not found", Missing::CxxCompiler),
+    ("c++: not found", Missing::CxxCompiler),
+    ("no cmake_cxx_compiler could be found", Missing::CxxCompiler),
+    ("c++ compiler cannot create executables", Missing::CxxCompiler),
+    ("pkg-config: command not found", Missing::PkgConfig),
+    ("could not find pkg-config", Missing::PkgConfig),
+    ("python.h: no such file", Missing::PythonHeaders),
+    ("openssl/ssl.h: no such file", Missing::OpenSsl),
+    ("openssl/opensslv.h: no such file", Missing::OpenSsl),
+    ("could not find directory of openssl installation", Missing::OpenSsl),
+    ("could not find openssl", Missing::OpenSsl),
+    ("zlib.h: no such file", Missing::Zlib),
+    ("could not find zlib", Missing::Zlib),
+    ("cannot find -lz:", Missing::Zlib),
+];
+
+impl Missing {
+    // the package for pacman, apt and dnf
+    fn packages(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            Missing::CCompiler => ("base-devel", "build-essential", "gcc"),
+            Missing::CxxCompiler => ("base-devel", "build-essential", "gcc-c++"),
+            Missing::PkgConfig => ("pkgconf", "pkg-config", "pkgconf-pkg-config"),
+            Missing::PythonHeaders => ("python", "python3-dev", "python3-devel"),
+            Missing::OpenSsl => ("openssl", "libssl-dev", "openssl-devel"),
+            Missing::Zlib => ("zlib", "zlib1g-dev", "zlib-devel"),
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Missing::CCompiler => "a C compiler",
+            Missing::CxxCompiler => "a C++ compiler",
+            Missing::PkgConfig => "pkg-config",
+            Missing::PythonHeaders => "the Python development headers",
+            Missing::OpenSsl => "the OpenSSL development headers",
+            Missing::Zlib => "the zlib development headers",
+        }
+    }
+}
+
+pub fn missing(output: &str) -> Vec<Missing> {
+    let output = output.to_lowercase();
+    let mut found = Vec::new();
+    for (pattern, missing) in PATTERNS {
+        if output.contains(pattern) && !found.contains(&missing) {
+            found.push(missing);
+        }
+    }
+    found
+}
+
+// pkg-config's "No package 'foo' found" and meson's "Dependency "foo" not found"
+pub fn missing_modules(output: &str) -> Vec<String> {
+    let mut modules: Vec<String> = Vec::new();
+    for line in output.lines() {
+        let module = [("No package '", "' found"), ("Package '", "', required by"), ("Dependency \"", "\" not found")]
+            .iter()
+            .find_map(|(start, end)| {
+                let rest = &line[line.find(start)? + start.len()..];
+                Some(&rest[..rest.find(end)?])
+            });
+        if let Some(module) = module.filter(|m| !m.is_empty() && !modules.iter().any(|known| known == m)) {
+            modules.push(module.to_string());
+        }
+    }
+    modules
+}
+
+// one line per thing the output says is missing
+pub fn suggestions(output: &str, manager: Option<PackageManager>) -> Vec<String> {
+    let mut lines = Vec::new();
+    for missing in missing(output) {
+        let (pacman, apt, dnf) = missing.packages();
+        lines.push(match manager {
+            Some(manager) => {
+                let package = match manager {
+                    PackageManager::Pacman => pacman,
+                    PackageManager::Apt => apt,
+                    PackageManager::Dnf => dnf,
+                };
+                format!("this usually means you need to install {} ({} {})", package, manager.install_command(), package)
+            }
+            None => format!("this usually means {} is missing", missing.description()),
+        });
+    }
+    for module in missing_modules(output) {
+        let package = match manager {
+            Some(PackageManager::Apt) => format!("the -dev package providing {}.pc, often lib{}-dev", module, module.trim_start_matches("lib")),
+            Some(PackageManager::Dnf) => format!("{}-devel", module),
+            _ => format!("the package providing {}.pc", module),
+        };
+        lines.push(format!("the build could not find {}; install {}", module, package));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognises_missing_compilers() {
+        assert_eq!(missing("make: cc: command not found\nmake: *** [Makefile:3: all] Error 127"), [Missing::CCompiler]);
+        assert_eq!(missing("error: linker `cc` not found\n  = note: No such file or directory"), [Missing::CCompiler]);
+        assert_eq!(missing("configure: error: C compiler cannot create executables\nSee `config.log'"), [Missing::CCompiler]);
+        assert_eq!(missing("configure: error: no acceptable C compiler found in $PATH"), [Missing::CCompiler]);
+        assert_eq!(missing("CMake Error at CMakeLists.txt:2 (project):\n  No CMAKE_CXX_COMPILER could be found."), [Missing::CxxCompiler]);
+        assert_eq!(missing("/bin/sh: 1: g++: command not found"), [Missing::CxxCompiler]);
+    }
+
+    #[test]
+    fn recognises_missing_headers_and_tools() {
+        assert_eq!(missing("src/main.c:1:10: fatal error: openssl/ssl.h: No such file or directory"), [Missing::OpenSsl]);
+        assert_eq!(
+            missing("  Could not find directory of OpenSSL installation, and this `-sys` crate cannot proceed"),
+            [Missing::OpenSsl]
+        );
+        assert_eq!(missing("ext.c:2:10: fatal error: Python.h: No such file or directory"), [Missing::PythonHeaders]);
+        assert_eq!(missing("deflate.c:4:10: fatal error: zlib.h: No such file or directory"), [Missing::Zlib]);
+        assert_eq!(missing("/usr/bin/ld: cannot find -lz: No such file or directory"), [Missing::Zlib]);
+        assert_eq!(missing("./configure: line 12: pkg-config: command not found"), [Missing::PkgConfig]);
+    }
+
+    #[test]
+    fn each_kind_is_reported_once_in_table_order() {
+        let output = "zlib.h: No such file or directory\ncc: command not found\ngcc: command not found\nzlib.h: No such file";
+        assert_eq!(missing(output), [Missing::CCompiler, Missing::Zlib]);
+    }
+
+    #[test]
+    fn ordinary_failures_match_nothing() {
+        assert!(missing("error[E0308]: mismatched types\nerror: could not compile `foo`").is_empty());
+        assert!(missing("undefined reference to `main'\ncollect2: error: ld returned 1 exit status").is_empty());
+        assert!(missing("").is_empty());
+    }
+
+    #[test]
+    fn finds_missing_pkg_config_modules() {
+        let output = "Package libfoo was not found in the pkg-config search path.\n\
+                      No package 'libfoo' found\n\
+                      Run-time dependency gtk4 found: NO\n\
+                      meson.build:5:0: ERROR: Dependency \"gtk4\" not found, tried pkgconfig\n\
+                      No package 'libfoo' found";
+        assert_eq!(missing_modules(output), ["libfoo", "gtk4"]);
+        assert_eq!(missing_modules("Package 'glib-2.0', required by 'gio-2.0', not found"), ["glib-2.0"]);
+        assert!(missing_modules("No package '' found").is_empty());
+    }
+
+    #[test]
+    fn suggests_the_package_for_the_distro() {
+        let output = "fatal error: openssl/ssl.h: No such file or directory";
+        assert_eq!(
+            suggestions(output, Some(PackageManager::Pacman)),
+            ["this usually means you need to install openssl (sudo pacman -S --needed openssl)"]
+        );
+        assert_eq!(
+            suggestions(output, Some(PackageManager::Apt)),
+            ["this usually means you need to install libssl-dev (sudo apt install libssl-dev)"]
+        );
+        assert_eq!(
+            suggestions("cc: command not found", Some(PackageManager::Dnf)),
+            ["this usually means you need to install gcc (sudo dnf install gcc)"]
+        );
+        assert_eq!(suggestions("cc: command not found", None), ["this usually means a C compiler is missing"]);
+    }
+
+    #[test]
+    fn suggests_dev_packages_for_modules() {
+        let output = "No package 'libxml-2.0' found";
+        assert_eq!(
+            suggestions(output, Some(PackageManager::Apt)),
+            ["the build could not find libxml-2.0; install the -dev package providing libxml-2.0.pc, often libxml-2.0-dev"]
+        );
+        assert_eq!(suggestions(output, Some(PackageManager::Dnf)), ["the build could not find libxml-2.0; install libxml-2.0-devel"]);
+        assert_eq!(suggestions(output, None), ["the build could not find libxml-2.0; install the package providing libxml-2.0.pc"]);
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use ansi_term::Colour::Yellow;
+use serde::{Deserialize, Serialize};
+use crate::distro;
+use crate::state::{self, State};
+use crate::utils;
+
+// one line of history.jsonl per command that changed what is installed
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Entry {
+    pub at: u64,
+    pub command: Vec<String>,
+    // "install", "downgrade" or "undo"
+    pub action: String,
+    pub packages: Vec<Change>,
+    // for "undo", the `at` of the entry it reverted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undoes: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Change {
+    pub name: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    // "installed", "upgraded", "failed", "restored", ...
+    pub outcome: String,
+}
+
+impl Change {
+    pub fn new(name: &str, before: Option<String>, after: Option<String>, outcome: &str) -> Change {
+        Change { name: name.to_string(), before, after, outcome: outcome.to_string() }
+    }
+}
+
+pub fn path() -> PathBuf {
+    state::state_dir().join("history.jsonl")
+}
+
+// appends the entry as a single line and syncs it to disk; a process killed halfway can leave at
+// most a partial last line, which `read` skips
+pub fn record(action: &str, packages: Vec<Change>) {
+    append(action, packages, None);
+}
+
+pub fn record_undo(undone: u64, packages: Vec<Change>) {
+    append("undo", packages, Some(undone));
+}
+
+fn append(action: &str, packages: Vec<Change>, undoes: Option<u64>) {
+    if packages.is_empty() {
+        return;
+    }
+    // however aurora was started, it reads as the command to run again
+    let command = std::iter::once("aurora".to_string()).chain(std::env::args().skip(1)).collect();
+    let entry = Entry { at: utils::unix_now(), command, action: action.to_string(), packages, undoes };
+    let line = serde_json::to_string(&entry).expect("Failed to serialize history entry");
+    let written = fs::create_dir_all(state::state_dir()).and_then(|_| append_line(&path(), &line));
+    if let Err(e) = written {
+        println!("{} Could not write to {}: {}", Yellow.paint("~> Warning:"), path().display(), e);
+    }
+}
+
+// after the partial line of a killed process, the entry starts a line of its own
+fn append_line(path: &Path, line: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).read(true).append(true).open(path)?;
+    let mut last = [b'\n'];
+    if file.metadata()?.len() > 0 {
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+    }
+    let mut record = String::new();
+    if last[0] != b'\n' {
+        record.push('\n');
+    }
+    record.push_str(line);
+    record.push('\n');
+    file.write_all(record.as_bytes())?;
+    file.sync_all()
+}
+
+// the newest entry no undo has reverted yet; undos themselves aren't undone, so running undo
+// again goes further back
+pub fn last_undoable() -> Option<Entry> {
+    let entries = read();
+    let undone: Vec<u64> = entries.iter().filter_map(|entry| entry.undoes).collect();
+    entries.into_iter().rev().find(|entry| entry.action != "undo" && !undone.contains(&entry.at))
+}
+
+// oldest first
+pub fn read() -> Vec<Entry> {
+    read_from(&path())
+}
+
+fn read_from(path: &Path) -> Vec<Entry> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+// what is installed now: pacman's version for packages built with makepkg, else the version the
+// binary printed or the commit it was built from
+pub fn versions() -> BTreeMap<String, String> {
+    let mut versions: BTreeMap<String, String> = State::load()
+        .packages
+        .into_iter()
+        .filter_map(|(name, record)| {
+            let version = record.version.or_else(|| record.commit.map(|commit| utils::short_commit(&commit).to_string()))?;
+            Some((name, version))
+        })
+        .collect();
+    versions.extend(distro::foreign());
+    versions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(at: u64) -> String {
+        let entry = Entry {
+            at,
+            command: vec!["aurora".to_string(), "install".to_string(), "foo".to_string()],
+            action: "install".to_string(),
+            packages: vec![Change::new("foo", None, Some("1.0".to_string()), "installed")],
+            undoes: None,
+        };
+        serde_json::to_string(&entry).unwrap()
+    }
+
+    #[test]
+    fn entries_after_a_partial_line_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.jsonl");
+        append_line(&path, &entry(1)).unwrap();
+        append_line(&path, &entry(2)).unwrap();
+        // killed halfway through the second entry
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, &content[..content.len() - 10]).unwrap();
+
+        append_line(&path, &entry(3)).unwrap();
+        let read: Vec<u64> = read_from(&path).iter().map(|entry| entry.at).collect();
+        assert_eq!(read, [1, 3]);
+        assert!(fs::read_to_string(&path).unwrap().ends_with("}\n"));
+    }
+}
@@ -0,0 +1,64 @@
+use std::env;
+use std::fmt;
+use std::sync::OnceLock;
+use ansi_term::Colour::Blue;
+use tracing::level_filters::LevelFilter;
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::EnvFilter;
+
+// diagnostics below the normal output: -v shows debug events, -vv trace events, and RUST_LOG
+// (e.g. `aurora::aur=trace,debug`) picks levels per module
+
+static VERBOSE: OnceLock<bool> = OnceLock::new();
+
+pub fn init(verbosity: u8, json: bool) {
+    // errors, warnings and progress are printed as the regular output; as events they are only
+    // wanted by whoever reads the json
+    let default = match verbosity {
+        0 if json => LevelFilter::INFO,
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+    let mut filter = EnvFilter::builder()
+        .with_default_directive(default.into())
+        .parse_lossy(env::var("RUST_LOG").unwrap_or_default());
+    if verbosity > 0 {
+        // -v still wins over a quieter RUST_LOG
+        filter = filter.add_directive(default.into());
+    }
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter).with_writer(std::io::stderr);
+    let _ = match json {
+        true => builder.json().flatten_event(true).with_current_span(true).with_span_list(false).try_init(),
+        false => builder.event_format(Plain).try_init(),
+    };
+    let _ = VERBOSE.set(tracing::enabled!(target: "aurora", tracing::Level::DEBUG));
+}
+
+// whether -v was given, for the extra output of --verbose
+pub fn verbose() -> bool {
+    VERBOSE.get().copied().unwrap_or(false)
+}
+
+// `~> debug aur: message`, in the style of the rest of the output
+struct Plain;
+
+impl<S, N> FormatEvent<S, N> for Plain
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let metadata = event.metadata();
+        let target = metadata.target();
+        let module = target.strip_prefix("aurora::").unwrap_or(target);
+        let level = metadata.level().as_str().to_ascii_lowercase();
+        write!(writer, "{} ", Blue.paint(format!("~> {} {}:", level, module)))?;
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
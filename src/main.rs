@@ -1,6 +1,30 @@
+mod aur;
+mod build_info;
+mod build_log;
+mod build_system;
 mod cli;
 mod commands;
+mod config;
+mod distro;
+mod hints;
+mod journal;
+mod log;
+mod manifest;
+mod notify;
+mod paths;
+mod pkgbuild;
+mod registry;
+mod runner;
+mod sandbox;
+mod sha256;
+mod sources;
+mod state;
+mod stats;
+mod sudo;
+mod trust;
+mod units;
 mod utils;
+mod version;
 
 use clap::Parser;
 use cli::Cli;
@@ -9,7 +33,66 @@ use cli::Commands;
 fn main() {
     let cli = Cli::parse();
 
+    #[cfg(windows)]
+    let _ = ansi_term::enable_ansi_support();
+
+    let json = match &cli.command {
+        Commands::Install(args) | Commands::Build(args) | Commands::Shell(args) => args.json,
+        Commands::Deps(args) => args.json,
+        _ => false,
+    };
+    log::init(cli.verbose, json);
+    aur::set_refresh(cli.refresh);
+
     match cli.command {
-        Commands::Install(args) => commands::install::install(&args.packages, &args.flags, args.yes),
+        Commands::Install(args) => {
+            runner::set_dry_run(args.dry_run);
+            commands::install::install(&args)
+        }
+        Commands::Build(mut args) => {
+            if args.from_build {
+                eprintln!("{} --from-build only applies to aurora install", ansi_term::Colour::Red.paint("Error:"));
+                std::process::exit(2);
+            }
+            args.build_only = true;
+            runner::set_dry_run(args.dry_run);
+            commands::install::install(&args)
+        }
+        Commands::Shell(mut args) => {
+            if args.dry_run || args.from_build {
+                eprintln!("{} aurora shell builds for real; --dry-run and --from-build don't apply", ansi_term::Colour::Red.paint("Error:"));
+                std::process::exit(2);
+            }
+            args.shell = true;
+            runner::set_dry_run(false);
+            commands::install::install(&args)
+        }
+        Commands::Fetch { packages } => commands::fetch::fetch(&packages),
+        Commands::List { orphans } => commands::list::list(orphans),
+        Commands::Search(args) => commands::search::search(&args),
+        Commands::Info { package } => commands::search::info(&package),
+        Commands::External(query) => commands::search::search(&cli::SearchArgs { query, interactive: true, ..Default::default() }),
+        Commands::Deps(args) => commands::deps::deps(&args),
+        Commands::Preview { package, file } => commands::preview::preview(&package, &file),
+        Commands::Comments { package, count } => commands::comments::comments(&package, count),
+        Commands::Why { package } => commands::why::why(&package),
+        Commands::History { limit, package, json } => commands::history::history(limit, package.as_deref(), json),
+        Commands::Undo { yes } => commands::undo::undo(yes),
+        Commands::Diff { package } => commands::diff::diff(&package),
+        Commands::Logs { package, raw } => commands::logs::logs(&package, raw),
+        Commands::Verify { packages, json } => commands::verify::verify(&packages, json),
+        Commands::CheckRebuild => commands::rebuild::check_rebuild(),
+        Commands::CheckUpdates { devel, json } => commands::updates::check_updates(devel, json),
+        Commands::Downgrade { package } => commands::downgrade::downgrade(&package),
+        Commands::Pin { package } => commands::pin::pin(&package),
+        Commands::Unpin { package } => commands::pin::unpin(&package),
+        Commands::Check { path } => commands::check::check(path),
+        Commands::Config { action } => commands::config::config(action),
+        Commands::Init(args) => commands::init::init(&args),
+        Commands::Clean { rpc_cache, trust } => commands::clean::clean(rpc_cache, trust),
+        Commands::Version { json: true } => println!("{}", serde_json::to_string_pretty(&build_info::json()).expect("Failed to serialize version")),
+        Commands::Version { json: false } => println!("{}", build_info::long()),
+        Commands::SelfUpdate { check } => commands::self_update::self_update(check),
+        Commands::GenerateMan { out_dir } => commands::man::generate_man(&out_dir),
     }
 }
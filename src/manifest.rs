@@ -0,0 +1,462 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use ansi_term::Colour::Yellow;
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use serde_json::Value;
+use crate::build_system::BuildSystem;
+use crate::config;
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+// in order of precedence
+pub const MANIFEST_FILES: [&str; 4] = ["aurora.toml", "aurora.yaml", "aurora.yml", "aurora.json"];
+
+#[derive(Deserialize, Default)]
+pub struct AuroraManifest {
+    #[serde(default)]
+    pub aurora: Requirements,
+    #[serde(default, deserialize_with = "build_system")]
+    pub build_system: Option<BuildSystem>,
+    pub binary: Option<String>,
+    #[serde(default)]
+    pub flags: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub target: Vec<String>,
+    pub preset: Option<String>,
+    pub install_cmd: Option<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub no_default_features: bool,
+    #[serde(default)]
+    pub pre_build: Vec<String>,
+    #[serde(default)]
+    pub post_install: Vec<String>,
+    // system packages the build needs, per distro family: "arch", "debian" or "fedora"
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, Vec<String>>,
+    // what the installed binary is run with to check it works, --version by default
+    pub version_args: Option<Vec<String>>,
+    // systemd user units to install, relative to the build directory; found by themselves if empty
+    #[serde(default)]
+    pub units: Vec<String>,
+    #[serde(flatten)]
+    pub unknown: BTreeMap<String, Value>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct Requirements {
+    pub min_version: Option<String>,
+    pub schema: Option<u32>,
+    #[serde(flatten)]
+    pub unknown: BTreeMap<String, Value>,
+}
+
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    let found: Vec<PathBuf> = MANIFEST_FILES
+        .iter()
+        .map(|f| dir.join(f))
+        .filter(|p| p.is_file())
+        .collect();
+
+    if found.len() > 1 {
+        let names: Vec<String> = found
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        println!(
+            "{} Found {}, using {}",
+            Yellow.paint("~> Warning:"),
+            names.join(", "),
+            names[0]
+        );
+    }
+    found.into_iter().next()
+}
+
+// ~/.config/aurora/recipes/<package>.{toml,json}, the user's own overrides
+pub fn local_recipe(package: &str) -> Option<PathBuf> {
+    let dir = config::config_dir().join("recipes");
+    ["toml", "json"]
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", package, ext)))
+        .find(|p| p.is_file())
+}
+
+// top-level fields of `overlay` replace those in `base`; returns the replaced field names
+pub fn merge(base: &mut Value, overlay: Value) -> Vec<String> {
+    let mut replaced = Vec::new();
+    if let (Some(base), Value::Object(overlay)) = (base.as_object_mut(), overlay) {
+        for (key, value) in overlay {
+            replaced.push(key.clone());
+            base.insert(key, value);
+        }
+    }
+    replaced
+}
+
+fn build_system<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<BuildSystem>, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    match BuildSystem::from_name(&name) {
+        Some(build_system) => Ok(Some(build_system)),
+        None => {
+            let valid: Vec<&str> = BuildSystem::ALL.iter().map(|b| b.name()).collect();
+            Err(de::Error::custom(format!(
+                "unknown build system '{}', expected one of: {}",
+                name,
+                valid.join(", ")
+            )))
+        }
+    }
+}
+
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    match OneOrMany::deserialize(deserializer) {
+        Ok(OneOrMany::One(s)) => Ok(vec![s]),
+        Ok(OneOrMany::Many(v)) => Ok(v),
+        Err(_) => Err(de::Error::custom("expected a string or a list of strings")),
+    }
+}
+
+impl AuroraManifest {
+    pub fn load(path: &Path) -> Result<AuroraManifest, String> {
+        let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => AuroraManifest::from_toml(&content),
+            Some("yaml") | Some("yml") => AuroraManifest::from_yaml(&content),
+            _ => AuroraManifest::from_json(&content),
+        }
+    }
+
+    // the untyped contents of a manifest, for merging several sources
+    pub fn read_value(path: &Path) -> Result<Value, String> {
+        let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let value: Value = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(&content).map_err(|e| e.to_string())?,
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&content).map_err(|e| e.to_string())?,
+            _ => serde_json::from_str(&content).map_err(|e| e.to_string())?,
+        };
+        if !value.is_object() {
+            return Err("expected a table of fields".to_string());
+        }
+        Ok(value)
+    }
+
+    pub fn from_value(value: Value) -> Result<AuroraManifest, String> {
+        check_requirements(&value)?;
+        serde_json::from_value(value.clone()).map_err(|e| describe_error(&value, &e.to_string()))
+    }
+
+    pub fn from_json(content: &str) -> Result<AuroraManifest, String> {
+        let value: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+        check_requirements(&value)?;
+        serde_json::from_str(content).map_err(|e| describe_error(&value, &e.to_string()))
+    }
+
+    pub fn from_toml(content: &str) -> Result<AuroraManifest, String> {
+        let value: Value = toml::from_str(content).map_err(|e| e.to_string())?;
+        check_requirements(&value)?;
+        toml::from_str(content).map_err(|e| describe_error(&value, e.to_string().trim_end()))
+    }
+
+    pub fn from_yaml(content: &str) -> Result<AuroraManifest, String> {
+        let value: Value = serde_yaml::from_str(content).map_err(|e| e.to_string())?;
+        check_requirements(&value)?;
+        serde_yaml::from_str(content).map_err(|e| describe_error(&value, &e.to_string()))
+    }
+
+    pub fn unknown_fields(&self) -> Vec<String> {
+        self.unknown
+            .keys()
+            .cloned()
+            .chain(self.aurora.unknown.keys().map(|k| format!("aurora.{}", k)))
+            .collect()
+    }
+}
+
+// checked before anything else, since a newer manifest may not parse into this version's schema
+fn check_requirements(value: &Value) -> Result<(), String> {
+    let requirements = &value["aurora"];
+
+    if let Some(schema) = requirements.get("schema") {
+        match schema.as_u64() {
+            Some(n) if n <= SCHEMA_VERSION as u64 && n > 0 => {}
+            Some(n) => {
+                return Err(format!(
+                    "manifest schema {} is not supported by aurora {} (supported: {}), upgrade aurora to build this package",
+                    n,
+                    env!("CARGO_PKG_VERSION"),
+                    SCHEMA_VERSION
+                ))
+            }
+            None => return Err("aurora.schema: expected a positive integer".to_string()),
+        }
+    }
+
+    if let Some(min_version) = requirements.get("min_version") {
+        let min_version = min_version
+            .as_str()
+            .ok_or("aurora.min_version: expected a version string")?;
+        let current = env!("CARGO_PKG_VERSION");
+        match compare_versions(current, min_version) {
+            Some(Ordering::Less) => {
+                return Err(format!(
+                    "this manifest requires aurora {} or newer, but {} is running; upgrade aurora to build this package",
+                    min_version, current
+                ))
+            }
+            Some(_) => {}
+            None => return Err(format!("aurora.min_version: '{}' is not a valid version", min_version)),
+        }
+    }
+    Ok(())
+}
+
+// semver ordering; missing minor/patch components count as 0 and a pre-release sorts first
+pub fn compare_versions(a: &str, b: &str) -> Option<Ordering> {
+    fn parse(version: &str) -> Option<(Vec<u64>, Option<&str>)> {
+        let version = version.trim().trim_start_matches('v');
+        let version = version.split('+').next()?;
+        let (core, pre) = match version.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (version, None),
+        };
+        let mut parts: Vec<u64> = core.split('.').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+        if parts.is_empty() || parts.len() > 3 {
+            return None;
+        }
+        parts.resize(3, 0);
+        Some((parts, pre))
+    }
+
+    let (a_core, a_pre) = parse(a)?;
+    let (b_core, b_pre) = parse(b)?;
+    Some(a_core.cmp(&b_core).then_with(|| match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => compare_prerelease(a, b),
+    }))
+}
+
+fn compare_prerelease(a: &str, b: &str) -> Ordering {
+    let mut a_ids = a.split('.');
+    let mut b_ids = b.split('.');
+    loop {
+        match (a_ids.next(), b_ids.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => {
+                // numeric identifiers compare numerically and sort before alphanumeric ones
+                let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+                    (Ok(x), Ok(y)) => x.cmp(&y),
+                    (Ok(_), Err(_)) => Ordering::Less,
+                    (Err(_), Ok(_)) => Ordering::Greater,
+                    (Err(_), Err(_)) => x.cmp(y),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
+
+// serde only reports where an error happened, so find the offending field by
+// deserializing each one on its own
+fn describe_error(value: &Value, message: &str) -> String {
+    let fields = match value.as_object() {
+        Some(fields) => fields,
+        None => return message.to_string(),
+    };
+
+    let fails = |key: &str, v: &Value| {
+        let single = Value::Object([(key.to_string(), v.clone())].into_iter().collect());
+        serde_json::from_value::<AuroraManifest>(single).err()
+    };
+
+    for (key, v) in fields {
+        if fails(key, v).is_none() {
+            continue;
+        }
+
+        let path = match v {
+            Value::Array(items) => items
+                .iter()
+                .position(|item| fails(key, &Value::Array(vec![item.clone()])).is_some())
+                .map(|i| format!("{}[{}]", key, i)),
+            Value::Object(entries) => entries
+                .iter()
+                .find(|(k, item)| {
+                    let single = [(k.to_string(), (*item).clone())].into_iter().collect();
+                    fails(key, &Value::Object(single)).is_some()
+                })
+                .map(|(k, _)| format!("{}.{}", key, k)),
+            _ => None,
+        }
+        .unwrap_or_else(|| key.clone());
+
+        return format!("{}: {}", path, message);
+    }
+    message.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pre_releases_sort_before_the_release() {
+        assert_eq!(compare_versions("1.0.0-alpha", "1.0.0"), Some(Ordering::Less));
+        assert_eq!(compare_versions("1.0.0-alpha", "1.0.0-alpha.1"), Some(Ordering::Less));
+        assert_eq!(compare_versions("1.0.0-alpha.1", "1.0.0-alpha.beta"), Some(Ordering::Less));
+        assert_eq!(compare_versions("1.0.0-beta.2", "1.0.0-beta.11"), Some(Ordering::Less));
+        assert_eq!(compare_versions("1.0.0-beta.11", "1.0.0-rc.1"), Some(Ordering::Less));
+        assert_eq!(compare_versions("1.0.0-rc.1", "1.0.0-rc.1"), Some(Ordering::Equal));
+    }
+
+    #[test]
+    fn missing_components_count_as_zero() {
+        assert_eq!(compare_versions("1", "1.0.0"), Some(Ordering::Equal));
+        assert_eq!(compare_versions("1.2", "1.2.0"), Some(Ordering::Equal));
+        assert_eq!(compare_versions("1.2", "1.10"), Some(Ordering::Less));
+        assert_eq!(compare_versions("0.1.1", "0.1"), Some(Ordering::Greater));
+    }
+
+    #[test]
+    fn prefix_and_build_metadata_are_ignored() {
+        assert_eq!(compare_versions("v1.2.3", "1.2.3"), Some(Ordering::Equal));
+        assert_eq!(compare_versions("1.2.3+build.5", "1.2.3+other"), Some(Ordering::Equal));
+        assert_eq!(compare_versions("1.2.3-rc.1+build", "1.2.3"), Some(Ordering::Less));
+        assert_eq!(compare_versions(" 2.0 ", "v1.9"), Some(Ordering::Greater));
+    }
+
+    #[test]
+    fn invalid_versions_do_not_compare() {
+        for invalid in ["", "v", "1.x", "1..2", "1.2.3.4", "latest", "-1.0"] {
+            assert_eq!(compare_versions(invalid, "1.0"), None, "{:?}", invalid);
+            assert_eq!(compare_versions("1.0", invalid), None, "{:?}", invalid);
+        }
+    }
+
+    const VALID: &str = r#"{
+        "aurora": { "schema": 1, "min_version": "0.1" },
+        "build_system": "cargo",
+        "flags": ["--locked"],
+        "target": "x86_64-unknown-linux-musl",
+        "env": { "RUSTFLAGS": "-Cstrip=symbols" },
+        "dependencies": { "arch": ["openssl"] }
+    }"#;
+
+    #[test]
+    fn a_valid_manifest_loads() {
+        let manifest = AuroraManifest::from_json(VALID).unwrap();
+        assert_eq!(manifest.aurora.schema, Some(SCHEMA_VERSION));
+        assert_eq!(manifest.build_system.map(|b| b.name()), Some("cargo"));
+        assert_eq!(manifest.flags, ["--locked"]);
+        assert_eq!(manifest.target, ["x86_64-unknown-linux-musl"]);
+        assert_eq!(manifest.env["RUSTFLAGS"], "-Cstrip=symbols");
+        assert_eq!(manifest.dependencies["arch"], ["openssl"]);
+        assert!(manifest.unknown_fields().is_empty());
+    }
+
+    fn error(manifest: &str) -> String {
+        match AuroraManifest::from_json(manifest) {
+            Ok(_) => panic!("{} loaded", manifest),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn newer_or_broken_requirements_are_refused() {
+        assert!(error(r#"{ "aurora": { "schema": 2 } }"#).starts_with("manifest schema 2 is not supported"));
+        assert!(error(r#"{ "aurora": { "schema": 0 } }"#).starts_with("manifest schema 0 is not supported"));
+        assert_eq!(error(r#"{ "aurora": { "schema": "one" } }"#), "aurora.schema: expected a positive integer");
+        assert!(error(r#"{ "aurora": { "min_version": "99.0" } }"#).starts_with("this manifest requires aurora 99.0 or newer"));
+        assert_eq!(error(r#"{ "aurora": { "min_version": "soon" } }"#), "aurora.min_version: 'soon' is not a valid version");
+        assert_eq!(error(r#"{ "aurora": { "min_version": 1 } }"#), "aurora.min_version: expected a version string");
+    }
+
+    #[test]
+    fn requirements_are_checked_before_the_fields() {
+        // a newer schema may well have fields this one can't read
+        let manifest = r#"{ "aurora": { "schema": 2 }, "flags": "--locked" }"#;
+        assert!(error(manifest).starts_with("manifest schema 2"));
+    }
+
+    #[test]
+    fn invalid_fields_are_named() {
+        assert!(error(r#"{ "build_system": "nope" }"#).starts_with("build_system: unknown build system 'nope', expected one of: "));
+        assert!(error(r#"{ "flags": ["--locked", 1] }"#).starts_with("flags[1]: "));
+        assert!(error(r#"{ "env": { "A": "1", "B": 2 } }"#).starts_with("env.B: "));
+        assert!(error(r#"{ "target": 3 }"#).starts_with("target: expected a string or a list of strings"));
+    }
+
+    #[test]
+    fn unknown_fields_are_kept_aside() {
+        let manifest = AuroraManifest::from_json(r#"{ "colour": true, "aurora": { "nightly": 1 }, "binary": "foo" }"#).unwrap();
+        assert_eq!(manifest.binary.as_deref(), Some("foo"));
+        assert_eq!(manifest.unknown_fields(), ["colour", "aurora.nightly"]);
+    }
+
+    const FORMATS: [(&str, &str); 4] = [
+        ("aurora.toml", "binary = \"foo\"\nflags = [\"--locked\"]\n\n[aurora]\nschema = 1\n"),
+        ("aurora.yaml", "binary: foo\nflags:\n  - --locked\naurora:\n  schema: 1\n"),
+        ("aurora.yml", "binary: foo\nflags: [--locked]\naurora: { schema: 1 }\n"),
+        ("aurora.json", r#"{ "binary": "foo", "flags": ["--locked"], "aurora": { "schema": 1 } }"#),
+    ];
+
+    #[test]
+    fn every_format_loads_the_same() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, content) in FORMATS {
+            let path = dir.path().join(name);
+            fs::write(&path, content).unwrap();
+            let manifest = AuroraManifest::load(&path).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            assert_eq!(manifest.binary.as_deref(), Some("foo"), "{}", name);
+            assert_eq!(manifest.flags, ["--locked"], "{}", name);
+            assert_eq!(manifest.aurora.schema, Some(1), "{}", name);
+            assert_eq!(AuroraManifest::read_value(&path).unwrap()["binary"], "foo", "{}", name);
+        }
+    }
+
+    #[test]
+    fn syntax_errors_and_non_tables_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, content) in [("aurora.toml", "binary = "), ("aurora.yaml", "binary: [foo"), ("aurora.json", "{ \"binary\": ")] {
+            let path = dir.path().join(name);
+            fs::write(&path, content).unwrap();
+            assert!(AuroraManifest::load(&path).is_err(), "{}", name);
+        }
+        let path = dir.path().join("aurora.yml");
+        fs::write(&path, "- foo\n").unwrap();
+        assert_eq!(AuroraManifest::read_value(&path).err().as_deref(), Some("expected a table of fields"));
+    }
+
+    #[test]
+    fn toml_wins_over_yaml_and_yaml_over_json() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(find(dir.path()), None);
+        for (name, content) in FORMATS {
+            fs::write(dir.path().join(name), content).unwrap();
+        }
+        for (name, _) in FORMATS {
+            assert_eq!(find(dir.path()), Some(dir.path().join(name)));
+            fs::remove_file(dir.path().join(name)).unwrap();
+        }
+        assert_eq!(find(dir.path()), None);
+    }
+}
@@ -0,0 +1,64 @@
+use std::env;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use crate::config::Config;
+use crate::runner::Run;
+use crate::utils;
+
+// how long an install has to take before `notify = true` sends a notification
+const DEFAULT_THRESHOLD_SECS: u64 = 60;
+
+// a desktop notification once a long install finishes; nothing happens without a graphical
+// session, and a missing notification daemon is ignored
+pub fn finished(package: &str, success: bool, elapsed: Duration) {
+    let config = Config::load();
+    let threshold = match (config.notify, config.notify_threshold_secs) {
+        (_, Some(secs)) => secs,
+        (true, None) => DEFAULT_THRESHOLD_SECS,
+        (false, None) => return,
+    };
+    if elapsed.as_secs() < threshold || !has_display() {
+        return;
+    }
+
+    let summary = if success {
+        format!("{} installed", package)
+    } else {
+        format!("{} failed to install", package)
+    };
+    send(&summary, &format!("took {}", utils::format_duration(elapsed)), success);
+}
+
+fn has_display() -> bool {
+    if cfg!(target_os = "macos") {
+        return true;
+    }
+    cfg!(unix) && ["DISPLAY", "WAYLAND_DISPLAY"].iter().any(|var| env::var_os(var).is_some_and(|v| !v.is_empty()))
+}
+
+fn send(summary: &str, body: &str, success: bool) {
+    let mut command = if cfg!(target_os = "macos") {
+        let script = format!("display notification {:?} with title \"aurora\" subtitle {:?}", body, summary);
+        let mut osascript = Command::new("osascript");
+        osascript.arg("-e").arg(script);
+        osascript
+    } else if utils::command_exists("notify-send") {
+        let mut notify_send = Command::new("notify-send");
+        notify_send
+            .arg("--app-name=aurora")
+            .arg(format!("--urgency={}", if success { "normal" } else { "critical" }))
+            .arg(summary)
+            .arg(body);
+        notify_send
+    } else if utils::command_exists("gdbus") {
+        let mut gdbus = Command::new("gdbus");
+        gdbus
+            .args(["call", "--session", "--dest=org.freedesktop.Notifications"])
+            .args(["--object-path=/org/freedesktop/Notifications", "--method=org.freedesktop.Notifications.Notify"])
+            .args(["aurora", "0", "", summary, body, "[]", "{}", "-1"]);
+        gdbus
+    } else {
+        return;
+    };
+    let _ = command.stdout(Stdio::null()).stderr(Stdio::null()).output_always();
+}
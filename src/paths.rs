@@ -0,0 +1,135 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use ansi_term::Colour::Red;
+
+pub fn home_dir() -> PathBuf {
+    match dirs::home_dir() {
+        Some(home) if !home.as_os_str().is_empty() => home,
+        _ => {
+            eprintln!(
+                "{} Could not determine your home directory. Set HOME or use --system.",
+                Red.paint("Error:")
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+pub fn data_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| home_dir().join(".local/share"))
+        .join("aurora")
+}
+
+#[cfg(target_os = "macos")]
+pub fn bin_dir() -> PathBuf {
+    home_dir().join("bin")
+}
+
+#[cfg(windows)]
+pub fn bin_dir() -> PathBuf {
+    home_dir().join(".local").join("bin")
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+pub fn bin_dir() -> PathBuf {
+    dirs::executable_dir().unwrap_or_else(|| home_dir().join(".local/bin"))
+}
+
+#[cfg(target_os = "linux")]
+pub fn temp_dir() -> PathBuf {
+    PathBuf::from("/tmp/aurora")
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn temp_dir() -> PathBuf {
+    env::temp_dir().join("aurora")
+}
+
+// packages makepkg built, kept for `aurora downgrade`
+pub fn package_cache_dir(package: &str) -> PathBuf {
+    data_dir().join("packages").join(package)
+}
+
+// the PKGBUILD each package makepkg built was last installed from, for `aurora diff`
+pub fn pkgbuild_copy(package: &str) -> PathBuf {
+    data_dir().join("pkgbuilds").join(package)
+}
+
+// the output of the last build of each package
+pub fn logs_dir() -> PathBuf {
+    data_dir().join("logs")
+}
+
+pub fn builds_dir() -> PathBuf {
+    let builds = temp_dir().join("builds");
+    if !builds.exists() {
+        std::fs::create_dir_all(&builds).expect("Failed to create temp directory");
+    }
+    builds
+}
+
+pub fn expand_tilde(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => home_dir().join(rest),
+        None if path == "~" => home_dir(),
+        None => PathBuf::from(path),
+    }
+}
+
+pub fn in_path(dir: &Path) -> bool {
+    env::var_os("PATH")
+        .map(|path| env::split_paths(&path).any(|p| p == dir))
+        .unwrap_or(false)
+}
+
+pub fn path_hint(dir: &Path) -> String {
+    let shell = env::var("SHELL").unwrap_or_default();
+    let dir = dir.display();
+
+    if shell.ends_with("fish") {
+        format!("fish_add_path {}", dir)
+    } else if shell.ends_with("bash") {
+        format!("echo 'export PATH=\"{}:$PATH\"' >> ~/.bashrc", dir)
+    } else if shell.ends_with("zsh") || cfg!(target_os = "macos") {
+        format!("echo 'export PATH=\"{}:$PATH\"' >> ~/.zshrc", dir)
+    } else {
+        format!("export PATH=\"{}:$PATH\"", dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use super::*;
+
+    #[test]
+    fn tilde_is_the_home_directory() {
+        assert_eq!(expand_tilde("~"), home_dir());
+        assert_eq!(expand_tilde("~/bin"), home_dir().join("bin"));
+        assert_eq!(expand_tilde("/usr/local/bin"), PathBuf::from("/usr/local/bin"));
+        // only the current user's home
+        assert_eq!(expand_tilde("~other/bin"), PathBuf::from("~other/bin"));
+    }
+
+    #[test]
+    fn directories_follow_the_platform() {
+        if cfg!(target_os = "linux") {
+            assert_eq!(temp_dir(), PathBuf::from("/tmp/aurora"));
+        } else {
+            assert_eq!(temp_dir(), env::temp_dir().join("aurora"));
+        }
+        if cfg!(target_os = "macos") {
+            assert_eq!(bin_dir(), home_dir().join("bin"));
+        }
+        assert!(builds_dir().starts_with(temp_dir()));
+        assert!(logs_dir().starts_with(data_dir()) && package_cache_dir("foo").ends_with("packages/foo"));
+    }
+
+    #[test]
+    fn the_configured_destination_wins() {
+        let config = Config { dest: Some("~/bin".to_string()), ..Config::default() };
+        assert_eq!(config.dest_dir(), home_dir().join("bin"));
+        assert_eq!(Config::default().dest_dir(), bin_dir());
+    }
+}
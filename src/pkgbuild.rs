@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+// the parts of .SRCINFO aurora looks at; split packages have their fields merged
+#[derive(Default)]
+pub struct SrcInfo {
+    pub pkgbase: Option<String>,
+    pub pkgver: String,
+    pub pkgrel: String,
+    pub epoch: Option<String>,
+    pub url: Option<String>,
+    pub arch: Vec<String>,
+    pub sources: Vec<String>,
+    // e.g. "sha256sums" -> one entry per source
+    pub checksums: BTreeMap<String, Vec<String>>,
+    pub depends: Vec<String>,
+    pub make_depends: Vec<String>,
+    pub install: Option<String>,
+}
+
+impl SrcInfo {
+    pub fn full_version(&self) -> String {
+        match &self.epoch {
+            Some(epoch) => format!("{}:{}-{}", epoch, self.pkgver, self.pkgrel),
+            None => format!("{}-{}", self.pkgver, self.pkgrel),
+        }
+    }
+}
+
+const CHECKSUMS: [&str; 8] = ["b2sums", "cksums", "md5sums", "sha1sums", "sha224sums", "sha256sums", "sha384sums", "sha512sums"];
+
+pub fn srcinfo(build_dir: &Path) -> Option<SrcInfo> {
+    Some(parse_srcinfo(&fs::read_to_string(build_dir.join(".SRCINFO")).ok()?))
+}
+
+// what split packages built from the same PKGBUILD share; the package itself without a .SRCINFO
+pub fn package_base(build_dir: &Path, package: &str) -> String {
+    srcinfo(build_dir).and_then(|info| info.pkgbase).unwrap_or_else(|| package.to_string())
+}
+
+pub fn parse_srcinfo(content: &str) -> SrcInfo {
+    let mut info = SrcInfo::default();
+    for line in content.lines() {
+        let (key, value) = match line.trim().split_once(" = ") {
+            Some((key, value)) => (key.trim(), value.trim().to_string()),
+            None => continue,
+        };
+        // source_x86_64, depends_aarch64 and so on count like the plain keys
+        let key = match key.split_once('_') {
+            Some((base, _)) if base == "source" || base == "depends" || base == "makedepends" || base.ends_with("sums") => base,
+            _ => key,
+        };
+        let push = |list: &mut Vec<String>| {
+            if !list.contains(&value) {
+                list.push(value.clone());
+            }
+        };
+        match key {
+            "pkgbase" => info.pkgbase = Some(value),
+            "pkgver" => info.pkgver = value,
+            "pkgrel" => info.pkgrel = value,
+            "epoch" => info.epoch = Some(value),
+            "url" => info.url = Some(value),
+            "install" => info.install = Some(value),
+            "arch" => push(&mut info.arch),
+            "source" => info.sources.push(value),
+            "depends" => push(&mut info.depends),
+            "makedepends" => push(&mut info.make_depends),
+            _ if CHECKSUMS.contains(&key) => info.checksums.entry(key.to_string()).or_default().push(value),
+            _ => {}
+        }
+    }
+    info
+}
+
+// the architectures a package builds for, from .SRCINFO or else the PKGBUILD's arch=() array
+pub fn arches(build_dir: &Path) -> Vec<String> {
+    if let Some(info) = srcinfo(build_dir).filter(|info| !info.arch.is_empty()) {
+        return info.arch;
+    }
+
+    let pkgbuild = fs::read_to_string(build_dir.join("PKGBUILD")).unwrap_or_default();
+    let start = match pkgbuild.find("\narch=(").or_else(|| pkgbuild.starts_with("arch=(").then_some(0)) {
+        Some(start) => start + pkgbuild[start..].find('(').unwrap_or(0) + 1,
+        None => return vec![],
+    };
+    let end = pkgbuild[start..].find(')').map(|end| start + end).unwrap_or(pkgbuild.len());
+    pkgbuild[start..end]
+        .split_whitespace()
+        .map(|arch| arch.trim_matches(|c| c == '\'' || c == '"').to_string())
+        .filter(|arch| !arch.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_packages_share_their_base() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(package_base(dir.path(), "foo-git"), "foo-git");
+        let srcinfo = "pkgbase = foo-git\n\tpkgver = 1.0\n\tpkgrel = 1\n\npkgname = foo-git\n\npkgname = foo-git-docs\n";
+        fs::write(dir.path().join(".SRCINFO"), srcinfo).unwrap();
+        assert_eq!(package_base(dir.path(), "foo-git-docs"), "foo-git");
+        assert_eq!(parse_srcinfo(srcinfo).full_version(), "1.0-1");
+    }
+}
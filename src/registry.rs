@@ -0,0 +1,103 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use crate::paths;
+use crate::runner::Run;
+use crate::utils;
+
+const RECIPE_EXTENSIONS: [&str; 3] = ["toml", "yaml", "json"];
+
+pub fn cache_dir() -> PathBuf {
+    paths::data_dir().join("registry")
+}
+
+fn is_git(registry: &str) -> bool {
+    registry.ends_with(".git") || registry.starts_with("git@") || registry.starts_with("git://")
+}
+
+// Ok(None) means the registry was reachable but has no recipe for the package
+pub fn fetch(registry: &str, package: &str) -> Result<Option<PathBuf>, String> {
+    if is_git(registry) {
+        fetch_git(registry, package)
+    } else {
+        fetch_http(registry.trim_end_matches('/'), package)
+    }
+}
+
+fn fetch_git(registry: &str, package: &str) -> Result<Option<PathBuf>, String> {
+    let dir = cache_dir().join("git");
+    let status = if dir.join(".git").is_dir() {
+        Command::new("git")
+            .arg("-C")
+            .arg(&dir)
+            .arg("pull")
+            .arg("--ff-only")
+            .arg("-q")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .run_always()
+    } else {
+        let _ = fs::remove_dir_all(&dir);
+        Command::new("git")
+            .arg("clone")
+            .arg("--depth=1")
+            .arg("-q")
+            .arg(registry)
+            .arg(&dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .run_always()
+    };
+
+    if !status.map(|s| s.success()).unwrap_or(false) {
+        return Err(format!("could not update {}", registry));
+    }
+
+    Ok(RECIPE_EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", package, ext)))
+        .find(|p| p.is_file()))
+}
+
+fn fetch_http(base: &str, package: &str) -> Result<Option<PathBuf>, String> {
+    if !utils::command_exists("curl") {
+        return Err("curl is needed to fetch recipes over https".to_string());
+    }
+
+    let dir = cache_dir().join("http");
+    for ext in RECIPE_EXTENSIONS {
+        let file = dir.join(format!("{}.{}", package, ext));
+        fs::create_dir_all(file.parent().unwrap_or(&dir)).map_err(|e| e.to_string())?;
+
+        match download(&format!("{}/{}.{}", base, package, ext), &file)? {
+            200 => return Ok(Some(file)),
+            404 | 410 => {
+                let _ = fs::remove_file(&file);
+            }
+            code => return Err(format!("{} answered with HTTP {}", base, code)),
+        }
+    }
+    Ok(None)
+}
+
+fn download(url: &str, file: &Path) -> Result<u32, String> {
+    let output = Command::new("curl")
+        .arg("-sSL")
+        .arg("--max-time")
+        .arg("15")
+        .arg("-o")
+        .arg(file)
+        .arg("-w")
+        .arg("%{http_code}")
+        .arg(url)
+        .output_always()
+        .map_err(|e| e.to_string())?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .map_err(|_| format!("unexpected response from {}", url))
+}
@@ -0,0 +1,316 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::io::{self, Read, Write};
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use crate::build_log::{self, Stream};
+use crate::log;
+use crate::sandbox;
+use tracing::{debug, trace};
+
+static DRY_RUN: OnceLock<bool> = OnceLock::new();
+// set by --clean-env: the variables besides CLEAN_ENV that build commands may see
+static PASSTHROUGH: OnceLock<Vec<String>> = OnceLock::new();
+static PRIORITY: OnceLock<Priority> = OnceLock::new();
+
+// what build commands are started with, from --nice and --ionice
+#[derive(Clone, Copy)]
+pub struct Priority {
+    pub nice: Option<i32>,
+    // the idle I/O class, like ionice -c3
+    pub idle_io: bool,
+}
+
+// what every build needs to find its tools and write to the terminal
+const CLEAN_ENV: [&str; 4] = ["PATH", "HOME", "LANG", "TERM"];
+// programs on windows can't even start without these
+#[cfg(windows)]
+const PLATFORM_ENV: [&str; 6] = ["SYSTEMROOT", "SYSTEMDRIVE", "TEMP", "TMP", "PATHEXT", "USERPROFILE"];
+#[cfg(not(windows))]
+const PLATFORM_ENV: [&str; 0] = [];
+static PLAN: Mutex<Vec<PlannedCommand>> = Mutex::new(Vec::new());
+
+#[derive(Serialize, Clone)]
+pub struct PlannedCommand {
+    pub argv: Vec<String>,
+    pub cwd: Option<String>,
+    pub env: BTreeMap<String, String>,
+}
+
+impl PlannedCommand {
+    fn from_command(cmd: &Command) -> PlannedCommand {
+        let argv = std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect();
+        let env = cmd
+            .get_envs()
+            .filter_map(|(k, v)| Some((k.to_string_lossy().into_owned(), v?.to_string_lossy().into_owned())))
+            .collect();
+        PlannedCommand {
+            argv,
+            cwd: cmd.get_current_dir().map(|d| d.to_string_lossy().into_owned()),
+            env,
+        }
+    }
+
+    // a line that can be pasted into a shell, like `set -x` output
+    pub fn shell_line(&self) -> String {
+        let mut words = Vec::new();
+        for (key, value) in &self.env {
+            words.push(format!("{}={}", key, shell_quote(value)));
+        }
+        words.extend(self.argv.iter().map(|a| shell_quote(a)));
+
+        match &self.cwd {
+            Some(cwd) => format!("cd {} && {}", shell_quote(cwd), words.join(" ")),
+            None => words.join(" "),
+        }
+    }
+}
+
+pub fn set_dry_run(dry_run: bool) {
+    let _ = DRY_RUN.set(dry_run);
+}
+
+pub fn dry_run() -> bool {
+    DRY_RUN.get().copied().unwrap_or(false)
+}
+
+pub fn set_clean_env(passthrough: Vec<String>) {
+    let mut kept: Vec<&str> = CLEAN_ENV.iter().chain(&PLATFORM_ENV).copied().collect();
+    kept.extend(passthrough.iter().map(|name| name.as_str()));
+    debug!("building with a clean environment, passing through {}", kept.join(", "));
+    let _ = PASSTHROUGH.set(passthrough);
+}
+
+pub fn set_priority(priority: Priority) {
+    if let Some(nice) = priority.nice {
+        debug!("builds run with niceness {}", nice);
+    }
+    if priority.idle_io {
+        debug!("builds run in the idle I/O class");
+    }
+    let _ = PRIORITY.set(priority);
+}
+
+// the priority is set in the child before it starts the build, and whatever it starts inherits it
+#[cfg(unix)]
+pub fn deprioritize(cmd: &mut Command) {
+    use std::os::unix::process::CommandExt;
+
+    let Some(priority) = PRIORITY.get().copied() else {
+        return;
+    };
+    // only system calls between fork and exec; a failure just leaves the priority as it was
+    unsafe {
+        cmd.pre_exec(move || {
+            if let Some(nice) = priority.nice {
+                libc::setpriority(libc::PRIO_PROCESS, 0, nice);
+            }
+            #[cfg(target_os = "linux")]
+            if priority.idle_io {
+                // IOPRIO_WHO_PROCESS, and IOPRIO_CLASS_IDLE shifted by IOPRIO_CLASS_SHIFT
+                libc::syscall(libc::SYS_ioprio_set, 1, 0, 3 << 13);
+            }
+            Ok(())
+        });
+    }
+}
+
+// windows has priority classes instead of niceness, and no I/O classes to pick
+#[cfg(windows)]
+pub fn deprioritize(cmd: &mut Command) {
+    use std::os::windows::process::CommandExt;
+
+    const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x4000;
+    if PRIORITY.get().is_some_and(|priority| priority.nice.is_some_and(|nice| nice > 0)) {
+        cmd.creation_flags(BELOW_NORMAL_PRIORITY_CLASS);
+    }
+}
+
+// with --clean-env, drops everything the command would inherit but the CLEAN_ENV variables and
+// the passthroughs; what was set on the command itself (the build and recipe env) stays
+pub fn sanitize(cmd: &mut Command) {
+    let Some(passthrough) = PASSTHROUGH.get() else {
+        return;
+    };
+    let explicit: Vec<(OsString, Option<OsString>)> =
+        cmd.get_envs().map(|(key, value)| (key.to_os_string(), value.map(|v| v.to_os_string()))).collect();
+    cmd.env_clear();
+    for name in CLEAN_ENV.iter().chain(&PLATFORM_ENV).copied().chain(passthrough.iter().map(|name| name.as_str())) {
+        if let Some(value) = env::var_os(name) {
+            cmd.env(name, value);
+        }
+    }
+    for (key, value) in explicit {
+        match value {
+            Some(value) => cmd.env(key, value),
+            None => cmd.env_remove(key),
+        };
+    }
+}
+
+// quotes a word for a POSIX shell, leaving plain words alone
+pub fn shell_quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+// commands recorded instead of run so far
+pub fn take_plan() -> Vec<PlannedCommand> {
+    std::mem::take(&mut *PLAN.lock().unwrap())
+}
+
+fn echo(cmd: &Command) {
+    if tracing::enabled!(tracing::Level::DEBUG) {
+        eprintln!("+ {}", PlannedCommand::from_command(cmd).shell_line());
+    }
+}
+
+fn exited(cmd: &Command, status: &ExitStatus) {
+    debug!("{} exited with {}", cmd.get_program().to_string_lossy(), status);
+}
+
+// every spawn goes through here so verbose mode can echo it; the plain
+// variants only record the command in dry-run mode, the `_always` ones run
+// regardless because detection depends on them
+pub trait Run {
+    fn run(&mut self) -> io::Result<ExitStatus>;
+    fn run_output(&mut self) -> io::Result<Output>;
+    fn run_always(&mut self) -> io::Result<ExitStatus>;
+    fn output_always(&mut self) -> io::Result<Output>;
+    fn output_within(&mut self, timeout: Duration) -> io::Result<Option<Output>>;
+    fn run_logged(&mut self) -> io::Result<ExitStatus>;
+    fn run_logged_visible(&mut self) -> io::Result<ExitStatus>;
+}
+
+impl Run for Command {
+    fn run(&mut self) -> io::Result<ExitStatus> {
+        if dry_run() {
+            PLAN.lock().unwrap().push(PlannedCommand::from_command(self));
+            return Ok(success());
+        }
+        self.run_always()
+    }
+
+    fn run_output(&mut self) -> io::Result<Output> {
+        if dry_run() {
+            PLAN.lock().unwrap().push(PlannedCommand::from_command(self));
+            return Ok(Output { status: success(), stdout: vec![], stderr: vec![] });
+        }
+        self.output_always()
+    }
+
+    fn run_always(&mut self) -> io::Result<ExitStatus> {
+        echo(self);
+        let status = self.status()?;
+        exited(self, &status);
+        Ok(status)
+    }
+
+    fn output_always(&mut self) -> io::Result<Output> {
+        echo(self);
+        let output = self.output()?;
+        exited(self, &output.status);
+        trace!("{} wrote {} bytes to stdout", self.get_program().to_string_lossy(), output.stdout.len());
+        Ok(output)
+    }
+
+    // like output_always, but kills the command and returns None once the timeout passes; meant
+    // for commands that print a little, since output beyond the pipe buffer isn't read until exit
+    fn output_within(&mut self, timeout: Duration) -> io::Result<Option<Output>> {
+        echo(self);
+        let mut child = self.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+        let start = Instant::now();
+        while child.try_wait()?.is_none() {
+            if start.elapsed() >= timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                debug!("{} was killed after {:?}", self.get_program().to_string_lossy(), timeout);
+                return Ok(None);
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        let output = child.wait_with_output()?;
+        exited(self, &output.status);
+        Ok(Some(output))
+    }
+
+    // like run, but everything the command prints is copied into the build log as well; stdout
+    // only reaches the terminal in verbose mode, stderr always does. these are the build commands,
+    // so --sandbox, --clean-env and --nice apply to them. the stdio is set here, after the
+    // command is wrapped for the sandbox, so whatever was set on it before is ignored
+    fn run_logged(&mut self) -> io::Result<ExitStatus> {
+        logged(self, log::verbose())
+    }
+
+    // like run_logged, but stdout reaches the terminal without -v as well, for builds whose
+    // progress is only printed there
+    fn run_logged_visible(&mut self) -> io::Result<ExitStatus> {
+        logged(self, true)
+    }
+}
+
+fn logged(cmd: &mut Command, show_stdout: bool) -> io::Result<ExitStatus> {
+    let mut sandboxed = sandbox::wrap(cmd);
+    let cmd = sandboxed.as_mut().unwrap_or(cmd);
+    sanitize(cmd);
+    deprioritize(cmd);
+    tee(cmd, show_stdout)
+}
+
+fn tee(cmd: &mut Command, show_stdout: bool) -> io::Result<ExitStatus> {
+    if dry_run() || !build_log::active() {
+        return cmd.stdout(if show_stdout { Stdio::inherit() } else { Stdio::null() }).run();
+    }
+    echo(cmd);
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let copy = |mut from: Box<dyn Read + Send>, stream: Stream, to_terminal: Option<Box<dyn Write + Send>>| {
+        thread::spawn(move || {
+            let mut to_terminal = to_terminal;
+            let mut buffer = [0; 8192];
+            while let Ok(n @ 1..) = from.read(&mut buffer) {
+                build_log::append(stream, &buffer[..n]);
+                if let Some(terminal) = to_terminal.as_mut() {
+                    let _ = terminal.write_all(&buffer[..n]);
+                    let _ = terminal.flush();
+                }
+            }
+        })
+    };
+    let stdout: Option<Box<dyn Write + Send>> = if show_stdout { Some(Box::new(io::stdout())) } else { None };
+    let threads = [
+        child.stdout.take().map(|out| copy(Box::new(out), Stream::Stdout, stdout)),
+        child.stderr.take().map(|err| copy(Box::new(err), Stream::Stderr, Some(Box::new(io::stderr())))),
+    ];
+    let status = child.wait()?;
+    for thread in threads.into_iter().flatten() {
+        let _ = thread.join();
+    }
+    exited(cmd, &status);
+    Ok(status)
+}
+
+#[cfg(unix)]
+pub fn success() -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw(0)
+}
+
+#[cfg(windows)]
+pub fn success() -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(0)
+}
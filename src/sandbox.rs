@@ -0,0 +1,224 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use crate::paths;
+use crate::runner::Run;
+
+// the build being sandboxed; build commands are wrapped while it is set, see Run::run_logged
+static CURRENT: Mutex<Option<Options>> = Mutex::new(None);
+
+// tool caches builds download into; binaries like ~/.cargo/bin stay read-only
+const CACHE_DIRS: [&str; 7] = [
+    "~/.cache",
+    "~/.cargo/registry",
+    "~/.cargo/git",
+    "~/go/pkg/mod",
+    "~/.npm",
+    "~/.gradle/caches",
+    "~/.m2/repository",
+];
+// keys and credentials a build has no business reading
+const HIDDEN_DIRS: [&str; 2] = ["~/.ssh", "~/.gnupg"];
+
+// lowercase snippets of what tools print when the network is gone
+const NETWORK_ERRORS: [&str; 7] = [
+    "could not resolve host",
+    "temporary failure in name resolution",
+    "name or service not known",
+    "network is unreachable",
+    "failed to lookup address",
+    "getaddrinfo",
+    "dial tcp",
+];
+
+#[derive(Clone)]
+pub struct Options {
+    pub build_dir: PathBuf,
+    // bound read-write on top of the build directory
+    pub writable: Vec<PathBuf>,
+    // replaced by empty directories
+    pub hidden: Vec<PathBuf>,
+    pub no_network: bool,
+}
+
+impl Options {
+    // the cache and hidden directories that exist; bwrap can't mount over missing ones
+    pub fn new(build_dir: &Path, extra_writable: &[String], no_network: bool) -> Options {
+        Options {
+            build_dir: build_dir.to_path_buf(),
+            writable: existing(CACHE_DIRS.iter().copied().chain(extra_writable.iter().map(|dir| dir.as_str()))),
+            hidden: existing(HIDDEN_DIRS.iter().copied()),
+            no_network,
+        }
+    }
+}
+
+fn existing<'a>(dirs: impl Iterator<Item = &'a str>) -> Vec<PathBuf> {
+    dirs.map(paths::expand_tilde).filter(|dir| dir.is_dir()).collect()
+}
+
+// everything the bwrap command line gets before the build command: the whole filesystem
+// read-only, a private /tmp, /dev and /proc, then the build directory and caches read-write
+pub fn bwrap_args(options: &Options, cwd: &Path) -> Vec<OsString> {
+    let mut args: Vec<OsString> = ["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp"]
+        .iter()
+        .map(OsString::from)
+        .collect();
+    for dir in std::iter::once(&options.build_dir).chain(&options.writable) {
+        args.extend([OsString::from("--bind"), dir.into(), dir.into()]);
+    }
+    for dir in &options.hidden {
+        args.extend([OsString::from("--tmpfs"), dir.into()]);
+    }
+    args.extend(["--unshare-pid", "--die-with-parent"].map(OsString::from));
+    if options.no_network {
+        args.push("--unshare-net".into());
+    }
+    args.extend([OsString::from("--chdir"), cwd.into()]);
+    args
+}
+
+// None when bwrap is there and can set up a sandbox, else why not
+pub fn unavailable() -> Option<String> {
+    if !cfg!(target_os = "linux") {
+        return Some("--sandbox uses bubblewrap, which only runs on Linux".to_string());
+    }
+    let works = Command::new("bwrap")
+        .args(["--ro-bind", "/", "/", "--dev", "/dev", "true"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .run_always();
+    match works {
+        Err(_) => Some("--sandbox needs bubblewrap (bwrap), which could not be found in PATH".to_string()),
+        Ok(status) if !status.success() => Some(
+            "bwrap could not set up a sandbox; unprivileged user namespaces may be turned off on this system".to_string(),
+        ),
+        Ok(_) => None,
+    }
+}
+
+pub fn enter(options: Options) {
+    *CURRENT.lock().unwrap() = Some(options);
+}
+
+pub fn leave() {
+    *CURRENT.lock().unwrap() = None;
+}
+
+// the same command inside bwrap, with the environment it was given; None outside a sandboxed build.
+// the stdio set on `cmd` can't be read back, so it has to be set on what this returns
+pub fn wrap(cmd: &Command) -> Option<Command> {
+    let current = CURRENT.lock().unwrap();
+    let options = current.as_ref()?;
+    let cwd = cmd.get_current_dir().map_or_else(|| options.build_dir.clone(), |dir| dir.to_path_buf());
+    let mut wrapped = Command::new("bwrap");
+    wrapped.args(bwrap_args(options, &cwd)).arg(cmd.get_program()).args(cmd.get_args()).current_dir(&cwd);
+    for (key, value) in cmd.get_envs() {
+        match value {
+            Some(value) => wrapped.env(key, value),
+            None => wrapped.env_remove(key),
+        };
+    }
+    Some(wrapped)
+}
+
+// whether a failed build's output says it wanted the network that --sandbox-no-net took away
+pub fn wanted_network(output: &str) -> bool {
+    let sandboxed = CURRENT.lock().unwrap().as_ref().is_some_and(|options| options.no_network);
+    let output = output.to_lowercase();
+    sandboxed && NETWORK_ERRORS.iter().any(|error| output.contains(error))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+    use super::*;
+
+    fn options(no_network: bool) -> Options {
+        Options {
+            build_dir: PathBuf::from("/tmp/aurora/builds/foo"),
+            writable: vec![PathBuf::from("/home/me/.cargo/registry")],
+            hidden: vec![PathBuf::from("/home/me/.ssh")],
+            no_network,
+        }
+    }
+
+    fn argv(options: &Options, cwd: &str) -> Vec<String> {
+        bwrap_args(options, Path::new(cwd)).iter().map(|arg| arg.to_string_lossy().into_owned()).collect()
+    }
+
+    // the index of `window` in `args`, if it appears as a run of consecutive arguments
+    fn position(args: &[String], window: &[&str]) -> Option<usize> {
+        args.windows(window.len()).position(|w| w.iter().zip(window).all(|(a, b)| a == b))
+    }
+
+    #[test]
+    fn filesystem_is_read_only_with_build_dir_and_caches_writable() {
+        let args = argv(&options(false), "/tmp/aurora/builds/foo");
+        assert_eq!(&args[..3], ["--ro-bind", "/", "/"]);
+        let build = position(&args, &["--bind", "/tmp/aurora/builds/foo", "/tmp/aurora/builds/foo"]).unwrap();
+        let cache = position(&args, &["--bind", "/home/me/.cargo/registry", "/home/me/.cargo/registry"]).unwrap();
+        // later mounts win, so the writable binds have to come after the read-only root
+        assert!(build > 0 && cache > build);
+        assert!(position(&args, &["--tmpfs", "/tmp"]).is_some());
+        assert!(position(&args, &["--dev", "/dev"]).is_some());
+        assert!(position(&args, &["--proc", "/proc"]).is_some());
+    }
+
+    #[test]
+    fn hidden_dirs_are_replaced_by_empty_ones() {
+        let args = argv(&options(false), "/tmp/aurora/builds/foo");
+        let hidden = position(&args, &["--tmpfs", "/home/me/.ssh"]).unwrap();
+        assert!(hidden > position(&args, &["--ro-bind", "/", "/"]).unwrap());
+        assert!(position(&args, &["--bind", "/home/me/.ssh"]).is_none());
+    }
+
+    #[test]
+    fn network_is_only_cut_with_no_network() {
+        assert!(!argv(&options(false), "/").contains(&"--unshare-net".to_string()));
+        assert!(argv(&options(true), "/").contains(&"--unshare-net".to_string()));
+        for no_network in [false, true] {
+            let args = argv(&options(no_network), "/");
+            assert!(args.contains(&"--unshare-pid".to_string()));
+            assert!(args.contains(&"--die-with-parent".to_string()));
+        }
+    }
+
+    #[test]
+    fn ends_by_changing_into_the_working_directory() {
+        let args = argv(&options(true), "/tmp/aurora/builds/foo/build");
+        assert_eq!(&args[args.len() - 2..], ["--chdir", "/tmp/aurora/builds/foo/build"]);
+    }
+
+    #[test]
+    fn nothing_but_the_root_when_there_is_nothing_to_bind() {
+        let options = Options { build_dir: PathBuf::from("/b"), writable: vec![], hidden: vec![], no_network: false };
+        let args = argv(&options, "/b");
+        assert_eq!(
+            args,
+            [
+                "--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp", "--bind", "/b", "/b",
+                "--unshare-pid", "--die-with-parent", "--chdir", "/b"
+            ]
+        );
+    }
+
+    #[test]
+    fn wrapping_keeps_the_arguments_environment_and_directory() {
+        let mut cmd = Command::new("make");
+        cmd.args(["-j4", "all"]).env("CC", "clang").env_remove("MAKEFLAGS").current_dir("/b/src");
+        enter(Options { build_dir: PathBuf::from("/b"), writable: vec![], hidden: vec![], no_network: false });
+        let wrapped = wrap(&cmd).unwrap();
+        leave();
+        assert!(wrap(&cmd).is_none());
+
+        assert_eq!(wrapped.get_program(), "bwrap");
+        let args: Vec<_> = wrapped.get_args().collect();
+        assert_eq!(&args[args.len() - 5..], ["--chdir", "/b/src", "make", "-j4", "all"]);
+        assert_eq!(wrapped.get_current_dir(), Some(Path::new("/b/src")));
+        let envs: Vec<_> = wrapped.get_envs().collect();
+        assert!(envs.contains(&(OsStr::new("CC"), Some(OsStr::new("clang")))));
+        assert!(envs.contains(&(OsStr::new("MAKEFLAGS"), None)));
+    }
+}
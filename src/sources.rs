@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use ansi_term::Colour::Red;
+use crate::pkgbuild::SrcInfo;
+
+// one host a build downloads from, with everything about it worth a second look
+#[derive(Default)]
+pub struct Host {
+    pub count: usize,
+    pub plain_http: bool,
+    pub ip_address: bool,
+    pub skipped: bool,
+}
+
+impl Host {
+    fn flags(&self) -> Vec<&'static str> {
+        let mut flags = Vec::new();
+        if self.plain_http {
+            flags.push("plain http");
+        }
+        if self.ip_address {
+            flags.push("ip address");
+        }
+        if self.skipped {
+            flags.push("checksum SKIP");
+        }
+        flags
+    }
+
+    pub fn suspicious(&self) -> bool {
+        !self.flags().is_empty()
+    }
+}
+
+// the hosts a PKGBUILD's source=() array downloads from; files shipped in the AUR repository
+// have no host and aren't listed
+pub fn from_srcinfo(info: &SrcInfo) -> BTreeMap<String, Host> {
+    let mut hosts: BTreeMap<String, Host> = BTreeMap::new();
+    for (index, source) in info.sources.iter().enumerate() {
+        // "name::url" renames the download
+        let url = source.split_once("::").map_or(source.as_str(), |(_, url)| url);
+        let Some((scheme, _)) = url.split_once("://") else {
+            continue;
+        };
+        let Some(name) = host(url) else {
+            continue;
+        };
+        // "git+https://" picks the VCS, and VCS sources can't be checksummed
+        let vcs = scheme.contains('+') || ["git", "svn", "hg", "bzr", "fossil"].contains(&scheme);
+        let skipped = !vcs && info.checksums.values().any(|sums| sums.get(index).is_some_and(|sum| sum == "SKIP"));
+        add(&mut hosts, name, scheme, skipped);
+    }
+    hosts
+}
+
+// the hosts a repository's git submodules point at; relative submodule urls live next to the
+// repository and aren't listed
+pub fn submodules(dir: &Path) -> BTreeMap<String, Host> {
+    let mut hosts: BTreeMap<String, Host> = BTreeMap::new();
+    let content = fs::read_to_string(dir.join(".gitmodules")).unwrap_or_default();
+    for line in content.lines() {
+        let Some((key, url)) = line.split_once('=') else {
+            continue;
+        };
+        if key.trim() != "url" {
+            continue;
+        }
+        let url = url.trim();
+        if let Some(name) = host(url) {
+            let scheme = url.split_once("://").map_or("ssh", |(scheme, _)| scheme);
+            add(&mut hosts, name, scheme, false);
+        }
+    }
+    hosts
+}
+
+fn add(hosts: &mut BTreeMap<String, Host>, name: String, scheme: &str, skipped: bool) {
+    let ip_address = name.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>().is_ok();
+    let host = hosts.entry(name).or_default();
+    host.count += 1;
+    host.plain_http |= scheme == "http" || scheme.ends_with("+http");
+    host.ip_address |= ip_address;
+    host.skipped |= skipped;
+}
+
+// the lowercased host of "scheme://user@host:port/path" or scp-style "user@host:path"
+pub fn host(url: &str) -> Option<String> {
+    let authority = match url.split_once("://") {
+        Some((_, rest)) => rest.split(['/', '?', '#']).next().unwrap_or(rest),
+        // scp-style addresses have a colon before any slash
+        None => url.split_once(':').filter(|(host, _)| !host.is_empty() && !host.contains('/'))?.0,
+    };
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    let host = if authority.starts_with('[') {
+        // [::1]:8080
+        authority.split_once(']').map_or(authority.to_string(), |(host, _)| format!("{}]", host))
+    } else {
+        authority.split(':').next().unwrap_or(authority).to_string()
+    };
+    (!host.is_empty()).then(|| host.to_lowercase())
+}
+
+pub fn print(hosts: &BTreeMap<String, Host>) {
+    for (name, host) in hosts {
+        let flags = host.flags();
+        if flags.is_empty() {
+            println!("     {} ({})", name, host.count);
+        } else {
+            println!("     {} ({}) {}", Red.paint(name), host.count, Red.paint(flags.join(", ")));
+        }
+    }
+}
+
+// hosts that aren't allowed_domains or a subdomain of one, or --allow-domain on this run;
+// nothing is refused while allowed_domains is empty
+pub fn unknown<'a>(hosts: &'a BTreeMap<String, Host>, allowed: &[String], extra: &[String]) -> Vec<&'a str> {
+    if allowed.is_empty() {
+        return vec![];
+    }
+    hosts
+        .keys()
+        .filter(|name| {
+            !allowed.iter().chain(extra).any(|domain| {
+                let domain = domain.trim_start_matches('.').to_lowercase();
+                **name == domain || name.ends_with(&format!(".{}", domain))
+            })
+        })
+        .map(|name| name.as_str())
+        .collect()
+}
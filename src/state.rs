@@ -0,0 +1,261 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use ansi_term::Colour::{Red, Yellow};
+use serde::{Deserialize, Serialize};
+use crate::paths;
+use crate::utils;
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct State {
+    #[serde(default)]
+    pub packages: BTreeMap<String, InstallRecord>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct InstallRecord {
+    pub build_system: String,
+    pub files: Vec<PathBuf>,
+    // sha256 of each installed file, for `aurora verify`
+    #[serde(default)]
+    pub sha256: BTreeMap<PathBuf, String>,
+    pub installed_at: u64,
+    #[serde(default)]
+    pub conflicts: Vec<Conflict>,
+    // where the manifest came from, e.g. "local + repository"; None when detected
+    #[serde(default)]
+    pub recipe: Option<String>,
+    #[serde(default)]
+    pub profile: Option<String>,
+    // the first line the installed binary printed for --version
+    #[serde(default)]
+    pub version: Option<String>,
+    // the commit the package was built from
+    #[serde(default)]
+    pub commit: Option<String>,
+    // set by `aurora pin`; installs keep building this commit until it is unpinned
+    #[serde(default)]
+    pub pinned: Option<String>,
+    // false for packages that were only installed because something needed them
+    #[serde(default = "explicit")]
+    pub explicit: bool,
+    #[serde(default)]
+    pub required_by: Vec<String>,
+    // what `reproducible` set for the build, SOURCE_DATE_EPOCH and the flags that remap paths
+    #[serde(default)]
+    pub reproducible: BTreeMap<String, String>,
+}
+
+fn explicit() -> bool {
+    true
+}
+
+// earlier installs kept for `aurora downgrade`
+const KEPT_VERSIONS: usize = 3;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Conflict {
+    pub path: PathBuf,
+    pub package: String,
+}
+
+pub fn state_dir() -> PathBuf {
+    paths::data_dir()
+}
+
+// versions/<package>/<installed_at>/ holds record.json and the installed files as files/<index>
+pub fn versions_dir(package: &str) -> PathBuf {
+    state_dir().join("versions").join(package)
+}
+
+// copies an install aside before it is replaced; files that are gone or can't be read are skipped
+pub fn save_version(package: &str, record: &InstallRecord) {
+    let dir = versions_dir(package).join(record.installed_at.to_string());
+    if dir.exists() || fs::create_dir_all(dir.join("files")).is_err() {
+        return;
+    }
+    for (index, file) in record.files.iter().enumerate() {
+        if file.is_file() {
+            let _ = fs::copy(file, dir.join("files").join(index.to_string()));
+        }
+    }
+    let written = serde_json::to_string_pretty(record).is_ok_and(|content| fs::write(dir.join("record.json"), content).is_ok());
+    if !written {
+        let _ = fs::remove_dir_all(&dir);
+        return;
+    }
+
+    let mut saved = saved_versions(package);
+    while saved.len() > KEPT_VERSIONS {
+        let (oldest, _) = saved.remove(0);
+        let _ = fs::remove_dir_all(oldest);
+    }
+}
+
+// oldest first
+pub fn saved_versions(package: &str) -> Vec<(PathBuf, InstallRecord)> {
+    let mut versions: Vec<(PathBuf, InstallRecord)> = fs::read_dir(versions_dir(package))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter_map(|dir| {
+            let record = serde_json::from_str(&fs::read_to_string(dir.join("record.json")).ok()?).ok()?;
+            Some((dir, record))
+        })
+        .collect();
+    versions.sort_by_key(|(_, record)| record.installed_at);
+    versions
+}
+
+fn state_file() -> PathBuf {
+    state_dir().join("installed.json")
+}
+
+// installed.json.corrupt.<time>, next to it
+fn move_aside(path: &Path) -> io::Result<PathBuf> {
+    let mut moved = path.as_os_str().to_owned();
+    moved.push(format!(".corrupt.{}", utils::unix_now()));
+    let moved = PathBuf::from(moved);
+    fs::rename(path, &moved)?;
+    Ok(moved)
+}
+
+impl State {
+    pub fn load() -> State {
+        let path = state_file();
+        match State::read(&path) {
+            Ok(state) => state,
+            Err(e) => {
+                eprintln!("{} {}", Red.paint("Error:"), e);
+                if !utils::prompt("~> Move it aside and start with an empty install database? [y/N]").eq_ignore_ascii_case("y") {
+                    std::process::exit(1);
+                }
+                match move_aside(&path) {
+                    Ok(moved) => println!("{} Moved it to {}", Yellow.paint("~> Warning:"), moved.display()),
+                    Err(e) => {
+                        eprintln!("{} Could not move {} aside: {}", Red.paint("Error:"), path.display(), e);
+                        std::process::exit(1);
+                    }
+                }
+                State::default()
+            }
+        }
+    }
+
+    // a missing file is an empty database
+    fn read(path: &Path) -> Result<State, String> {
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| format!("The install database {} is corrupt: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(State::default()),
+            Err(e) => Err(format!("Could not read the install database {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = self.write(&state_file()) {
+            eprintln!("{} Could not write the install database {}: {}", Red.paint("Error:"), state_file().display(), e);
+            std::process::exit(1);
+        }
+    }
+
+    // through a temporary file, so a failed write leaves the old database
+    fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, content)?;
+        fs::rename(&tmp, path)
+    }
+
+    // `package` installed over a file of `owner`'s, which no longer owns it
+    pub fn hand_over(&mut self, path: &Path, owner: &str, package: &str) {
+        if let Some(record) = self.packages.get_mut(owner) {
+            record.files.retain(|file| file != path);
+            record.sha256.remove(path);
+            record.conflicts.push(Conflict { path: path.to_path_buf(), package: package.to_string() });
+        }
+    }
+
+    pub fn owner_of(&self, path: &Path) -> Option<&str> {
+        self.packages
+            .iter()
+            .find(|(_, record)| record.files.iter().any(|f| f == path))
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_missing_database_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(State::read(&dir.path().join("installed.json")).unwrap().packages.is_empty());
+    }
+
+    #[test]
+    fn a_corrupt_database_is_named_and_moved_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("installed.json");
+        fs::write(&path, "{\"packages\": {\"foo\": ").unwrap();
+        let e = State::read(&path).err().unwrap();
+        assert!(e.contains(&path.display().to_string()) && e.contains("corrupt"), "{}", e);
+
+        let moved = move_aside(&path).unwrap();
+        assert!(!path.exists());
+        assert_eq!(moved.parent(), Some(dir.path()));
+        assert!(moved.file_name().unwrap().to_string_lossy().starts_with("installed.json.corrupt."));
+        assert!(State::read(&path).unwrap().packages.is_empty());
+    }
+
+    fn record(files: &[&str]) -> InstallRecord {
+        InstallRecord {
+            build_system: "cargo".to_string(),
+            files: files.iter().map(PathBuf::from).collect(),
+            sha256: files.iter().map(|file| (PathBuf::from(file), "0".repeat(64))).collect(),
+            installed_at: 1,
+            conflicts: vec![],
+            recipe: None,
+            profile: None,
+            version: Some("foo 1.0".to_string()),
+            commit: None,
+            pinned: None,
+            explicit: true,
+            required_by: vec![],
+            reproducible: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn the_database_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data/installed.json");
+        let mut state = State::default();
+        state.packages.insert("foo".to_string(), record(&["/usr/local/bin/foo"]));
+        state.write(&path).unwrap();
+        assert!(!dir.path().join("data/installed.json.tmp").exists());
+        let read = State::read(&path).unwrap();
+        assert_eq!(read.owner_of(Path::new("/usr/local/bin/foo")), Some("foo"));
+        assert_eq!(read.packages["foo"].version.as_deref(), Some("foo 1.0"));
+    }
+
+    #[test]
+    fn overwritten_files_change_owner() {
+        let mut state = State::default();
+        state.packages.insert("bar".to_string(), record(&["/usr/local/bin/bar", "/usr/local/bin/tool"]));
+        state.packages.insert("foo".to_string(), record(&["/usr/local/bin/tool"]));
+        let tool = Path::new("/usr/local/bin/tool");
+        state.hand_over(tool, "bar", "foo");
+
+        let bar = &state.packages["bar"];
+        assert_eq!(bar.files, [PathBuf::from("/usr/local/bin/bar")]);
+        assert!(!bar.sha256.contains_key(tool));
+        assert_eq!(bar.conflicts.iter().map(|c| (c.path.as_path(), c.package.as_str())).collect::<Vec<_>>(), [(tool, "foo")]);
+        assert_eq!(state.owner_of(tool), Some("foo"));
+    }
+}
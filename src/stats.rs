@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::paths;
+use crate::utils;
+
+// durations kept per package and build system
+const KEPT: usize = 5;
+
+// how long earlier builds took, for estimates only
+#[derive(Serialize, Deserialize, Default)]
+struct Stats {
+    #[serde(default)]
+    builds: BTreeMap<String, BTreeMap<String, Vec<u64>>>,
+}
+
+fn stats_file() -> PathBuf {
+    paths::data_dir().join("stats.json")
+}
+
+fn load() -> Stats {
+    fs::read_to_string(stats_file())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn record(package: &str, build_system: &str, elapsed: Duration) {
+    let mut stats = load();
+    let durations = stats
+        .builds
+        .entry(package.to_string())
+        .or_default()
+        .entry(build_system.to_string())
+        .or_default();
+    durations.push(elapsed.as_secs());
+    if durations.len() > KEPT {
+        durations.drain(..durations.len() - KEPT);
+    }
+
+    // a lost estimate is not worth failing an install over
+    let dir = paths::data_dir();
+    let tmp = dir.join("stats.json.tmp");
+    let written = fs::create_dir_all(&dir).is_ok()
+        && serde_json::to_string_pretty(&stats).is_ok_and(|content| fs::write(&tmp, content).is_ok());
+    if written {
+        let _ = fs::rename(&tmp, stats_file());
+    }
+}
+
+// the median of the recorded builds
+pub fn estimate(package: &str, build_system: &str) -> Option<Duration> {
+    let mut durations = load().builds.get(package)?.get(build_system)?.clone();
+    if durations.is_empty() {
+        return None;
+    }
+    durations.sort_unstable();
+    Some(Duration::from_secs(durations[durations.len() / 2]))
+}
+
+// " (last time: 7m 32s)" for the build banner, or nothing for a first build
+pub fn banner_note(estimate: Option<Duration>) -> String {
+    estimate
+        .map(|e| format!(" (last time: {})", utils::format_duration(e)))
+        .unwrap_or_default()
+}
+
+// a line counting down to the estimate while the build runs with its output hidden
+pub struct Eta {
+    done: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Eta {
+    pub fn start(estimate: Option<Duration>) -> Option<Eta> {
+        let estimate = estimate?;
+        if !io::stdout().is_terminal() {
+            return None;
+        }
+        let done = Arc::new(AtomicBool::new(false));
+        let stop = done.clone();
+        let thread = thread::spawn(move || {
+            let start = Instant::now();
+            while !stop.load(Ordering::Relaxed) {
+                let elapsed = start.elapsed();
+                let left = match estimate.checked_sub(elapsed) {
+                    Some(left) => format!("about {} left", utils::format_duration(left)),
+                    None => "taking longer than last time".to_string(),
+                };
+                print!("\r\x1b[2K~> {} elapsed, {}", utils::format_duration(elapsed), left);
+                let _ = io::stdout().flush();
+                thread::sleep(Duration::from_millis(500));
+            }
+            print!("\r\x1b[2K");
+            let _ = io::stdout().flush();
+        });
+        Some(Eta { done, thread: Some(thread) })
+    }
+}
+
+impl Drop for Eta {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
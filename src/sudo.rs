@@ -0,0 +1,60 @@
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+use crate::config::Config;
+use crate::runner::{self, Run};
+use crate::utils;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+static STOPPED: AtomicBool = AtomicBool::new(false);
+
+// sudo -v up front, so the privileged steps at the end of a long build don't sit at a password
+// prompt; with `keep_sudo_alive = true` the timestamp is then refreshed until aurora exits
+pub fn authenticate() -> bool {
+    if runner::dry_run() || utils::is_root() || !utils::command_exists("sudo") {
+        return true;
+    }
+    println!("~> Authenticating now so the install doesn't wait for a password later");
+    let authenticated = Command::new("sudo").arg("-v").run_always().is_ok_and(|s| s.success());
+    if authenticated {
+        keep_alive();
+    }
+    authenticated
+}
+
+// does nothing unless sudo (not doas or run0) is the privilege command; the thread ends with the
+// process, so Ctrl-C needs no cleanup of its own
+pub fn keep_alive() {
+    if !Config::load().keep_sudo_alive || runner::dry_run() || utils::is_root() || !utils::command_exists("sudo") {
+        return;
+    }
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    thread::spawn(|| loop {
+        for _ in 0..REFRESH_INTERVAL.as_secs() {
+            thread::sleep(Duration::from_secs(1));
+            if STOPPED.load(Ordering::SeqCst) {
+                return;
+            }
+        }
+        // -n never prompts; once the timestamp is gone there's nothing left to keep alive
+        let refreshed = Command::new("sudo")
+            .args(["-n", "-v"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .run_always()
+            .is_ok_and(|s| s.success());
+        if !refreshed {
+            return;
+        }
+    });
+}
+
+pub fn stop() {
+    STOPPED.store(true, Ordering::SeqCst);
+}
@@ -0,0 +1,94 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::paths;
+use crate::sha256;
+use crate::utils;
+
+// what was reviewed and accepted
+#[derive(Serialize, Deserialize, Default)]
+struct Trust {
+    // keyed by package base, so split packages share the review
+    #[serde(default)]
+    reviewed: BTreeMap<String, Reviewed>,
+    // by package name; build as with --yes while the reviewed files are unchanged
+    #[serde(default)]
+    always: BTreeSet<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Reviewed {
+    sha256: String,
+    reviewed_at: u64,
+}
+
+pub fn trust_file() -> PathBuf {
+    paths::data_dir().join("trust.json")
+}
+
+fn load() -> Trust {
+    fs::read_to_string(trust_file())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save(trust: &Trust) {
+    // losing the cache only means reviewing again
+    let dir = paths::data_dir();
+    let tmp = dir.join("trust.json.tmp");
+    let written = fs::create_dir_all(&dir).is_ok()
+        && serde_json::to_string_pretty(trust).is_ok_and(|content| fs::write(&tmp, content).is_ok());
+    if written {
+        let _ = fs::rename(&tmp, trust_file());
+    }
+}
+
+// one hash over the names and contents of the files a review shows, and the other lines it
+// lists; files that don't exist count too, so adding one changes the hash
+pub fn fingerprint(files: &[&Path], lines: &[String]) -> String {
+    let mut data = Vec::new();
+    for file in files {
+        data.extend(file.file_name().unwrap_or_default().as_encoded_bytes());
+        let content = fs::read(file).unwrap_or_default();
+        data.extend(format!("\0{}\0", content.len()).as_bytes());
+        data.extend(content);
+    }
+    for line in lines {
+        data.extend(format!("{}\n", line).as_bytes());
+    }
+    sha256::bytes(&data)
+}
+
+// when the package base was last reviewed, if the files are the same as they were then
+pub fn reviewed_at(base: &str, fingerprint: &str) -> Option<u64> {
+    load().reviewed.get(base).filter(|r| r.sha256 == fingerprint).map(|r| r.reviewed_at)
+}
+
+pub fn always(package: &str) -> bool {
+    load().always.contains(package)
+}
+
+pub fn always_trusted() -> Vec<String> {
+    load().always.into_iter().collect()
+}
+
+// whether anything changed
+pub fn set_always(package: &str, always: bool) -> bool {
+    let mut trust = load();
+    let changed = match always {
+        true => trust.always.insert(package.to_string()),
+        false => trust.always.remove(package),
+    };
+    if changed {
+        save(&trust);
+    }
+    changed
+}
+
+pub fn remember(base: &str, fingerprint: &str) {
+    let mut trust = load();
+    trust.reviewed.insert(base.to_string(), Reviewed { sha256: fingerprint.to_string(), reviewed_at: utils::unix_now() });
+    save(&trust);
+}
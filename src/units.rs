@@ -0,0 +1,175 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use ansi_term::Colour::Yellow;
+use tracing::debug;
+use crate::paths;
+use crate::runner::Run;
+use crate::utils;
+
+// nobody keeps their units in these, and they can be huge
+const SKIPPED_DIRS: [&str; 4] = [".git", "target", "node_modules", "vendor"];
+const MAX_DEPTH: usize = 6;
+
+// where systemd looks for a user's own units
+pub fn user_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| paths::home_dir().join(".config"))
+        .join("systemd")
+        .join("user")
+}
+
+fn is_unit(name: &str) -> bool {
+    name.ends_with(".service") || name.ends_with(".timer")
+}
+
+// the units the recipe lists, or else the .service and .timer files in a directory called
+// "user" (systemd/user, contrib/systemd/user...), since units anywhere else tend to be system
+// units; a staged copy wins over the one in the sources, it may have had its paths filled in
+pub fn find(build_dir: &Path, listed: &[String]) -> Vec<PathBuf> {
+    if !listed.is_empty() {
+        return listed.iter().map(|unit| build_dir.join(unit)).collect();
+    }
+    let mut found = Vec::new();
+    let mut pending = vec![(build_dir.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Ok(kind) = entry.file_type() else {
+                continue;
+            };
+            if kind.is_dir() {
+                if depth < MAX_DEPTH && !SKIPPED_DIRS.contains(&name.as_str()) {
+                    pending.push((entry.path(), depth + 1));
+                }
+            } else if is_unit(&name) && dir.file_name().is_some_and(|d| d == "user") {
+                found.push(entry.path());
+            }
+        }
+    }
+    let staging = build_dir.join("aurora-staging");
+    found.sort_by_key(|path| (!path.starts_with(&staging), path.clone()));
+    let mut names = Vec::new();
+    found.retain(|path| {
+        let name = path.file_name().map(|n| n.to_os_string());
+        let new = !names.contains(&name);
+        names.push(name);
+        new
+    });
+    found
+}
+
+// copies the units into the user unit directory with ExecStart= and friends pointing at the
+// installed binaries, and returns the files written; `owned` are the units the package's last
+// install wrote, which may be replaced
+pub fn install(package: &str, units: &[PathBuf], binaries: &[PathBuf], owned: &[PathBuf]) -> Vec<PathBuf> {
+    if units.is_empty() {
+        return vec![];
+    }
+    if !utils::command_exists("systemctl") {
+        println!("~> {} ships systemd user units, but systemctl was not found; skipping them", package);
+        return vec![];
+    }
+    let dir = user_dir();
+    if let Err(e) = fs::create_dir_all(&dir) {
+        println!("{} Could not create {}: {}", Yellow.paint("~> Warning:"), dir.display(), e);
+        return vec![];
+    }
+
+    let mut written = Vec::new();
+    for unit in units {
+        let Some(name) = unit.file_name() else {
+            continue;
+        };
+        let content = match fs::read_to_string(unit) {
+            Ok(content) => content,
+            Err(e) => {
+                println!("{} Could not read the unit {}: {}", Yellow.paint("~> Warning:"), unit.display(), e);
+                continue;
+            }
+        };
+        let target = dir.join(name);
+        if target.exists() && !owned.contains(&target) {
+            println!(
+                "{} {} already exists and wasn't installed by {}, leaving it alone",
+                Yellow.paint("~> Warning:"),
+                target.display(),
+                package
+            );
+            continue;
+        }
+        match fs::write(&target, rewrite_exec(&content, binaries)) {
+            Ok(()) => written.push(target),
+            Err(e) => println!("{} Could not write {}: {}", Yellow.paint("~> Warning:"), target.display(), e),
+        }
+    }
+    if written.is_empty() {
+        return written;
+    }
+
+    daemon_reload();
+    let names: Vec<String> = written.iter().filter_map(|u| u.file_name()).map(|n| n.to_string_lossy().into_owned()).collect();
+    println!("~> Installed user units to {}: {}", dir.display(), names.join(", "));
+    // a timer starts its service, so the service itself isn't enabled
+    let enable: Vec<String> = names
+        .iter()
+        .filter(|name| match name.strip_suffix(".service") {
+            Some(stem) => !names.contains(&format!("{}.timer", stem)),
+            None => true,
+        })
+        .map(|name| name.replacen("@.", "@<instance>.", 1))
+        .collect();
+    println!("~> To start them: systemctl --user enable --now {}", enable.join(" "));
+    written
+}
+
+// stops and disables units a package no longer ships, then deletes them
+pub fn remove(units: &[PathBuf]) {
+    if units.is_empty() {
+        return;
+    }
+    let names: Vec<String> = units.iter().filter_map(|u| u.file_name()).map(|n| n.to_string_lossy().into_owned()).collect();
+    println!("~> Disabling and removing user units that are no longer shipped: {}", names.join(", "));
+    let _ = Command::new("systemctl").args(["--user", "disable", "--now"]).args(&names).run();
+    for unit in units {
+        if let Err(e) = fs::remove_file(unit) {
+            debug!("removing {} failed: {}", unit.display(), e);
+        }
+    }
+    daemon_reload();
+}
+
+fn daemon_reload() {
+    let reloaded = Command::new("systemctl").args(["--user", "daemon-reload"]).run();
+    if !reloaded.is_ok_and(|s| s.success()) {
+        println!("{} systemctl --user daemon-reload failed; run it before starting the units", Yellow.paint("~> Warning:"));
+    }
+}
+
+// ExecStart=/usr/bin/foo serve becomes ExecStart=/home/me/.local/bin/foo serve when foo was
+// installed; the prefixes systemd allows before the path (@, -, :, +, !) stay
+fn rewrite_exec(content: &str, binaries: &[PathBuf]) -> String {
+    let mut rewritten = String::with_capacity(content.len());
+    for line in content.lines() {
+        let exec = line.split_once('=').filter(|(key, _)| key.trim().starts_with("Exec"));
+        let Some((key, value)) = exec else {
+            rewritten.push_str(line);
+            rewritten.push('\n');
+            continue;
+        };
+        let command = value.trim_start().trim_start_matches(['@', '-', ':', '+', '!']);
+        let flags = &value[..value.len() - command.len()];
+        let end = command.find(char::is_whitespace).unwrap_or(command.len());
+        let program = Path::new(&command[..end]);
+        let installed = binaries.iter().find(|binary| program.file_name().is_some_and(|name| binary.file_name() == Some(name)));
+        match installed {
+            Some(binary) => rewritten.push_str(&format!("{}={}{}{}", key, flags, binary.display(), &command[end..])),
+            None => rewritten.push_str(line),
+        }
+        rewritten.push('\n');
+    }
+    rewritten
+}
@@ -1,27 +1,630 @@
-use std::path::Path;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use ansi_term::Colour::Red;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use ansi_term::Colour::{Red, Yellow};
+use crate::runner::Run;
 
 pub fn command_exists(command: &str) -> bool {
-    if let Ok(path) = std::env::var("PATH") {
-        for p in path.split(':') {
-            let full_path = Path::new(p).join(command);
-            if full_path.exists() {
-                return true;
+    find_command(command).is_some()
+}
+
+// the full path `command` resolves to through PATH, like `command -v`
+pub fn find_command(command: &str) -> Option<PathBuf> {
+    find_command_in(command, &std::env::var_os("PATH")?)
+}
+
+pub fn find_command_in(command: &str, path: &OsStr) -> Option<PathBuf> {
+    if command.is_empty() {
+        return None;
+    }
+    // a name with a slash is not looked up in PATH at all
+    if command.contains('/') || (cfg!(windows) && command.contains('\\')) {
+        let path = PathBuf::from(command);
+        return is_runnable(&path).then_some(path);
+    }
+
+    for dir in std::env::split_paths(path) {
+        // an empty entry is the current directory, a leading ~ is left unexpanded by some shells
+        let dir = if dir.as_os_str().is_empty() {
+            PathBuf::from(".")
+        } else if let Ok(rest) = dir.strip_prefix("~") {
+            match dirs::home_dir() {
+                Some(home) => home.join(rest),
+                None => continue,
             }
+        } else {
+            dir
+        };
+
+        if let Some(found) = executable_candidates(command)
+            .iter()
+            .map(|c| dir.join(c))
+            .find(|c| is_runnable(c))
+        {
+            return Some(found);
         }
     }
-    false
+    None
+}
+
+#[cfg(unix)]
+fn is_runnable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path)
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(windows)]
+fn is_runnable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(windows)]
+fn executable_candidates(command: &str) -> Vec<String> {
+    let pathext = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+    let mut candidates = vec![command.to_string()];
+    candidates.extend(
+        pathext
+            .split(';')
+            .filter(|ext| !ext.is_empty())
+            .map(|ext| format!("{}{}", command, ext.to_ascii_lowercase())),
+    );
+    candidates
+}
+
+#[cfg(not(windows))]
+fn executable_candidates(command: &str) -> Vec<String> {
+    vec![command.to_string()]
+}
+
+pub fn git_clone(url: &str, dir: &Path) -> bool {
+    Command::new("git")
+        .arg("clone")
+        .arg("--depth=1")
+        .arg(url)
+        .arg(dir)
+        .stdout(std::process::Stdio::null())
+        .run_always()
+        .expect("Git command failed")
+        .success()
+}
+
+pub fn git_head(dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("rev-parse")
+        .arg("HEAD")
+        .output_always()
+        .ok()?;
+    let commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !commit.is_empty()).then_some(commit)
+}
+
+// when the last commit was made, for SOURCE_DATE_EPOCH
+pub fn git_commit_time(dir: &Path) -> Option<u64> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["log", "-1", "--format=%ct"])
+        .output_always()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+pub fn short_commit(commit: &str) -> &str {
+    commit.get(..7).unwrap_or(commit)
+}
+
+// `uname -m`, with the names rust uses for the same machines as a fallback
+pub fn host_arch() -> String {
+    let uname = Command::new("uname")
+        .arg("-m")
+        .output_always()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|arch| !arch.is_empty());
+    uname.unwrap_or_else(|| std::env::consts::ARCH.to_string())
+}
+
+// the cpu part of a triple or arch name, spelled the way uname does
+pub fn normalize_arch(arch: &str) -> &str {
+    match arch.split('-').next().unwrap_or(arch) {
+        "amd64" | "x64" => "x86_64",
+        "arm64" => "aarch64",
+        "i386" | "i486" | "i586" | "i686" | "x86" => "i686",
+        other => other,
+    }
 }
 
 pub fn get_privilege_command() -> String {
-    if command_exists("sudo") {
-        "sudo".to_string()
-    } else if command_exists("doas") {
-        "doas".to_string()
+    for command in ["sudo", "doas", "run0"] {
+        if command_exists(command) {
+            return command.to_string();
+        }
+    }
+
+    eprintln!("{} Neither sudo, doas nor run0 found! Install one to continue.",
+              Red.paint("Error:"));
+    std::process::exit(1);
+}
+
+#[cfg(unix)]
+pub fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    let executable_bit = fs::metadata(path)
+        .map(|m| m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false);
+    if !executable_bit {
+        return false;
+    }
+
+    let mut magic = [0u8; 4];
+    let read = fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok();
+    if !read {
+        return false;
+    }
+
+    // ELF, the Mach-O variants (32/64 bit, both byte orders, universal) and scripts
+    matches!(
+        magic,
+        [0x7f, b'E', b'L', b'F']
+            | [0xfe, 0xed, 0xfa, 0xce]
+            | [0xfe, 0xed, 0xfa, 0xcf]
+            | [0xce, 0xfa, 0xed, 0xfe]
+            | [0xcf, 0xfa, 0xed, 0xfe]
+            | [0xca, 0xfe, 0xba, 0xbe]
+            | [b'#', b'!', _, _]
+    )
+}
+
+#[cfg(windows)]
+pub fn is_executable(path: &Path) -> bool {
+    let mut magic = [0u8; 2];
+    let is_pe = fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .map(|_| magic == *b"MZ")
+        .unwrap_or(false);
+    let is_script = path
+        .extension()
+        .map(|e| e.eq_ignore_ascii_case("bat") || e.eq_ignore_ascii_case("cmd"))
+        .unwrap_or(false);
+    is_pe || is_script
+}
+
+#[cfg(unix)]
+pub fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(windows)]
+pub fn is_root() -> bool {
+    false
+}
+
+pub fn page_file(path: &Path) {
+    let paged = command_exists("less")
+        && Command::new("less")
+            .arg(path)
+            .status()
+            .map(|s| s.success())
+            .unwrap_or(false);
+
+    if !paged {
+        match fs::read_to_string(path) {
+            Ok(content) => println!("{}", content),
+            Err(e) => eprintln!("{} Failed to read {}: {}", Red.paint("Error:"), path.display(), e),
+        }
+    }
+}
+
+// an interactive $SHELL (cmd on windows) in `dir`; returns when it exits
+pub fn shell(dir: &Path, env: &BTreeMap<String, String>) {
+    let shell = if cfg!(windows) {
+        std::env::var("COMSPEC").unwrap_or_else(|_| "cmd.exe".to_string())
     } else {
-        eprintln!("{} Neither sudo nor doas found! Install one to continue.", 
-                  Red.paint("Error:"));
-        std::process::exit(1);
+        std::env::var("SHELL").ok().filter(|s| !s.is_empty()).unwrap_or_else(|| "sh".to_string())
+    };
+    if let Err(e) = Command::new(&shell).envs(env).current_dir(dir).status() {
+        eprintln!("{} Could not start {}: {}", Red.paint("Error:"), shell, e);
+    }
+}
+
+pub fn parallel_jobs() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+static PROMPT_TIMEOUT: Mutex<Option<Duration>> = Mutex::new(None);
+// the questions that took their default because nobody answered
+static TIMED_OUT: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub fn set_prompt_timeout(secs: Option<u64>) {
+    *PROMPT_TIMEOUT.lock().unwrap() = secs.map(Duration::from_secs);
+}
+
+pub fn timed_out_prompts() -> Vec<String> {
+    TIMED_OUT.lock().unwrap().clone()
+}
+
+// with --prompt-timeout, an unanswered question in a terminal gets an empty answer, which every
+// prompt reads as its default
+pub fn prompt(question: &str) -> String {
+    print!("{} ", question);
+    io::stdout().flush().unwrap();
+    let timeout = *PROMPT_TIMEOUT.lock().unwrap();
+    if let Some(timeout) = timeout.filter(|_| io::stdin().is_terminal()) {
+        if !input_within(timeout) {
+            println!();
+            println!("{} No answer after {}s, taking the default", Yellow.paint("~>"), timeout.as_secs());
+            TIMED_OUT.lock().unwrap().push(question.trim_start_matches("~> ").to_string());
+            return String::new();
+        }
+    }
+    read_answer()
+}
+
+// for the review, whose default is to build what nobody looked at; that is what --skip-review
+// is for
+pub fn prompt_untimed(question: &str) -> String {
+    print!("{} ", question);
+    io::stdout().flush().unwrap();
+    read_answer()
+}
+
+fn read_answer() -> String {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    input.trim().to_string()
+}
+
+// a terminal in canonical mode only turns readable once a whole line was typed, so the read
+// that follows doesn't block; a failed poll (EINTR) just waits as before
+#[cfg(unix)]
+fn input_within(timeout: Duration) -> bool {
+    let mut stdin = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+    let millis = timeout.as_millis().min(i32::MAX as u128) as i32;
+    unsafe { libc::poll(&mut stdin, 1, millis) != 0 }
+}
+
+// console handles can't be polled like this; prompts wait for an answer as before
+#[cfg(windows)]
+fn input_within(_timeout: Duration) -> bool {
+    true
+}
+
+// the 1-minute load average
+#[cfg(unix)]
+pub fn load_average() -> Option<f64> {
+    let mut load = [0.0];
+    (unsafe { libc::getloadavg(load.as_mut_ptr(), 1) } == 1).then_some(load[0])
+}
+
+#[cfg(windows)]
+pub fn load_average() -> Option<f64> {
+    None
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+
+    // civil-from-days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02} UTC", year, month, day, rem / 3600, (rem % 3600) / 60)
+}
+
+pub fn format_duration(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+// levenshtein distance, counted in chars
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1).min(row[j] + 1).min(diagonal + (ca != *cb) as usize);
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+pub fn snapshot_tree(dir: &Path) -> BTreeMap<PathBuf, SystemTime> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(current) = pending.pop() {
+        let entries = match fs::read_dir(&current) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let meta = match fs::symlink_metadata(&path) {
+                Ok(m) => m,
+                Err(_) => continue,
+            };
+            if meta.is_dir() {
+                pending.push(path);
+            } else if let Ok(modified) = meta.modified() {
+                files.insert(path, modified);
+            }
+        }
+    }
+    files
+}
+
+pub fn changed_files(dir: &Path, before: &BTreeMap<PathBuf, SystemTime>) -> Vec<PathBuf> {
+    snapshot_tree(dir)
+        .into_iter()
+        .filter(|(path, modified)| before.get(path) != Some(modified))
+        .map(|(path, _)| path)
+        .collect()
+}
+
+pub fn copy_tree(src: &Path, dst: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut copied = Vec::new();
+    fs::create_dir_all(dst)?;
+
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let from = entry.path();
+        let to = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copied.extend(copy_tree(&from, &to)?);
+        } else {
+            fs::copy(&from, &to)?;
+            copied.push(to);
+        }
+    }
+    Ok(copied)
+}
+
+pub fn install_tree(src: &Path, dst: &Path, privileged: bool) -> Option<Vec<PathBuf>> {
+    if !privileged {
+        return match copy_tree(src, dst) {
+            Ok(copied) => Some(copied),
+            Err(e) => {
+                eprintln!("{} Failed to copy {}: {}", Red.paint("Error:"), src.display(), e);
+                None
+            }
+        };
+    }
+
+    let privilege = get_privilege_command();
+    let run = |cmd: &mut Command| cmd.run().map(|s| s.success()).unwrap_or(false);
+    let ok = run(Command::new(&privilege).arg("mkdir").arg("-p").arg(dst))
+        && run(Command::new(&privilege).arg("cp").arg("-R").arg(src.join(".")).arg(dst));
+    if !ok {
+        eprintln!("{} Failed to copy {} into {}", Red.paint("Error:"), src.display(), dst.display());
+        return None;
+    }
+
+    Some(
+        snapshot_tree(src)
+            .into_keys()
+            .filter_map(|p| p.strip_prefix(src).ok().map(|rel| dst.join(rel)))
+            .collect(),
+    )
+}
+
+pub fn install_symlink(target: &Path, link: &Path, privileged: bool) -> bool {
+    if privileged {
+        return Command::new(get_privilege_command())
+            .arg("ln")
+            .arg("-sf")
+            .arg(target)
+            .arg(link)
+            .run()
+            .map(|s| s.success())
+            .unwrap_or(false);
+    }
+
+    let _ = fs::remove_file(link);
+    #[cfg(unix)]
+    let result = std::os::unix::fs::symlink(target, link);
+    #[cfg(windows)]
+    let result = fs::copy(target, link).map(|_| ());
+
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("{} Failed to link {}: {}", Red.paint("Error:"), link.display(), e);
+            false
+        }
+    }
+}
+
+pub fn make_executable(path: &Path) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(path, fs::Permissions::from_mode(0o755));
+    }
+    #[cfg(windows)]
+    let _ = path;
+}
+
+// the program a script's #! line runs, looking through `/usr/bin/env`
+pub fn shebang_interpreter(path: &Path) -> Option<String> {
+    let mut head = [0u8; 256];
+    let read = fs::File::open(path).and_then(|mut f| f.read(&mut head)).ok()?;
+    let line = String::from_utf8_lossy(&head[..read]);
+    let line = line.strip_prefix("#!")?.lines().next()?;
+
+    let mut parts = line.split_whitespace();
+    let program = parts.next()?;
+    let name = program.rsplit('/').next()?;
+    if name == "env" {
+        parts.find(|p| !p.starts_with('-')).map(|p| p.to_string())
+    } else {
+        Some(name.to_string())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+    use super::*;
+
+    // a directory holding an executable `tool`, a non-executable `plain` and a directory `dir`
+    fn bin_dir(parent: &Path) -> PathBuf {
+        let bin = parent.join("bin");
+        fs::create_dir_all(bin.join("dir")).unwrap();
+        for (name, mode) in [("tool", 0o755), ("plain", 0o644)] {
+            fs::write(bin.join(name), "#!/bin/sh\n").unwrap();
+            fs::set_permissions(bin.join(name), fs::Permissions::from_mode(mode)).unwrap();
+        }
+        fs::set_permissions(bin.join("dir"), fs::Permissions::from_mode(0o755)).unwrap();
+        bin
+    }
+
+    fn path(dirs: &[&Path]) -> std::ffi::OsString {
+        std::env::join_paths(dirs).unwrap()
+    }
+
+    #[test]
+    fn only_executable_files_are_found() {
+        let temp = tempfile::tempdir().unwrap();
+        let bin = bin_dir(temp.path());
+        let path = path(&[&temp.path().join("missing"), &bin]);
+        assert_eq!(find_command_in("tool", &path), Some(bin.join("tool")));
+        assert_eq!(find_command_in("plain", &path), None);
+        assert_eq!(find_command_in("dir", &path), None);
+        assert_eq!(find_command_in("", &path), None);
+    }
+
+    #[test]
+    fn the_first_entry_wins() {
+        let (first, second) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let (first, second) = (bin_dir(first.path()), bin_dir(second.path()));
+        assert_eq!(find_command_in("tool", &path(&[&first, &second])), Some(first.join("tool")));
+        assert_eq!(find_command_in("tool", &path(&[&second, &first])), Some(second.join("tool")));
+    }
+
+    #[test]
+    fn empty_and_odd_entries_are_skipped() {
+        let temp = tempfile::tempdir().unwrap();
+        let bin = bin_dir(temp.path());
+        let mut path = std::ffi::OsString::from("::/nonexistent:");
+        path.push(bin.as_os_str());
+        assert_eq!(find_command_in("tool", &path), Some(bin.join("tool")));
+        assert_eq!(find_command_in("tool", OsStr::new("")), None);
+    }
+
+    #[test]
+    fn a_leading_tilde_is_the_home_directory() {
+        let Some(home) = dirs::home_dir().filter(|home| home.is_dir()) else {
+            return;
+        };
+        let temp = tempfile::tempdir_in(&home).unwrap();
+        let bin = bin_dir(temp.path());
+        let path = Path::new("~").join(bin.strip_prefix(&home).unwrap());
+        assert_eq!(find_command_in("tool", path.as_os_str()), Some(bin.join("tool")));
+    }
+
+    #[test]
+    fn paths_with_a_slash_are_not_looked_up() {
+        let temp = tempfile::tempdir().unwrap();
+        let bin = bin_dir(temp.path());
+        let tool = bin.join("tool");
+        assert_eq!(find_command_in(tool.to_str().unwrap(), OsStr::new("")), Some(tool));
+        assert_eq!(find_command_in(bin.join("plain").to_str().unwrap(), &path(&[&bin])), None);
+        assert_eq!(find_command_in("bin/tool", &path(&[temp.path()])), None);
+    }
+
+    #[test]
+    fn executables_are_recognized_by_their_magic() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = |name: &str, content: &[u8], mode: u32| {
+            let path = temp.path().join(name);
+            fs::write(&path, content).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+            path
+        };
+        let binaries: [(&str, &[u8]); 7] = [
+            ("elf", b"\x7fELF\x02\x01\x01"),
+            ("macho32", b"\xfe\xed\xfa\xce\x00"),
+            ("macho64", b"\xfe\xed\xfa\xcf\x00"),
+            ("macho32-le", b"\xce\xfa\xed\xfe\x00"),
+            ("macho64-le", b"\xcf\xfa\xed\xfe\x00"),
+            ("universal", b"\xca\xfe\xba\xbe\x00"),
+            ("script", b"#!/bin/sh\n"),
+        ];
+        for (name, magic) in binaries {
+            assert!(is_executable(&file(name, magic, 0o755)), "{}", name);
+            assert!(!is_executable(&file(&format!("{}.noexec", name), magic, 0o644)), "{} without the executable bit", name);
+        }
+        assert!(!is_executable(&file("data", b"PK\x03\x04", 0o755)));
+        assert!(!is_executable(&file("short", b"#", 0o755)));
+        assert!(!is_executable(&temp.path().join("missing")));
+        assert!(!is_executable(temp.path()));
+    }
+
+    #[test]
+    fn escripts_name_their_interpreter() {
+        let temp = tempfile::tempdir().unwrap();
+        let interpreter = |head: &str| {
+            let path = temp.path().join("script");
+            fs::write(&path, head).unwrap();
+            shebang_interpreter(&path)
+        };
+        assert_eq!(interpreter("#!/usr/bin/env escript\n%%! -smp enable\n").as_deref(), Some("escript"));
+        assert_eq!(interpreter("#!/usr/local/bin/escript\n").as_deref(), Some("escript"));
+        assert_eq!(interpreter("#!/usr/bin/env -S escript -c\n").as_deref(), Some("escript"));
+        assert_eq!(interpreter("#! /bin/sh\n").as_deref(), Some("sh"));
+        assert_eq!(interpreter("\x7fELF"), None);
+        assert_eq!(interpreter("#!\n"), None);
     }
 }
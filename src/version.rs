@@ -0,0 +1,190 @@
+use std::cmp::Ordering;
+
+// pacman's vercmp: [epoch:]version[-release], where a missing release matches any release
+pub fn vercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    let (a_epoch, a_version, a_release) = parse_evr(a);
+    let (b_epoch, b_version, b_release) = parse_evr(b);
+
+    rpmvercmp(a_epoch, b_epoch)
+        .then_with(|| rpmvercmp(a_version, b_version))
+        .then_with(|| match (a_release, b_release) {
+            (Some(a), Some(b)) => rpmvercmp(a, b),
+            _ => Ordering::Equal,
+        })
+}
+
+fn parse_evr(evr: &str) -> (&str, &str, Option<&str>) {
+    let digits = evr.bytes().take_while(|b| b.is_ascii_digit()).count();
+    let (epoch, rest) = match evr[digits..].strip_prefix(':') {
+        Some(rest) => (if digits == 0 { "0" } else { &evr[..digits] }, rest),
+        None => ("0", evr),
+    };
+    match rest.rsplit_once('-') {
+        Some((version, release)) => (epoch, version, Some(release)),
+        None => (epoch, rest, None),
+    }
+}
+
+// compares alternating runs of digits and letters; separators only matter by their length
+fn rpmvercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut one, mut two) = (0, 0);
+
+    while one < a.len() && two < b.len() {
+        let (start1, start2) = (one, two);
+        while one < a.len() && !a[one].is_ascii_alphanumeric() {
+            one += 1;
+        }
+        while two < b.len() && !b[two].is_ascii_alphanumeric() {
+            two += 1;
+        }
+        if one == a.len() || two == b.len() {
+            break;
+        }
+        if one - start1 != two - start2 {
+            return (one - start1).cmp(&(two - start2));
+        }
+
+        let numeric = a[one].is_ascii_digit();
+        let run = |s: &[u8], from: usize| {
+            let len = s[from..]
+                .iter()
+                .take_while(|c| if numeric { c.is_ascii_digit() } else { c.is_ascii_alphabetic() })
+                .count();
+            from + len
+        };
+        let (end1, end2) = (run(a, one), run(b, two));
+
+        // a number is newer than letters
+        if end2 == two {
+            return if numeric { Ordering::Greater } else { Ordering::Less };
+        }
+
+        let (mut seg1, mut seg2) = (&a[one..end1], &b[two..end2]);
+        if numeric {
+            while seg1.len() > 1 && seg1[0] == b'0' {
+                seg1 = &seg1[1..];
+            }
+            while seg2.len() > 1 && seg2[0] == b'0' {
+                seg2 = &seg2[1..];
+            }
+            if seg1.len() != seg2.len() {
+                return seg1.len().cmp(&seg2.len());
+            }
+        }
+        match seg1.cmp(seg2) {
+            Ordering::Equal => {}
+            other => return other,
+        }
+        one = end1;
+        two = end2;
+    }
+
+    let (rest1, rest2) = (&a[one..], &b[two..]);
+    if rest1.is_empty() && rest2.is_empty() {
+        return Ordering::Equal;
+    }
+    // "1.0a" is older than "1.0", which is older than "1.0.1"
+    let alpha = |s: &[u8]| s.first().is_some_and(|c| c.is_ascii_alphabetic());
+    if (rest1.is_empty() && !alpha(rest2)) || alpha(rest1) {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    }
+}
+
+// "foo>=1.2" is the package "foo" with the constraint ">=" "1.2"
+pub fn parse_dependency(spec: &str) -> (&str, Option<(&str, &str)>) {
+    match spec.find(['<', '>', '=']) {
+        Some(i) => {
+            let (name, rest) = spec.split_at(i);
+            let op_len = rest.bytes().take_while(|b| b"<>=".contains(b)).count();
+            (name, Some((&rest[..op_len], &rest[op_len..])))
+        }
+        None => (spec, None),
+    }
+}
+
+pub fn satisfies(version: &str, op: &str, wanted: &str) -> bool {
+    let ordering = vercmp(version, wanted);
+    match op {
+        "=" => ordering == Ordering::Equal,
+        ">=" => ordering != Ordering::Less,
+        "<=" => ordering != Ordering::Greater,
+        ">" => ordering == Ordering::Greater,
+        "<" => ordering == Ordering::Less,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_older(a: &str, b: &str) {
+        assert_eq!(vercmp(a, b), Ordering::Less, "{} should be older than {}", a, b);
+        assert_eq!(vercmp(b, a), Ordering::Greater, "{} should be newer than {}", b, a);
+    }
+
+    #[test]
+    fn letters_sort_before_the_release_and_further_numbers_after() {
+        assert_older("1.0a", "1.0");
+        assert_older("1.0", "1.0.1");
+        assert_older("1.0a", "1.0.1");
+        assert_older("1.0alpha", "1.0beta");
+        assert_older("1.0rc1", "1.0");
+        assert_older("1.0.a", "1.0.1");
+        assert_older("1.0", "1.0.0");
+    }
+
+    #[test]
+    fn epochs_win_over_versions() {
+        assert_older("2.0", "1:1.0");
+        assert_older("1:2.0", "2:0.1");
+        assert_eq!(vercmp("0:1.0", "1.0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn a_missing_release_matches_any() {
+        assert_eq!(vercmp("1.0-1", "1.0"), Ordering::Equal);
+        assert_eq!(vercmp("1.0", "1.0-7"), Ordering::Equal);
+        assert_older("1.0-1", "1.0-2");
+        assert_older("1.0-9", "1.1");
+    }
+
+    #[test]
+    fn leading_zeros_and_separators_are_ignored() {
+        assert_eq!(vercmp("1.01", "1.1"), Ordering::Equal);
+        assert_eq!(vercmp("1.010", "1.10"), Ordering::Equal);
+        assert_older("1.001", "1.2");
+        assert_older("1.9", "1.10");
+        assert_eq!(vercmp("1.0_1", "1.0.1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn dependencies_split_into_name_and_constraint() {
+        assert_eq!(parse_dependency("foo"), ("foo", None));
+        assert_eq!(parse_dependency("foo>=1.2"), ("foo", Some((">=", "1.2"))));
+        assert_eq!(parse_dependency("foo<2"), ("foo", Some(("<", "2"))));
+        assert_eq!(parse_dependency("foo=1:2.0-1"), ("foo", Some(("=", "1:2.0-1"))));
+        assert_eq!(parse_dependency("lib32-foo<=3"), ("lib32-foo", Some(("<=", "3"))));
+    }
+
+    #[test]
+    fn constraints() {
+        assert!(satisfies("1.2-1", ">=", "1.2"));
+        assert!(satisfies("2.0-3", "=", "2.0"));
+        assert!(satisfies("1.0a", "<", "1.0"));
+        assert!(satisfies("1:0.1", ">", "9.9"));
+        assert!(satisfies("1.0", "<=", "1.0"));
+        assert!(!satisfies("1.2", "<", "1.2"));
+        assert!(!satisfies("1.10", "<", "1.9"));
+        assert!(!satisfies("1.0", "~", "1.0"));
+    }
+}